@@ -2,28 +2,44 @@
 //!
 //! Connects to the game server via Lightyear and renders entities.
 
+mod token;
+
+use std::fs::OpenOptions;
+use std::io::{self, Write};
 use std::net::{Ipv4Addr, SocketAddr};
+use std::path::Path;
 
 use anyhow::Result;
 use bevy::prelude::*;
-use lightyear::netcode::Key;
+use bevy::tasks::futures::check_ready;
+use bevy::tasks::{IoTaskPool, Task};
+use lightyear::netcode::ConnectToken;
+use lightyear::prelude::client::input::InputSystems;
 use lightyear::prelude::client::{
     ClientPlugins, Connect as LightyearConnect, NetcodeClient, NetcodeConfig,
 };
+use lightyear::prelude::input::native::{ActionState, InputMarker};
 use lightyear::prelude::{Authentication, LocalAddr, PeerAddr, UdpIo, *};
 use roguebench_protocol::prelude::*;
 
+use token::TokenError;
+
 /// Server address to connect to.
-const SERVER_ADDR: SocketAddr = SocketAddr::new(
-    std::net::IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
-    5000,
-);
+const SERVER_ADDR: SocketAddr =
+    SocketAddr::new(std::net::IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 5000);
+
+/// Address of the server's HTTP endpoint handing out connect tokens.
+const TOKEN_ADDR: SocketAddr =
+    SocketAddr::new(std::net::IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+
+/// Environment variable holding the player's secret, to play as another player.
+const PLAYER_VAR: &str = "ROGUEBENCH_PLAYER";
+
+/// File the player's secret is kept in, to keep playing as them across launches.
+const SECRET_FILE: &str = "player.secret";
 
 /// Client local address (use any available port).
-const CLIENT_ADDR: SocketAddr = SocketAddr::new(
-    std::net::IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)),
-    0,
-);
+const CLIENT_ADDR: SocketAddr = SocketAddr::new(std::net::IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), 0);
 
 // ============================================================================
 // UI
@@ -32,6 +48,13 @@ const CLIENT_ADDR: SocketAddr = SocketAddr::new(
 #[derive(Component)]
 struct EntityLabel;
 
+/// Marker drawn in front of an entity, pointing where it faces.
+#[derive(Component)]
+struct FacingMarker;
+
+/// Distance of the facing marker from the entity's centre.
+const FACING_MARKER_DISTANCE: f32 = 14.0;
+
 fn setup_camera(mut commands: Commands) {
     commands.spawn(Camera2d);
 }
@@ -52,18 +75,329 @@ fn spawn_labels_for_entities(
                     ..default()
                 },
                 TextColor(Color::WHITE),
+                Transform::from_xyz(0.0, 24.0, 1.0),
+            ));
+        });
+    }
+}
+
+/// Definition a replicated entity was spawned from, as synced to this client.
+#[derive(Component, Debug)]
+struct Template(EntityDef);
+
+/// Resolve the template of each entity once both it and the definitions
+/// have arrived, and again whenever either changes.
+fn resolve_templates(
+    mut commands: Commands,
+    definitions: Res<Definitions>,
+    entities: Query<(Entity, Ref<TemplateId>)>,
+) {
+    for (entity, template_id) in entities.iter() {
+        if !definitions.is_changed() && !template_id.is_changed() {
+            continue;
+        }
+        match template_id.resolve(&definitions) {
+            Some(def) => commands.entity(entity).insert(Template(def)),
+            None => commands.entity(entity).remove::<Template>(),
+        };
+    }
+}
+
+/// Show each entity's health out of its template's under its name.
+#[allow(clippy::type_complexity)]
+fn update_entity_labels(
+    entities: Query<
+        (&EntityName, &Health, &Template, &Children),
+        Or<(Changed<Health>, Changed<Template>)>,
+    >,
+    mut labels: Query<&mut Text2d, With<EntityLabel>>,
+) {
+    for (name, health, Template(def), children) in entities.iter() {
+        let mut texts = labels.iter_many_mut(children);
+        while let Some(mut text) = texts.fetch_next() {
+            text.0 = format!("{} {}/{}", name.0, health.0, def.health);
+        }
+    }
+}
+
+/// Give entities with a replicated position something to draw at it.
+fn spawn_entity_bodies(trigger: On<Add, Position>, mut commands: Commands) {
+    commands
+        .entity(trigger.entity)
+        .insert((
+            Sprite::from_color(Color::srgb(0.8, 0.3, 0.3), Vec2::splat(16.0)),
+            Transform::default(),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                FacingMarker,
+                Sprite::from_color(Color::WHITE, Vec2::splat(4.0)),
+                Transform::from_xyz(FACING_MARKER_DISTANCE, 0.0, 1.0),
             ));
         });
+}
+
+fn tile_color(kind: TileKind) -> Color {
+    match kind {
+        TileKind::Floor => Color::srgb(0.25, 0.25, 0.3),
+        TileKind::Wall => Color::srgb(0.55, 0.5, 0.45),
+        TileKind::Pit => Color::BLACK,
+        TileKind::Hazard => Color::srgb(0.7, 0.2, 0.1),
+    }
+}
+
+fn spawn_room_tiles(
+    mut commands: Commands,
+    new_rooms: Query<(Entity, &RoomLayout), Added<RoomLayout>>,
+    mut cameras: Query<&mut Transform, With<Camera2d>>,
+) {
+    for (entity, layout) in new_rooms.iter() {
+        tracing::info!("Received room layout {}x{}", layout.width, layout.height);
+
+        commands
+            .entity(entity)
+            .insert((Transform::default(), Visibility::default()))
+            .with_children(|parent| {
+                for y in 0..layout.height {
+                    for x in 0..layout.width {
+                        let Some(kind) = layout.tile(x, y) else {
+                            continue;
+                        };
+                        parent.spawn((
+                            Sprite::from_color(tile_color(kind), Vec2::splat(TILE_SIZE)),
+                            Transform::from_translation(RoomLayout::tile_center(x, y).extend(-1.0)),
+                        ));
+                    }
+                }
+            });
+
+        // Keep the room centred on screen
+        let center = Vec2::new(layout.width as f32, layout.height as f32) * TILE_SIZE / 2.0;
+        for mut transform in cameras.iter_mut() {
+            transform.translation = center.extend(transform.translation.z);
+        }
+    }
+}
+
+#[derive(Component)]
+struct WaveCounter;
+
+fn setup_wave_counter(mut commands: Commands) {
+    commands.spawn((
+        WaveCounter,
+        Text::new(""),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.0),
+            left: Val::Px(8.0),
+            ..default()
+        },
+    ));
+}
+
+fn update_wave_counter(
+    progress: Query<&WaveProgress, Changed<WaveProgress>>,
+    mut counters: Query<&mut Text, With<WaveCounter>>,
+) {
+    for progress in progress.iter() {
+        let label = match progress.phase {
+            EncounterPhase::Cleared => "Encounter cleared".to_string(),
+            _ => format!(
+                "Wave {}/{} - {} left",
+                progress.wave + 1,
+                progress.total_waves,
+                progress.remaining
+            ),
+        };
+        for mut text in counters.iter_mut() {
+            text.0 = label.clone();
+        }
+    }
+}
+
+/// Seconds a projectile has been in flight on this client.
+#[derive(Component)]
+struct ProjectileAge(f32);
+
+fn spawn_projectile_sprites(
+    mut commands: Commands,
+    new_projectiles: Query<(Entity, &Projectile), Added<Projectile>>,
+) {
+    for (entity, projectile) in new_projectiles.iter() {
+        commands.entity(entity).insert((
+            ProjectileAge(0.0),
+            Sprite::from_color(Color::srgb(1.0, 0.9, 0.4), Vec2::splat(6.0)),
+            Transform::from_translation(projectile.origin.extend(1.0)),
+        ));
+    }
+}
+
+fn move_projectiles(
+    time: Res<Time>,
+    mut projectiles: Query<(&Projectile, &mut ProjectileAge, &mut Transform)>,
+) {
+    // The server only sends where a projectile started; extrapolate the rest
+    for (projectile, mut age, mut transform) in projectiles.iter_mut() {
+        age.0 += time.delta_secs();
+        let position = projectile.position_after(age.0);
+        transform.translation = position.extend(transform.translation.z);
+    }
+}
+
+/// Icon for one status effect on an entity, counting down on its own.
+#[derive(Component)]
+struct StatusIcon {
+    remaining: f32,
+}
+
+fn update_status_icons(
+    mut commands: Commands,
+    changed: Query<(Entity, &StatusEffects, Option<&Children>), Changed<StatusEffects>>,
+    icons: Query<(), With<StatusIcon>>,
+) {
+    for (entity, statuses, children) in changed.iter() {
+        for child in children.into_iter().flatten() {
+            if icons.contains(*child) {
+                commands.entity(*child).despawn();
+            }
+        }
+        commands.entity(entity).with_children(|parent| {
+            for (index, status) in statuses.0.iter().enumerate() {
+                // No names or art on the client yet, so each effect gets its own hue
+                let hue = status.effect.as_bytes()[0] as f32 / 255.0 * 360.0;
+                let size = 8.0 + 2.0 * status.stacks.min(4) as f32;
+                parent.spawn((
+                    StatusIcon {
+                        remaining: status.remaining,
+                    },
+                    Sprite::from_color(Color::hsl(hue, 0.8, 0.5), Vec2::splat(size)),
+                    Transform::from_xyz(index as f32 * 14.0, -28.0, 1.0),
+                ));
+            }
+        });
     }
 }
 
-fn update_entity_positions(
-    mut labels: Query<&mut Transform, With<EntityLabel>>,
+fn tick_status_icons(
+    mut commands: Commands,
     time: Res<Time>,
+    mut icons: Query<(Entity, &mut StatusIcon)>,
 ) {
-    // Simple animation to show entities are alive
-    for mut transform in labels.iter_mut() {
-        transform.translation.y = (time.elapsed_secs() * 2.0).sin() * 5.0;
+    // The server only sends remaining time when effects change
+    for (entity, mut icon) in icons.iter_mut() {
+        icon.remaining -= time.delta_secs();
+        if icon.remaining <= 0.0 {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+fn update_entity_positions(mut entities: Query<(&Position, &mut Transform), Changed<Position>>) {
+    // Remote entities are interpolated, so this moves smoothly between server updates
+    for (position, mut transform) in entities.iter_mut() {
+        transform.translation = position.0.extend(transform.translation.z);
+    }
+}
+
+fn update_facing_markers(
+    entities: Query<(&Facing, &Children), Changed<Facing>>,
+    mut markers: Query<&mut Transform, With<FacingMarker>>,
+) {
+    for (facing, children) in entities.iter() {
+        let mut iter = markers.iter_many_mut(children);
+        while let Some(mut transform) = iter.fetch_next() {
+            let offset = Vec2::from_angle(facing.0) * FACING_MARKER_DISTANCE;
+            transform.translation = offset.extend(transform.translation.z);
+        }
+    }
+}
+
+// ============================================================================
+// Input
+// ============================================================================
+
+/// -1, 0 or 1 depending on which of two opposing sets of keys are held.
+fn key_axis(keys: &ButtonInput<KeyCode>, negative: [KeyCode; 2], positive: [KeyCode; 2]) -> f32 {
+    let held = |codes: [KeyCode; 2]| keys.any_pressed(codes) as i8 as f32;
+    held(positive) - held(negative)
+}
+
+/// Send the player entity this client controls its input.
+fn take_control(
+    trigger: On<Add, (Predicted, Controlled)>,
+    mut commands: Commands,
+    players: Query<(), (With<Predicted>, With<Controlled>)>,
+) {
+    if players.contains(trigger.entity) {
+        commands
+            .entity(trigger.entity)
+            .insert(InputMarker::<PlayerInput>::default());
+    }
+}
+
+/// Read the keyboard and mouse into the input of the controlled player.
+///
+/// Nothing is read until the client has synced its input timeline with the
+/// server, as earlier input would never be sent.
+fn write_player_input(
+    synced: Query<(), With<IsSynced<InputTimeline>>>,
+    keys: Option<Res<ButtonInput<KeyCode>>>,
+    mouse: Option<Res<ButtonInput<MouseButton>>>,
+    windows: Query<&Window>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    mut players: Query<(&mut ActionState<PlayerInput>, &Position), With<InputMarker<PlayerInput>>>,
+) {
+    if synced.is_empty() {
+        return;
+    }
+    let movement = keys.as_deref().map_or(Vec2::ZERO, |keys| {
+        Vec2::new(
+            key_axis(
+                keys,
+                [KeyCode::KeyA, KeyCode::ArrowLeft],
+                [KeyCode::KeyD, KeyCode::ArrowRight],
+            ),
+            key_axis(
+                keys,
+                [KeyCode::KeyS, KeyCode::ArrowDown],
+                [KeyCode::KeyW, KeyCode::ArrowUp],
+            ),
+        )
+    });
+    let attack = mouse
+        .as_deref()
+        .is_some_and(|mouse| mouse.pressed(MouseButton::Left));
+    let cursor = windows
+        .iter()
+        .find_map(Window::cursor_position)
+        .and_then(|cursor| {
+            cameras
+                .iter()
+                .find_map(|(camera, transform)| camera.viewport_to_world_2d(transform, cursor).ok())
+        });
+
+    for (mut input, position) in players.iter_mut() {
+        // Keep aiming the same way while the cursor is outside the window
+        let aim = cursor.map_or(input.aim, |cursor| cursor - position.0);
+        input.0 = PlayerInput {
+            movement,
+            aim,
+            attack,
+        };
+    }
+}
+
+/// Move the controlled player ahead of the server, which moves it the same way.
+///
+/// Lightyear rolls back and replays this when the server disagrees.
+fn predict_player_movement(
+    time: Res<Time>,
+    mut players: Query<(&ActionState<PlayerInput>, &mut Position), With<Predicted>>,
+) {
+    let dt = time.delta_secs();
+    for (input, mut position) in players.iter_mut() {
+        let at = input.walk(position.0, PLAYER_SPEED, dt);
+        position.set_if_neq(Position(at));
     }
 }
 
@@ -71,14 +405,62 @@ fn update_entity_positions(
 // Networking
 // ============================================================================
 
-fn spawn_client(mut commands: Commands) {
-    tracing::info!("Connecting to server at {}", SERVER_ADDR);
+/// Where and how the client connects.
+#[derive(Resource, Debug, Clone)]
+struct ConnectionSettings {
+    server_addr: SocketAddr,
+    /// Address of the HTTP endpoint handing out connect tokens.
+    token_addr: SocketAddr,
+    /// Secret proving who the player is.
+    secret: PlayerSecret,
+    /// Simulated latency and loss on packets from the server.
+    conditioner: Option<LinkConditionerConfig>,
+}
+
+/// Connect token being fetched in the background.
+#[derive(Resource)]
+struct TokenTask(Task<Result<ConnectToken, TokenError>>);
 
-    let auth = Authentication::Manual {
-        server_addr: SERVER_ADDR,
-        client_id: rand::random(),
-        private_key: Key::default(),
-        protocol_id: 0,
+/// Start fetching a connect token, without holding up the frames meanwhile.
+fn request_connect_token(
+    mut commands: Commands,
+    settings: Res<ConnectionSettings>,
+    protocol: Res<ProtocolHash>,
+) {
+    tracing::info!(
+        "Connecting to server at {} (protocol {})",
+        settings.server_addr,
+        *protocol
+    );
+
+    let addr = settings.token_addr;
+    let request = TokenRequest {
+        secret: settings.secret.clone(),
+        protocol: *protocol,
+    };
+    let task = IoTaskPool::get().spawn(async move { token::request_token(addr, &request) });
+    commands.insert_resource(TokenTask(task));
+}
+
+/// Connect to the server once the connect token has arrived.
+fn spawn_client(
+    mut commands: Commands,
+    settings: Res<ConnectionSettings>,
+    task: Option<ResMut<TokenTask>>,
+) {
+    let Some(result) = task.and_then(|mut task| check_ready(&mut task.0)) else {
+        return;
+    };
+    commands.remove_resource::<TokenTask>();
+
+    let server_addr = settings.server_addr;
+    let auth = match result {
+        Ok(token) => Authentication::Token(token),
+        Err(e) => {
+            tracing::error!("Can't join the server: {}", e);
+            commands.insert_resource(ConnectionProblem::from(e));
+            return;
+        }
     };
 
     let netcode_client = NetcodeClient::new(auth, NetcodeConfig::default())
@@ -88,9 +470,11 @@ fn spawn_client(mut commands: Commands) {
         .spawn((
             netcode_client,
             LocalAddr(CLIENT_ADDR),
-            PeerAddr(SERVER_ADDR),
+            PeerAddr(server_addr),
             UdpIo::default(),
+            Link::new(settings.conditioner.clone().map(RecvLinkConditioner::new)),
             ReplicationReceiver::default(),
+            PredictionManager::default(),
         ))
         .id();
 
@@ -101,10 +485,201 @@ fn log_connection_status(trigger: On<Add, Link>) {
     tracing::info!("Connected to server: {:?}", trigger.entity);
 }
 
+/// Why the client couldn't join the server.
+#[derive(Resource, Debug)]
+enum ConnectionProblem {
+    /// The server speaks another protocol.
+    VersionMismatch(VersionMismatch),
+    /// No connect token could be had.
+    NoToken(String),
+}
+
+impl From<TokenError> for ConnectionProblem {
+    fn from(e: TokenError) -> Self {
+        match e {
+            TokenError::Mismatch(mismatch) => Self::VersionMismatch(mismatch),
+            TokenError::Unavailable(reason) => Self::NoToken(reason),
+        }
+    }
+}
+
+impl ConnectionProblem {
+    /// What to tell the player.
+    fn message(&self) -> String {
+        match self {
+            Self::VersionMismatch(mismatch) => format!(
+                "Version mismatch: this client can't play on the server. \
+                 Update the game and try again.\n({mismatch})"
+            ),
+            Self::NoToken(reason) => format!("Couldn't reach the server.\n({reason})"),
+        }
+    }
+}
+
+/// Stop connecting once the server says it speaks another protocol.
+///
+/// Runs on raw packets, before netcode discards the server's answer as garbage.
+/// Only links still connecting are checked: packets aren't tied to the server's
+/// address, so anyone could otherwise disconnect a playing client with one.
+#[allow(clippy::type_complexity)]
+fn detect_version_mismatch(
+    mut commands: Commands,
+    mut clients: Query<(Entity, &mut Link), (With<NetcodeClient>, Without<Connected>)>,
+) {
+    for (client, mut link) in clients.iter_mut() {
+        let packets: Vec<_> = link.recv.drain().collect();
+        for packet in packets {
+            match VersionMismatch::from_packet(&packet) {
+                Some(mismatch) => {
+                    tracing::error!("Server turned us away: {}", mismatch);
+                    commands.insert_resource(ConnectionProblem::VersionMismatch(mismatch));
+                    commands.trigger(Disconnect { entity: client });
+                }
+                None => link.recv.push_raw(packet),
+            }
+        }
+    }
+}
+
+#[derive(Component)]
+struct ConnectionErrorText;
+
+fn setup_connection_error(mut commands: Commands) {
+    commands.spawn((
+        ConnectionErrorText,
+        Text::new(""),
+        TextColor(Color::srgb(1.0, 0.4, 0.4)),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(8.0),
+            left: Val::Px(8.0),
+            ..default()
+        },
+    ));
+}
+
+fn show_connection_error(
+    problem: Option<Res<ConnectionProblem>>,
+    mut texts: Query<&mut Text, With<ConnectionErrorText>>,
+) {
+    let Some(problem) = problem.filter(|problem| problem.is_changed()) else {
+        return;
+    };
+    for mut text in texts.iter_mut() {
+        text.0 = problem.message();
+    }
+}
+
+// ============================================================================
+// Definitions
+// ============================================================================
+
+/// Keep the local copy of the server's definitions up to date, asking for
+/// all of them again if it falls behind.
+fn sync_definitions(
+    mut definitions: ResMut<Definitions>,
+    mut clients: Query<(
+        &mut MessageReceiver<DefinitionSync>,
+        &mut MessageSender<RequestDefinitions>,
+    )>,
+    mut awaiting_snapshot: Local<bool>,
+) {
+    for (mut receiver, mut sender) in clients.iter_mut() {
+        for sync in receiver.receive() {
+            let snapshot = matches!(sync, DefinitionSync::Snapshot { .. });
+            match definitions.apply(sync) {
+                Ok(()) => *awaiting_snapshot &= !snapshot,
+                Err(stale) if !*awaiting_snapshot => {
+                    tracing::warn!("Asking for all definitions again: {}", stale);
+                    sender.send::<ReliableChannel>(RequestDefinitions);
+                    *awaiting_snapshot = true;
+                }
+                Err(_) => {}
+            }
+        }
+    }
+}
+
+// ============================================================================
+// Gameplay events
+// ============================================================================
+
+/// Trigger each gameplay event message from the server as a Bevy event, for
+/// effects to observe.
+fn trigger_gameplay_events<M>(mut commands: Commands, mut receivers: Query<&mut MessageReceiver<M>>)
+where
+    M: Event,
+    for<'a> M::Trigger<'a>: Default,
+{
+    for mut receiver in receivers.iter_mut() {
+        for event in receiver.receive() {
+            commands.trigger(event);
+        }
+    }
+}
+
 // ============================================================================
 // Main
 // ============================================================================
 
+/// Client networking, gameplay presentation and UI systems.
+///
+/// Expects `ClientPlugins` and `ProtocolPlugin` to have been added first.
+struct GamePlugin;
+
+impl Plugin for GamePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Definitions>()
+            .add_systems(Startup, setup_camera)
+            .add_systems(Startup, request_connect_token)
+            .add_systems(Update, spawn_client)
+            .add_systems(Startup, setup_wave_counter)
+            .add_systems(Startup, setup_connection_error)
+            .add_systems(
+                PreUpdate,
+                detect_version_mismatch
+                    .after(LinkSystems::Receive)
+                    .before(ConnectionSystems::Receive),
+            )
+            .add_systems(Update, show_connection_error)
+            .add_systems(Update, sync_definitions)
+            .add_systems(
+                Update,
+                (
+                    trigger_gameplay_events::<HitLanded>,
+                    trigger_gameplay_events::<EntityDied>,
+                    trigger_gameplay_events::<ItemCollected>,
+                    trigger_gameplay_events::<WaveComplete>,
+                ),
+            )
+            .add_systems(Update, spawn_labels_for_entities)
+            .add_systems(
+                Update,
+                (resolve_templates, update_entity_labels)
+                    .chain()
+                    .after(sync_definitions)
+                    .after(spawn_labels_for_entities),
+            )
+            .add_systems(Update, spawn_room_tiles)
+            .add_systems(Update, update_wave_counter)
+            .add_systems(Update, (spawn_projectile_sprites, move_projectiles).chain())
+            .add_systems(Update, (update_status_icons, tick_status_icons).chain())
+            .add_systems(
+                Update,
+                (update_entity_positions, update_facing_markers)
+                    .after(InterpolationSystems::Interpolate),
+            )
+            .add_systems(
+                FixedPreUpdate,
+                write_player_input.in_set(InputSystems::WriteClientInputs),
+            )
+            .add_systems(FixedUpdate, predict_player_movement)
+            .add_observer(spawn_entity_bodies)
+            .add_observer(take_control)
+            .add_observer(log_connection_status);
+    }
+}
+
 fn main() -> Result<()> {
     tracing_subscriber::fmt()
         .with_env_filter("roguebench_client=info,lightyear=warn")
@@ -112,6 +687,19 @@ fn main() -> Result<()> {
 
     tracing::info!("Starting client...");
 
+    // Play as the player whose secret was given or saved, or as a new one
+    let secret = match std::env::var(PLAYER_VAR) {
+        Ok(secret) => PlayerSecret(secret),
+        Err(_) => load_or_create_secret(Path::new(SECRET_FILE)).unwrap_or_else(|e| {
+            tracing::warn!(
+                "Can't keep the player secret in {}, playing as a new player: {}",
+                SECRET_FILE,
+                e
+            );
+            PlayerSecret::generate()
+        }),
+    };
+
     App::new()
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
@@ -125,12 +713,701 @@ fn main() -> Result<()> {
             tick_duration: tick_duration(),
         })
         .add_plugins(ProtocolPlugin)
-        .add_systems(Startup, setup_camera)
-        .add_systems(Startup, spawn_client)
-        .add_systems(Update, spawn_labels_for_entities)
-        .add_systems(Update, update_entity_positions)
-        .add_observer(log_connection_status)
+        .insert_resource(ConnectionSettings {
+            server_addr: SERVER_ADDR,
+            token_addr: TOKEN_ADDR,
+            secret,
+            conditioner: None,
+        })
+        .add_plugins(GamePlugin)
         .run();
 
     Ok(())
 }
+
+/// Read the player's secret from `path`, or make a new player and save their
+/// secret there, readable only by the current user.
+fn load_or_create_secret(path: &Path) -> io::Result<PlayerSecret> {
+    match std::fs::read_to_string(path) {
+        Ok(secret) => return Ok(PlayerSecret(secret.trim().to_string())),
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        Err(_) => {}
+    }
+
+    let secret = PlayerSecret::generate();
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(secret.0.as_bytes())?;
+    tracing::info!(
+        "Playing as a new player; their secret is saved in {}",
+        path.display()
+    );
+    Ok(secret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+    use std::net::{TcpListener, UdpSocket};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    use lightyear::prelude::server::{ClientOf, ServerPlugins};
+    use roguebench_editor::prelude::*;
+    use roguebench_engine::prelude::*;
+    use roguebench_engine::{
+        CELL_SIZE, DamageDealt, FEEDBACK_RADIUS, PlayerId, ReloadContent, Storage, VIEW_DISTANCE,
+    };
+    use roguebench_storage::{ContentStoreExt, MemoryStore};
+    use tokio::sync::mpsc::UnboundedSender;
+
+    /// A local address with a free UDP port.
+    fn free_addr() -> SocketAddr {
+        UdpSocket::bind((Ipv4Addr::LOCALHOST, 0))
+            .unwrap()
+            .local_addr()
+            .unwrap()
+    }
+
+    /// Serve connect tokens on a free local port from a background thread.
+    fn serve_tokens(issuer: TokenIssuer, tx: UnboundedSender<EditorMessage>) -> SocketAddr {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        listener.set_nonblocking(true).unwrap();
+        std::thread::spawn(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(async move {
+                let listener = tokio::net::TcpListener::from_std(listener).unwrap();
+                axum::serve(listener, token_router(issuer, tx))
+                    .await
+                    .unwrap();
+            });
+        });
+        addr
+    }
+
+    /// A game server on `addr`, and the address of its token endpoint.
+    fn server_app(addr: SocketAddr) -> (App, SocketAddr) {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let engine = EnginePlugin::new(Arc::new(MemoryStore::new()), rx, addr);
+        let private_key = engine.config.private_key;
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(ServerPlugins {
+                tick_duration: tick_duration(),
+            })
+            .add_plugins(ProtocolPlugin)
+            .add_plugins(engine);
+        app.finish();
+        app.cleanup();
+
+        let issuer = TokenIssuer {
+            server_addr: addr,
+            protocol: *app.world().resource::<ProtocolHash>(),
+            private_key,
+        };
+        (app, serve_tokens(issuer, tx))
+    }
+
+    fn client_app(
+        server_addr: SocketAddr,
+        token_addr: SocketAddr,
+        conditioner: Option<LinkConditionerConfig>,
+    ) -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(ClientPlugins {
+                tick_duration: tick_duration(),
+            })
+            .add_plugins(ProtocolPlugin)
+            .insert_resource(ConnectionSettings {
+                server_addr,
+                token_addr,
+                secret: PlayerSecret::generate(),
+                conditioner,
+            })
+            .add_plugins(GamePlugin);
+        app.finish();
+        app.cleanup();
+        app
+    }
+
+    /// Step both apps in real time until `done` holds, failing after `timeout`.
+    fn run_until(
+        server: &mut App,
+        client: &mut App,
+        timeout: Duration,
+        mut done: impl FnMut(&mut App, &mut App) -> bool,
+    ) {
+        let start = Instant::now();
+        while !done(server, client) {
+            assert!(start.elapsed() < timeout, "timed out after {timeout:?}");
+            server.update();
+            client.update();
+            std::thread::sleep(tick_duration() / 2);
+        }
+    }
+
+    /// Step both apps in real time for a while.
+    fn run_for(server: &mut App, client: &mut App, duration: Duration) {
+        let start = Instant::now();
+        run_until(server, client, duration * 2, |_, _| {
+            start.elapsed() >= duration
+        });
+    }
+
+    #[derive(Component)]
+    struct Mover;
+
+    #[test]
+    fn remote_entities_are_interpolated_between_server_updates() {
+        let addr = free_addr();
+        let (mut server, tokens) = server_app(addr);
+        let mut client = client_app(addr, tokens, None);
+
+        // Walk right at one unit per server tick
+        server.add_systems(
+            FixedUpdate,
+            |mut movers: Query<&mut Transform, With<Mover>>| {
+                for mut transform in movers.iter_mut() {
+                    transform.translation.x += 1.0;
+                }
+            },
+        );
+        server.update();
+        server.world_mut().spawn((
+            Mover,
+            EntityName("Mover".to_string()),
+            Transform::default(),
+            Replicate::to_clients(NetworkTarget::All),
+            InterpolationTarget::to_clients(NetworkTarget::All),
+        ));
+
+        // (interpolated, latest from the server, rendered)
+        let mut samples: Vec<(f32, f32, f32)> = Vec::new();
+        run_until(
+            &mut server,
+            &mut client,
+            Duration::from_secs(15),
+            |_, client| {
+                let mut movers = client.world_mut().query_filtered::<
+                    (&Position, &Confirmed<Position>, &Transform),
+                    With<Interpolated>,
+                >();
+                if let Ok((position, confirmed, transform)) = movers.single(client.world()) {
+                    samples.push((position.0.x, confirmed.0.0.x, transform.translation.x));
+                }
+                samples.len() >= 90
+            },
+        );
+
+        let updates = samples.windows(2).filter(|w| w[0].1 != w[1].1).count();
+        let steps = samples.windows(2).filter(|w| w[0].0 != w[1].0).count();
+        // Frames between server updates still move the entity
+        assert!(steps > updates, "{steps} steps for {updates} updates");
+        for window in samples.windows(2) {
+            let (before, after) = (window[0], window[1]);
+            assert!(
+                after.0 >= before.0,
+                "moved backwards: {before:?} -> {after:?}"
+            );
+        }
+        for (interpolated, confirmed, rendered) in &samples {
+            // Interpolation trails the server and is what gets drawn
+            assert!(interpolated <= confirmed);
+            assert_eq!(interpolated, rendered);
+        }
+    }
+
+    #[test]
+    fn predicted_movement_converges_with_the_server_under_latency() {
+        let addr = free_addr();
+        let (mut server, tokens) = server_app(addr);
+        let mut client = client_app(
+            addr,
+            tokens,
+            Some(LinkConditionerConfig {
+                incoming_latency: Duration::from_millis(40),
+                incoming_jitter: Duration::from_millis(5),
+                incoming_loss: 0.0,
+            }),
+        );
+        client.init_resource::<ButtonInput<KeyCode>>();
+        server.update();
+
+        fn server_x(server: &mut App) -> Option<f32> {
+            let mut players = server
+                .world_mut()
+                .query_filtered::<&Transform, With<ControlledBy>>();
+            players.single(server.world()).ok().map(|t| t.translation.x)
+        }
+        // (predicted, latest from the server)
+        fn client_x(client: &mut App) -> Option<(f32, f32)> {
+            let mut players = client.world_mut().query_filtered::<
+                (&Position, &Confirmed<Position>),
+                (With<Predicted>, With<InputMarker<PlayerInput>>),
+            >();
+            let (predicted, confirmed) = players.single(client.world()).ok()?;
+            Some((predicted.0.x, confirmed.0.0.x))
+        }
+
+        run_until(
+            &mut server,
+            &mut client,
+            Duration::from_secs(15),
+            |_, client| client_x(client).is_some(),
+        );
+        // Give the client time to run far enough ahead of the server that
+        // its input arrives in time
+        run_for(&mut server, &mut client, Duration::from_secs(2));
+
+        // Walk right; the client moves before the server has heard about it
+        client
+            .world_mut()
+            .resource_mut::<ButtonInput<KeyCode>>()
+            .press(KeyCode::KeyD);
+        let mut ran_ahead = false;
+        run_until(
+            &mut server,
+            &mut client,
+            Duration::from_secs(15),
+            |_, client| {
+                let (predicted, confirmed) = client_x(client).unwrap();
+                ran_ahead |= predicted > confirmed;
+                predicted > 100.0
+            },
+        );
+        assert!(ran_ahead);
+
+        // Once input stops, the server catches up and both agree
+        client
+            .world_mut()
+            .resource_mut::<ButtonInput<KeyCode>>()
+            .release(KeyCode::KeyD);
+        run_until(
+            &mut server,
+            &mut client,
+            Duration::from_secs(15),
+            |server, client| {
+                let (predicted, confirmed) = client_x(client).unwrap();
+                server_x(server) == Some(confirmed) && predicted == confirmed
+            },
+        );
+        let settled = client_x(&mut client).unwrap();
+        assert!(settled.0 > 100.0);
+
+        // Nothing drifts afterwards
+        run_for(&mut server, &mut client, Duration::from_millis(500));
+        assert_eq!(client_x(&mut client), Some(settled));
+        assert_eq!(server_x(&mut server), Some(settled.0));
+    }
+
+    #[test]
+    fn clients_join_as_the_player_their_token_was_issued_to() {
+        let addr = free_addr();
+        let (mut server, tokens) = server_app(addr);
+        let mut client = client_app(addr, tokens, None);
+        let player = client
+            .world()
+            .resource::<ConnectionSettings>()
+            .secret
+            .player();
+
+        run_until(
+            &mut server,
+            &mut client,
+            Duration::from_secs(10),
+            |server, _| {
+                let mut players = server.world_mut().query::<&PlayerId>();
+                players.iter(server.world()).next().is_some()
+            },
+        );
+        let mut players = server.world_mut().query::<&PlayerId>();
+        assert_eq!(players.single(server.world()).unwrap().0, player);
+    }
+
+    #[test]
+    fn player_secrets_are_kept_in_a_private_file() {
+        let path = std::env::temp_dir().join(format!("roguebench-{}.secret", uuid::Uuid::new_v4()));
+        let secret = load_or_create_secret(&path).unwrap();
+        assert!(secret.is_valid());
+        // The next launch plays as the same player
+        assert_eq!(load_or_create_secret(&path).unwrap(), secret);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn clients_keep_up_with_published_definitions() {
+        let addr = free_addr();
+        let (mut server, tokens) = server_app(addr);
+        let storage = server.world().resource::<Storage>().0.clone();
+        let potion = ItemDef::new("Potion", 10);
+        storage.save(&potion).unwrap();
+        let mut client = client_app(addr, tokens, None);
+
+        // Everything published so far arrives on connect
+        run_until(
+            &mut server,
+            &mut client,
+            Duration::from_secs(10),
+            |_, client| client.world().resource::<Definitions>().revision() > 0,
+        );
+        let revision = server.world().resource::<Definitions>().revision();
+        let definitions = client.world().resource::<Definitions>();
+        assert_eq!(definitions.revision(), revision);
+        assert_eq!(
+            definitions.get::<ItemDef>(potion.id).unwrap().name,
+            "Potion"
+        );
+
+        // Then each change made in the editor
+        let elixir = ItemDef::new("Elixir", 5);
+        storage.save(&elixir).unwrap();
+        storage.delete::<ItemDef>(potion.id).unwrap();
+        server.world_mut().trigger(ReloadContent {
+            kind: ItemDef::KIND,
+        });
+        run_until(
+            &mut server,
+            &mut client,
+            Duration::from_secs(10),
+            |_, client| client.world().resource::<Definitions>().revision() > revision,
+        );
+        let definitions = client.world().resource::<Definitions>();
+        assert_eq!(definitions.revision(), revision + 1);
+        assert_eq!(
+            definitions.get::<ItemDef>(elixir.id).unwrap().name,
+            "Elixir"
+        );
+        assert!(definitions.get::<ItemDef>(potion.id).is_none());
+    }
+
+    #[test]
+    fn snapshots_are_sent_once_per_revision() {
+        #[derive(Resource, Default)]
+        struct Snapshots(u32);
+
+        let addr = free_addr();
+        let (mut server, tokens) = server_app(addr);
+        let storage = server.world().resource::<Storage>().0.clone();
+        storage.save(&ItemDef::new("Potion", 10)).unwrap();
+        let mut client = client_app(addr, tokens, None);
+        run_until(
+            &mut server,
+            &mut client,
+            Duration::from_secs(10),
+            |_, client| client.world().resource::<Definitions>().revision() > 0,
+        );
+
+        // A client asking for everything every frame
+        client.init_resource::<Snapshots>();
+        client.add_systems(
+            Update,
+            (|mut clients: Query<(
+                &mut MessageSender<RequestDefinitions>,
+                &mut MessageReceiver<DefinitionSync>,
+            )>,
+              mut snapshots: ResMut<Snapshots>| {
+                for (mut sender, mut receiver) in clients.iter_mut() {
+                    for _ in 0..5 {
+                        sender.send::<ReliableChannel>(RequestDefinitions);
+                    }
+                    let received = receiver.receive();
+                    let count = received
+                        .filter(|sync| matches!(sync, DefinitionSync::Snapshot { .. }))
+                        .count();
+                    snapshots.0 += count as u32;
+                }
+            })
+            .before(sync_definitions),
+        );
+        run_for(&mut server, &mut client, Duration::from_millis(300));
+        assert_eq!(client.world().resource::<Snapshots>().0, 0);
+
+        // Only a newer revision earns another one
+        storage.save(&ItemDef::new("Elixir", 5)).unwrap();
+        server.world_mut().trigger(ReloadContent {
+            kind: ItemDef::KIND,
+        });
+        run_for(&mut server, &mut client, Duration::from_millis(300));
+        assert_eq!(client.world().resource::<Snapshots>().0, 1);
+    }
+
+    #[test]
+    fn clients_resolve_replicated_templates_to_their_definitions() {
+        let addr = free_addr();
+        let (mut server, tokens) = server_app(addr);
+        let storage = server.world().resource::<Storage>().0.clone();
+        // Two goblins only their templates tell apart
+        let mut archer = EntityDef::new("Goblin", 30);
+        archer.slug = Some("goblin-archer".to_string());
+        storage.save_entity(&archer).unwrap();
+        storage.save_entity(&EntityDef::new("Goblin", 60)).unwrap();
+        let mut client = client_app(addr, tokens, None);
+
+        let mut templates = client.world_mut().query::<(&TemplateId, &Template)>();
+        run_until(
+            &mut server,
+            &mut client,
+            Duration::from_secs(10),
+            |_, client| templates.iter(client.world()).count() == 2,
+        );
+        let (id, Template(def)) = templates
+            .iter(client.world())
+            .find(|(id, _)| id.id == archer.id)
+            .unwrap();
+        assert_eq!(id.slug.as_deref(), Some("goblin-archer"));
+        assert_eq!(def.health, 30);
+    }
+
+    #[test]
+    fn gameplay_events_reach_clients_near_them() {
+        let addr = free_addr();
+        let (mut server, tokens) = server_app(addr);
+        let mut client = client_app(addr, tokens, None);
+
+        #[derive(Resource, Default)]
+        struct Heard(Vec<Entity>, Vec<Entity>);
+        client.init_resource::<Heard>();
+        client.add_observer(|hit: On<HitLanded>, mut heard: ResMut<Heard>| {
+            heard.0.push(hit.target);
+        });
+        client.add_observer(|death: On<EntityDied>, mut heard: ResMut<Heard>| {
+            heard.1.push(death.entity);
+        });
+
+        let mut players = server
+            .world_mut()
+            .query_filtered::<Entity, With<PlayerId>>();
+        run_until(
+            &mut server,
+            &mut client,
+            Duration::from_secs(10),
+            |server, _| players.iter(server.world()).next().is_some(),
+        );
+        let player = players.single(server.world()).unwrap();
+        let far_away = server
+            .world_mut()
+            .spawn((
+                EntityName("Distant".to_string()),
+                Transform::from_xyz(10.0 * FEEDBACK_RADIUS, 0.0, 0.0),
+                Replicate::to_clients(NetworkTarget::All),
+            ))
+            .id();
+        run_for(&mut server, &mut client, Duration::from_millis(300));
+
+        // Out of earshot first, then the player itself dying
+        for (target, health) in [(far_away, 0), (player, 0)] {
+            server.world_mut().trigger(DamageDealt {
+                target,
+                source: target,
+                amount: 5,
+                health,
+            });
+        }
+        run_until(
+            &mut server,
+            &mut client,
+            Duration::from_secs(10),
+            |_, client| {
+                let heard = client.world().resource::<Heard>();
+                !heard.0.is_empty() && !heard.1.is_empty()
+            },
+        );
+        run_for(&mut server, &mut client, Duration::from_millis(200));
+
+        let Heard(hits, deaths) = client.world().resource::<Heard>();
+        assert_eq!(hits, deaths);
+        assert_eq!(hits.len(), 1);
+        // Mapped to the client's copy of the player
+        let name = client.world().get::<EntityName>(hits[0]).unwrap();
+        assert_eq!(name.0, "Player");
+    }
+
+    #[test]
+    fn clients_only_receive_entities_near_their_player() {
+        let addr = free_addr();
+        let (mut server, tokens) = server_app(addr);
+        let mut clients = [(); 2].map(|_| client_app(addr, tokens, None));
+
+        /// Step every app in real time until `done` holds.
+        fn run_all_until(
+            server: &mut App,
+            clients: &mut [App],
+            mut done: impl FnMut(&mut App, &mut [App]) -> bool,
+        ) {
+            let start = Instant::now();
+            while !done(server, clients) {
+                assert!(start.elapsed() < Duration::from_secs(15), "timed out");
+                server.update();
+                clients.iter_mut().for_each(App::update);
+                std::thread::sleep(tick_duration() / 2);
+            }
+        }
+
+        /// Names of the replicated entities a client holds, sorted.
+        fn seen(client: &mut App) -> Vec<String> {
+            let mut names = client
+                .world_mut()
+                .query_filtered::<&EntityName, With<Replicated>>();
+            let mut names: Vec<_> = names.iter(client.world()).map(|n| n.0.clone()).collect();
+            names.sort();
+            names
+        }
+
+        let mut players = server
+            .world_mut()
+            .query_filtered::<(Entity, &ControlledBy), With<PlayerId>>();
+        run_all_until(&mut server, &mut clients, |server, _| {
+            players.iter(server.world()).count() == 2
+        });
+
+        // Send the second client's player well out of sight of the first
+        let far = Vec3::new(4.0 * (VIEW_DISTANCE + 1) as f32 * CELL_SIZE, 0.0, 0.0);
+        let mut ids = clients[1].world_mut().query::<&LocalId>();
+        let local = ids.single(clients[1].world()).unwrap().0;
+        let wanderer = players
+            .iter(server.world())
+            .find_map(|(player, controlled_by)| {
+                let remote = server.world().get::<RemoteId>(controlled_by.owner)?;
+                (remote.0 == local).then_some(player)
+            })
+            .unwrap();
+        server
+            .world_mut()
+            .get_mut::<Transform>(wanderer)
+            .unwrap()
+            .translation = far;
+        for (name, at) in [("Crate", Vec3::ZERO), ("Far crate", far)] {
+            server.world_mut().spawn((
+                EntityName(name.to_string()),
+                Transform::from_translation(at),
+                Replicate::to_clients(NetworkTarget::All),
+            ));
+        }
+        let apart = |clients: &mut [App]| {
+            seen(&mut clients[0]) == ["Crate", "Player"]
+                && seen(&mut clients[1]) == ["Far crate", "Player"]
+        };
+        run_all_until(&mut server, &mut clients, |_, clients| apart(clients));
+
+        // Walking back brings the first client's entities into view, and
+        // leaves the far crate behind
+        server
+            .world_mut()
+            .get_mut::<Transform>(wanderer)
+            .unwrap()
+            .translation = Vec3::ZERO;
+        let together = ["Crate", "Player", "Player"];
+        run_all_until(&mut server, &mut clients, |_, clients| {
+            seen(&mut clients[0]) == together && seen(&mut clients[1]) == together
+        });
+    }
+
+    #[test]
+    fn clients_with_a_forged_token_are_ignored() {
+        let addr = free_addr();
+        let (mut server, _) = server_app(addr);
+        // Signed with a key the server doesn't know
+        let forger = TokenIssuer {
+            server_addr: addr,
+            protocol: *server.world().resource::<ProtocolHash>(),
+            private_key: lightyear::netcode::generate_key(),
+        };
+        let tokens = serve_tokens(forger, tokio::sync::mpsc::unbounded_channel().0);
+        let mut client = client_app(addr, tokens, None);
+
+        run_for(&mut server, &mut client, Duration::from_secs(1));
+        let mut clients = server.world_mut().query::<&ClientOf>();
+        assert_eq!(clients.iter(server.world()).count(), 0);
+        let mut connected = client.world_mut().query::<&Connected>();
+        assert_eq!(connected.iter(client.world()).count(), 0);
+    }
+
+    #[test]
+    fn connected_clients_ignore_forged_version_packets() {
+        let addr = free_addr();
+        let (mut server, tokens) = server_app(addr);
+        let mut client = client_app(addr, tokens, None);
+        let connected = |client: &mut App| {
+            let mut connected = client
+                .world_mut()
+                .query_filtered::<(), (With<NetcodeClient>, With<Connected>)>();
+            connected.iter(client.world()).count() == 1
+        };
+        run_until(
+            &mut server,
+            &mut client,
+            Duration::from_secs(10),
+            |_, client| connected(client),
+        );
+
+        // Anyone can send the client a packet claiming the server moved on
+        let ours = *server.world().resource::<ProtocolHash>();
+        let forged = VersionMismatch {
+            client: ours,
+            server: ProtocolHash(ours.0 ^ 1),
+        };
+        let mut links = client
+            .world_mut()
+            .query_filtered::<&mut Link, With<NetcodeClient>>();
+        let mut link = links.single_mut(client.world_mut()).unwrap();
+        link.recv.push_raw(forged.to_packet().into());
+        client
+            .world_mut()
+            .run_system_once(detect_version_mismatch)
+            .unwrap();
+        client.world_mut().flush();
+        assert!(client.world().get_resource::<ConnectionProblem>().is_none());
+
+        // Left for netcode, which drops such garbage in release builds but
+        // overflows reading it in debug ones, so take it back before going on
+        let mut link = links.single_mut(client.world_mut()).unwrap();
+        assert_eq!(link.recv.drain().count(), 1);
+        run_for(&mut server, &mut client, Duration::from_millis(200));
+        assert!(connected(&mut client));
+    }
+
+    #[test]
+    fn clients_of_another_protocol_are_turned_away() {
+        let addr = free_addr();
+        let (mut server, tokens) = server_app(addr);
+        let mut client = client_app(addr, tokens, None);
+        let ours = *server.world().resource::<ProtocolHash>();
+        assert_eq!(*client.world().resource::<ProtocolHash>(), ours);
+        // A stale build registering something else
+        let stale = ProtocolHash(ours.0 ^ 1);
+        client.insert_resource(stale);
+
+        // Refused a token, so it never reaches the game server
+        run_for(&mut server, &mut client, Duration::from_millis(200));
+        let problem = client.world().get_resource::<ConnectionProblem>();
+        let Some(ConnectionProblem::VersionMismatch(mismatch)) = problem else {
+            panic!("expected a version mismatch, got {problem:?}");
+        };
+        assert_eq!(
+            *mismatch,
+            VersionMismatch {
+                client: stale,
+                server: ours,
+            }
+        );
+
+        let mut texts = client
+            .world_mut()
+            .query_filtered::<&Text, With<ConnectionErrorText>>();
+        let text = texts.single(client.world()).unwrap();
+        assert!(text.0.starts_with("Version mismatch"));
+        let mut clients = server.world_mut().query::<&ClientOf>();
+        assert_eq!(clients.iter(server.world()).count(), 0);
+    }
+}
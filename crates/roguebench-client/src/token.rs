@@ -0,0 +1,61 @@
+//! Fetching connect tokens from the server's HTTP endpoint.
+
+use std::fmt;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use lightyear::netcode::ConnectToken;
+use roguebench_protocol::{CONNECT_PATH, TokenRequest, VersionMismatch};
+
+/// How long to wait on the token endpoint before giving up.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Why no connect token could be had.
+#[derive(Debug)]
+pub enum TokenError {
+    /// The server speaks another protocol.
+    Mismatch(VersionMismatch),
+    /// The endpoint couldn't be reached or gave an unexpected answer.
+    Unavailable(String),
+}
+
+impl fmt::Display for TokenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Mismatch(mismatch) => mismatch.fmt(f),
+            Self::Unavailable(reason) => write!(f, "no connect token: {reason}"),
+        }
+    }
+}
+
+impl From<ureq::Error> for TokenError {
+    fn from(e: ureq::Error) -> Self {
+        Self::Unavailable(e.to_string())
+    }
+}
+
+/// Ask the endpoint at `addr` for a token to join its game server.
+///
+/// Blocks until answered or timed out, so run it off the main thread.
+pub fn request_token(addr: SocketAddr, request: &TokenRequest) -> Result<ConnectToken, TokenError> {
+    let body = serde_json::to_vec(request).map_err(|e| TokenError::Unavailable(e.to_string()))?;
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(TIMEOUT))
+        .http_status_as_error(false)
+        .build()
+        .into();
+    let mut response = agent
+        .post(format!("http://{addr}{CONNECT_PATH}"))
+        .header("Content-Type", "application/json")
+        .send(&body[..])?;
+    let body = response.body_mut().read_to_vec()?;
+    match response.status().as_u16() {
+        200 => ConnectToken::try_from_bytes(&body)
+            .map_err(|e| TokenError::Unavailable(format!("bad token: {e:?}"))),
+        409 => Err(match serde_json::from_slice(&body) {
+            Ok(mismatch) => TokenError::Mismatch(mismatch),
+            Err(e) => TokenError::Unavailable(e.to_string()),
+        }),
+        status => Err(TokenError::Unavailable(format!("HTTP status {status}"))),
+    }
+}
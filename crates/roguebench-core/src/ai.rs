@@ -0,0 +1,195 @@
+//! Enemy AI profiles.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Behaviour states an AI-driven entity moves between.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AiState {
+    /// Standing still.
+    Idle,
+    /// Strolling around its home, or heading back to it after giving up a chase.
+    Wander,
+    /// Moving towards its target.
+    Chase,
+    /// In range and attacking its target.
+    Attack,
+    /// Moving away from its target.
+    Flee,
+}
+
+impl AiState {
+    pub const ALL: [AiState; 5] = [
+        AiState::Idle,
+        AiState::Wander,
+        AiState::Chase,
+        AiState::Attack,
+        AiState::Flee,
+    ];
+}
+
+/// Tuning for an AI-driven entity.
+///
+/// Every enemy runs the same state machine; how it behaves comes from these
+/// parameters alone. The presets show a few archetypes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct AiProfile {
+    /// Distance in world units at which a visible player is noticed.
+    #[schemars(range(min = 0.0))]
+    pub detection_radius: f32,
+    /// Distance from home past which the entity gives up a chase.
+    #[schemars(range(min = 0.0))]
+    pub leash_range: f32,
+    /// Distance to its target at which the entity starts attacking.
+    #[schemars(range(min = 0.0))]
+    pub attack_range: f32,
+    /// Seconds between melee attacks. Armed entities fire as fast as their weapon allows.
+    #[schemars(range(min = 0.0))]
+    pub attack_cooldown: f32,
+    /// Damage per melee attack, for entities without a weapon.
+    #[serde(default)]
+    #[schemars(range(min = 0))]
+    pub melee_damage: i32,
+    /// Speed while wandering, in world units per second; 0 never wanders.
+    #[serde(default)]
+    #[schemars(range(min = 0.0))]
+    pub wander_speed: f32,
+    /// How far from home the entity wanders.
+    #[serde(default)]
+    #[schemars(range(min = 0.0))]
+    pub wander_radius: f32,
+    /// Speed while chasing or fleeing, in world units per second.
+    #[schemars(range(min = 0.0))]
+    pub chase_speed: f32,
+    /// Fraction of its health below which the entity runs away; 0 never flees.
+    #[serde(default)]
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub flee_health: f32,
+    /// Distance inside which the entity backs away from its target; 0 never does.
+    #[serde(default)]
+    #[schemars(range(min = 0.0))]
+    pub keep_away: f32,
+}
+
+impl AiProfile {
+    /// Average melee enemy: wanders, chases and swings.
+    pub fn grunt() -> Self {
+        Self {
+            detection_radius: 200.0,
+            leash_range: 400.0,
+            attack_range: 24.0,
+            attack_cooldown: 1.0,
+            melee_damage: 5,
+            wander_speed: 40.0,
+            wander_radius: 64.0,
+            chase_speed: 90.0,
+            flee_health: 0.0,
+            keep_away: 0.0,
+        }
+    }
+
+    /// Waits in place, then closes the gap fast; runs off when hurt.
+    pub fn dasher() -> Self {
+        Self {
+            detection_radius: 160.0,
+            leash_range: 300.0,
+            attack_range: 20.0,
+            attack_cooldown: 0.6,
+            melee_damage: 3,
+            wander_speed: 0.0,
+            wander_radius: 0.0,
+            chase_speed: 220.0,
+            flee_health: 0.3,
+            keep_away: 0.0,
+        }
+    }
+
+    /// Keeps its distance and shoots with its weapon.
+    pub fn archer() -> Self {
+        Self {
+            detection_radius: 320.0,
+            leash_range: 480.0,
+            attack_range: 240.0,
+            attack_cooldown: 0.0,
+            melee_damage: 0,
+            wander_speed: 30.0,
+            wander_radius: 48.0,
+            chase_speed: 70.0,
+            flee_health: 0.0,
+            keep_away: 96.0,
+        }
+    }
+
+    /// Slow and stubborn, hits hard and never gives up ground.
+    pub fn brute() -> Self {
+        Self {
+            detection_radius: 180.0,
+            leash_range: 640.0,
+            attack_range: 32.0,
+            attack_cooldown: 2.0,
+            melee_damage: 15,
+            wander_speed: 20.0,
+            wander_radius: 32.0,
+            chase_speed: 50.0,
+            flee_health: 0.0,
+            keep_away: 0.0,
+        }
+    }
+
+    /// Describe any problems with the profile.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        for (field, value) in [
+            ("Detection radius", self.detection_radius),
+            ("Leash range", self.leash_range),
+            ("Attack range", self.attack_range),
+            ("Attack cooldown", self.attack_cooldown),
+            ("Wander speed", self.wander_speed),
+            ("Wander radius", self.wander_radius),
+            ("Chase speed", self.chase_speed),
+            ("Keep away distance", self.keep_away),
+        ] {
+            if !value.is_finite() {
+                problems.push(format!("{field} must be a finite number"));
+            } else if value < 0.0 {
+                problems.push(format!("{field} can't be negative"));
+            }
+        }
+        if self.melee_damage < 0 {
+            problems.push("Melee damage can't be negative".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.flee_health) {
+            problems.push("Flee health must be a fraction between 0 and 1".to_string());
+        }
+        if self.keep_away >= self.attack_range && self.keep_away > 0.0 {
+            problems.push("Keep away distance must be shorter than the attack range".to_string());
+        }
+        problems
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profiles_reject_negative_damage_and_unbounded_values() {
+        assert!(AiProfile::grunt().validate().is_empty());
+
+        let mut profile = AiProfile::brute();
+        profile.melee_damage = -5;
+        profile.chase_speed = f32::INFINITY;
+        profile.detection_radius = f32::NAN;
+        profile.leash_range = -1.0;
+        assert_eq!(
+            profile.validate(),
+            [
+                "Detection radius must be a finite number",
+                "Leash range can't be negative",
+                "Chase speed must be a finite number",
+                "Melee damage can't be negative",
+            ]
+        );
+    }
+}
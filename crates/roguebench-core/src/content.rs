@@ -0,0 +1,37 @@
+//! Shared trait for authored content kinds.
+
+use schemars::{JsonSchema, Schema};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use uuid::Uuid;
+
+use crate::content_schema;
+
+/// A kind of authored content that can be stored and loaded generically.
+///
+/// Each kind is persisted as a serialized document keyed by [`ContentDef::KIND`]
+/// and its id, so adding a new kind needs no storage schema changes.
+pub trait ContentDef:
+    Clone + Serialize + DeserializeOwned + JsonSchema + Send + Sync + 'static
+{
+    /// Stable name for this kind of content (e.g. `"item"`).
+    ///
+    /// Used as the storage key and in editor routes, so it must never change
+    /// once content has been authored.
+    const KIND: &'static str;
+
+    /// Stable identifier of this definition.
+    fn id(&self) -> Uuid;
+
+    /// Describe any problems that should stop this definition from being saved.
+    ///
+    /// Returns an empty list when the definition is valid.
+    fn validate(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// JSON Schema describing documents of this kind.
+    fn schema() -> Schema {
+        content_schema::<Self>(Self::KIND)
+    }
+}
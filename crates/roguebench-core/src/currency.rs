@@ -0,0 +1,46 @@
+//! Currency definitions.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::ContentDef;
+
+/// Definition of a currency as stored in the content database.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct CurrencyDef {
+    pub id: Uuid,
+    pub name: String,
+    /// Amount every new wallet starts with.
+    #[serde(default)]
+    pub starting_amount: u32,
+    /// Whether balances are banked into the player's profile when a run ends.
+    #[serde(default)]
+    pub persistent: bool,
+}
+
+impl CurrencyDef {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            starting_amount: 0,
+            persistent: false,
+        }
+    }
+}
+
+impl ContentDef for CurrencyDef {
+    const KIND: &'static str = "currency";
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn validate(&self) -> Vec<String> {
+        if self.name.trim().is_empty() {
+            return vec!["Currency needs a name".to_string()];
+        }
+        Vec::new()
+    }
+}
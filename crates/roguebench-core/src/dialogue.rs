@@ -0,0 +1,259 @@
+//! Dialogue graph definitions and validation.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::ContentDef;
+
+/// A story flag requirement for a dialogue choice.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct FlagCondition {
+    pub flag: String,
+    /// Whether the flag must be set (`true`) or unset (`false`).
+    pub value: bool,
+}
+
+impl FlagCondition {
+    /// Check the condition against the set of currently raised flags.
+    pub fn is_met(&self, flags: &HashSet<String>) -> bool {
+        flags.contains(&self.flag) == self.value
+    }
+}
+
+/// Side effect applied when a node is entered or a choice is picked.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum DialogueEffect {
+    /// Raise or clear a story flag.
+    SetFlag { flag: String, value: bool },
+}
+
+/// A response the player can pick at a node.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DialogueChoice {
+    pub text: String,
+    /// Node to go to next; `None` ends the conversation.
+    #[serde(default)]
+    pub target: Option<String>,
+    /// All conditions must hold for the choice to be offered.
+    #[serde(default)]
+    pub conditions: Vec<FlagCondition>,
+    #[serde(default)]
+    pub effects: Vec<DialogueEffect>,
+}
+
+impl DialogueChoice {
+    /// Whether this choice is offered given the raised flags.
+    pub fn is_available(&self, flags: &HashSet<String>) -> bool {
+        self.conditions
+            .iter()
+            .all(|condition| condition.is_met(flags))
+    }
+}
+
+/// A single line of dialogue and the choices that follow it.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DialogueNode {
+    /// Identifier unique within the dialogue, used as a choice target.
+    pub id: String,
+    pub speaker: String,
+    pub text: String,
+    /// Effects applied when the node is entered.
+    #[serde(default)]
+    pub effects: Vec<DialogueEffect>,
+    #[serde(default)]
+    pub choices: Vec<DialogueChoice>,
+}
+
+/// Definition of a dialogue tree as stored in the content database.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DialogueDef {
+    pub id: Uuid,
+    pub name: String,
+    /// ID of the node the conversation starts at.
+    pub start: String,
+    pub nodes: Vec<DialogueNode>,
+}
+
+/// A structural problem found by [`DialogueDef::issues`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DialogueIssue {
+    /// The start node doesn't exist.
+    MissingStart { start: String },
+    /// Two nodes share an ID.
+    DuplicateNode { node: String },
+    /// A choice points at a node that doesn't exist.
+    MissingTarget { node: String, target: String },
+    /// No path from the start node reaches this node.
+    UnreachableNode { node: String },
+    /// The node offers no choices, so the conversation can't continue or end.
+    DeadEnd { node: String },
+}
+
+impl fmt::Display for DialogueIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingStart { start } => write!(f, "start node '{start}' does not exist"),
+            Self::DuplicateNode { node } => write!(f, "node '{node}' is defined more than once"),
+            Self::MissingTarget { node, target } => {
+                write!(
+                    f,
+                    "node '{node}' has a choice targeting missing node '{target}'"
+                )
+            }
+            Self::UnreachableNode { node } => write!(f, "node '{node}' is unreachable"),
+            Self::DeadEnd { node } => write!(f, "node '{node}' has no choices"),
+        }
+    }
+}
+
+impl DialogueDef {
+    pub fn new(name: impl Into<String>, start: impl Into<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            start: start.into(),
+            nodes: Vec::new(),
+        }
+    }
+
+    /// Look up a node by ID.
+    pub fn node(&self, id: &str) -> Option<&DialogueNode> {
+        self.nodes.iter().find(|node| node.id == id)
+    }
+
+    /// Find structural problems in the graph.
+    pub fn issues(&self) -> Vec<DialogueIssue> {
+        let mut issues = Vec::new();
+
+        let mut nodes: HashMap<&str, &DialogueNode> = HashMap::new();
+        for node in &self.nodes {
+            if nodes.insert(node.id.as_str(), node).is_some() {
+                issues.push(DialogueIssue::DuplicateNode {
+                    node: node.id.clone(),
+                });
+            }
+        }
+
+        for node in &self.nodes {
+            if node.choices.is_empty() {
+                issues.push(DialogueIssue::DeadEnd {
+                    node: node.id.clone(),
+                });
+            }
+            for target in node.choices.iter().filter_map(|c| c.target.as_ref()) {
+                if !nodes.contains_key(target.as_str()) {
+                    issues.push(DialogueIssue::MissingTarget {
+                        node: node.id.clone(),
+                        target: target.clone(),
+                    });
+                }
+            }
+        }
+
+        if !nodes.contains_key(self.start.as_str()) {
+            issues.push(DialogueIssue::MissingStart {
+                start: self.start.clone(),
+            });
+            return issues;
+        }
+
+        // Breadth-first walk from the start node
+        let mut reached = HashSet::from([self.start.as_str()]);
+        let mut queue = VecDeque::from([self.start.as_str()]);
+        while let Some(id) = queue.pop_front() {
+            let targets = nodes[id].choices.iter().filter_map(|c| c.target.as_deref());
+            for target in targets {
+                if nodes.contains_key(target) && reached.insert(target) {
+                    queue.push_back(target);
+                }
+            }
+        }
+        for node in &self.nodes {
+            if !reached.contains(node.id.as_str()) {
+                issues.push(DialogueIssue::UnreachableNode {
+                    node: node.id.clone(),
+                });
+            }
+        }
+
+        issues
+    }
+}
+
+impl ContentDef for DialogueDef {
+    const KIND: &'static str = "dialogue";
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn validate(&self) -> Vec<String> {
+        self.issues().iter().map(ToString::to_string).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: &str, targets: &[Option<&str>]) -> DialogueNode {
+        DialogueNode {
+            id: id.to_string(),
+            speaker: "Guard".to_string(),
+            text: format!("Line {id}"),
+            effects: Vec::new(),
+            choices: targets
+                .iter()
+                .map(|target| DialogueChoice {
+                    text: "...".to_string(),
+                    target: target.map(str::to_string),
+                    conditions: Vec::new(),
+                    effects: Vec::new(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn valid_dialogue_has_no_issues() {
+        let mut dialogue = DialogueDef::new("Gate", "greet");
+        dialogue.nodes = vec![
+            node("greet", &[Some("ask"), None]),
+            node("ask", &[Some("greet"), None]),
+        ];
+        assert!(dialogue.issues().is_empty());
+    }
+
+    #[test]
+    fn issues_flag_unreachable_dead_end_and_missing_target() {
+        let mut dialogue = DialogueDef::new("Gate", "greet");
+        dialogue.nodes = vec![
+            node("greet", &[Some("ask"), Some("nowhere")]),
+            node("ask", &[]),
+            node("orphan", &[None]),
+        ];
+
+        let issues = dialogue.issues();
+        assert_eq!(issues.len(), 3);
+        assert!(issues.contains(&DialogueIssue::DeadEnd {
+            node: "ask".to_string()
+        }));
+        assert!(issues.contains(&DialogueIssue::MissingTarget {
+            node: "greet".to_string(),
+            target: "nowhere".to_string()
+        }));
+        assert!(issues.contains(&DialogueIssue::UnreachableNode {
+            node: "orphan".to_string()
+        }));
+
+        dialogue.start = "missing".to_string();
+        assert!(dialogue.issues().contains(&DialogueIssue::MissingStart {
+            start: "missing".to_string()
+        }));
+    }
+}
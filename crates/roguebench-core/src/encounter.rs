@@ -0,0 +1,186 @@
+//! Wave-based encounter definitions.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::ContentDef;
+
+/// Most entities a single wave can spawn.
+pub const MAX_WAVE_SPAWNS: u32 = 256;
+
+/// How a wave picks the room spawn point for each entity it spawns.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum SpawnSelection {
+    /// Cycle through all of the room's spawn points in order.
+    #[default]
+    Sequential,
+    /// Cycle through the spawn points with the given tag.
+    Tagged { tag: String },
+    /// Pick any of the room's spawn points at random, using the run seed.
+    Random,
+}
+
+/// When a wave counts as cleared.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum ClearCondition {
+    /// Every entity of the wave has died.
+    #[default]
+    AllDefeated,
+    /// At most `count` entities of the wave are still alive.
+    Remaining { count: u32 },
+    /// The wave has been running for `seconds`, however many are left.
+    Survive { seconds: f32 },
+}
+
+/// A number of entities spawned from one template.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct WaveSpawn {
+    /// ID of the [`EntityDef`](crate::EntityDef) to spawn.
+    #[schemars(extend("x-content-kind" = "entity"))]
+    pub entity: Uuid,
+    #[schemars(range(min = 1))]
+    pub count: u32,
+}
+
+/// One wave of an encounter.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct WaveDef {
+    pub spawns: Vec<WaveSpawn>,
+    /// Seconds to wait before the wave starts spawning.
+    #[serde(default)]
+    #[schemars(range(min = 0.0))]
+    pub start_delay: f32,
+    /// Seconds between two consecutive spawns.
+    #[serde(default)]
+    #[schemars(range(min = 0.0))]
+    pub spawn_delay: f32,
+    #[serde(default)]
+    pub spawn_selection: SpawnSelection,
+    #[serde(default)]
+    pub clear: ClearCondition,
+}
+
+impl WaveDef {
+    pub fn new(spawns: Vec<WaveSpawn>) -> Self {
+        Self {
+            spawns,
+            start_delay: 0.0,
+            spawn_delay: 0.0,
+            spawn_selection: SpawnSelection::default(),
+            clear: ClearCondition::default(),
+        }
+    }
+
+    /// Total number of entities the wave spawns, saturating at `u32::MAX`.
+    pub fn total(&self) -> u32 {
+        self.spawns
+            .iter()
+            .fold(0, |total: u32, spawn| total.saturating_add(spawn.count))
+    }
+}
+
+/// Definition of an arena encounter as stored in the content database.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct EncounterDef {
+    pub id: Uuid,
+    pub name: String,
+    pub waves: Vec<WaveDef>,
+    /// Whether clearing the encounter wins the run, as for a final boss.
+    #[serde(default)]
+    pub ends_run: bool,
+}
+
+impl EncounterDef {
+    pub fn new(name: impl Into<String>, waves: Vec<WaveDef>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            waves,
+            ends_run: false,
+        }
+    }
+}
+
+impl ContentDef for EncounterDef {
+    const KIND: &'static str = "encounter";
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.waves.is_empty() {
+            problems.push("Encounter has no waves".to_string());
+        }
+        for (index, wave) in self.waves.iter().enumerate() {
+            match wave.total() {
+                0 => problems.push(format!("Wave {index} spawns nothing")),
+                total if total > MAX_WAVE_SPAWNS => problems.push(format!(
+                    "Wave {index} spawns more than {MAX_WAVE_SPAWNS} entities"
+                )),
+                _ => {}
+            }
+            let mut delays = vec![wave.start_delay, wave.spawn_delay];
+            if let ClearCondition::Survive { seconds } = wave.clear {
+                delays.push(seconds);
+            }
+            if delays.iter().any(|delay| !delay.is_finite()) {
+                problems.push(format!("Wave {index} has a delay that never ends"));
+            } else if delays.iter().any(|delay| *delay < 0.0) {
+                problems.push(format!("Wave {index} has a negative delay"));
+            }
+        }
+        problems
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wave(counts: &[u32]) -> WaveDef {
+        WaveDef::new(
+            counts
+                .iter()
+                .map(|&count| WaveSpawn {
+                    entity: Uuid::new_v4(),
+                    count,
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn waves_are_bounded_in_size_and_time() {
+        let mut waves = vec![
+            wave(&[3, 2]),
+            wave(&[u32::MAX, u32::MAX]),
+            wave(&[MAX_WAVE_SPAWNS, 1]),
+            wave(&[1]),
+            wave(&[1]),
+            wave(&[1]),
+        ];
+        assert_eq!(waves[1].total(), u32::MAX);
+        waves[3].spawn_delay = f32::NAN;
+        waves[4].start_delay = -1.0;
+        waves[5].clear = ClearCondition::Survive {
+            seconds: f32::INFINITY,
+        };
+        let encounter = EncounterDef::new("Horde", waves);
+
+        assert_eq!(
+            encounter.validate(),
+            [
+                "Wave 1 spawns more than 256 entities",
+                "Wave 2 spawns more than 256 entities",
+                "Wave 3 has a delay that never ends",
+                "Wave 4 has a negative delay",
+                "Wave 5 has a delay that never ends",
+            ]
+        );
+    }
+}
@@ -0,0 +1,101 @@
+//! Factions and how they regard each other.
+
+use std::collections::BTreeMap;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::ContentDef;
+
+/// How one faction regards another.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Relation {
+    /// Attacked on sight.
+    Hostile,
+    /// Left alone, but can be hurt.
+    #[default]
+    Neutral,
+    /// Never attacked or hurt.
+    Friendly,
+}
+
+/// Definition of a faction as stored in the content database.
+///
+/// Each faction holds its own row of the relationship matrix. Members of the
+/// same faction are always friendly to each other.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct FactionDef {
+    pub id: Uuid,
+    pub name: String,
+    /// How this faction regards others, keyed by faction ID; unlisted factions are neutral.
+    #[serde(default)]
+    #[schemars(extend("x-content-kind" = "faction"))]
+    pub relations: BTreeMap<Uuid, Relation>,
+}
+
+impl FactionDef {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            relations: BTreeMap::new(),
+        }
+    }
+
+    /// Set how this faction regards another.
+    pub fn with_relation(mut self, faction: Uuid, relation: Relation) -> Self {
+        self.relations.insert(faction, relation);
+        self
+    }
+
+    /// How this faction regards another, if the matrix says anything about it.
+    pub fn relation_to(&self, faction: Uuid) -> Option<Relation> {
+        if faction == self.id {
+            return Some(Relation::Friendly);
+        }
+        self.relations.get(&faction).copied()
+    }
+}
+
+impl ContentDef for FactionDef {
+    const KIND: &'static str = "faction";
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.name.trim().is_empty() {
+            problems.push("Faction needs a name".to_string());
+        }
+        if self.relations.contains_key(&self.id) {
+            problems.push("A faction can't set a relation to itself".to_string());
+        }
+        problems
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relations_default_to_neutral_and_self_is_friendly() {
+        let bandits = FactionDef::new("Bandits");
+        let guards = FactionDef::new("Guards").with_relation(bandits.id, Relation::Hostile);
+
+        assert_eq!(guards.relation_to(bandits.id), Some(Relation::Hostile));
+        assert_eq!(guards.relation_to(guards.id), Some(Relation::Friendly));
+        assert_eq!(bandits.relation_to(guards.id), None);
+
+        let relations = serde_json::to_value(&guards).unwrap()["relations"].clone();
+        assert_eq!(relations[bandits.id.to_string()], "hostile");
+
+        let mut confused = FactionDef::new("");
+        confused.relations.insert(confused.id, Relation::Hostile);
+        assert_eq!(confused.validate().len(), 2);
+    }
+}
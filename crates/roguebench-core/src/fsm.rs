@@ -0,0 +1,290 @@
+//! Typed finite state machines.
+//!
+//! A [`StateMachineDef`] declares the states and guarded transitions of a
+//! machine; a [`StateMachine`] is the serializable runtime state of one
+//! instance of it.
+
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+/// Number of transitions kept in a machine's history.
+pub const HISTORY_LEN: usize = 16;
+
+/// A change from one state to another.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateTransition<S> {
+    pub from: S,
+    pub to: S,
+    /// Seconds spent in `from` before leaving it.
+    pub after: f32,
+}
+
+/// Runtime state of a machine: where it is, for how long, and how it got there.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateMachine<S> {
+    current: S,
+    time_in_state: f32,
+    history: VecDeque<StateTransition<S>>,
+}
+
+impl<S: Clone + PartialEq> StateMachine<S> {
+    pub fn new(initial: S) -> Self {
+        Self {
+            current: initial,
+            time_in_state: 0.0,
+            history: VecDeque::new(),
+        }
+    }
+
+    pub fn current(&self) -> &S {
+        &self.current
+    }
+
+    pub fn is(&self, state: &S) -> bool {
+        self.current == *state
+    }
+
+    /// Seconds since the current state was entered.
+    pub fn time_in_state(&self) -> f32 {
+        self.time_in_state
+    }
+
+    /// The most recent transitions, oldest first.
+    pub fn history(&self) -> impl Iterator<Item = &StateTransition<S>> {
+        self.history.iter()
+    }
+
+    /// Advance the time spent in the current state.
+    pub fn tick(&mut self, dt: f32) {
+        self.time_in_state += dt;
+    }
+
+    /// Move to a state without checking any transition rules.
+    pub fn enter(&mut self, to: S) -> StateTransition<S> {
+        let transition = StateTransition {
+            from: std::mem::replace(&mut self.current, to.clone()),
+            to,
+            after: self.time_in_state,
+        };
+        self.time_in_state = 0.0;
+        if self.history.len() == HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back(transition.clone());
+        transition
+    }
+}
+
+/// Condition a transition must pass, given the machine's context.
+pub type Guard<S, C> = Arc<dyn Fn(&C, &StateMachine<S>) -> bool + Send + Sync>;
+
+/// A declared transition.
+pub struct TransitionDef<S, C> {
+    /// State the transition leaves; `None` for any state other than `to`.
+    pub from: Option<S>,
+    pub to: S,
+    pub guard: Guard<S, C>,
+}
+
+/// Declared states and transitions of a machine with context `C`.
+///
+/// Transitions are checked in declaration order; the first one whose guard
+/// passes is taken.
+pub struct StateMachineDef<S, C> {
+    pub initial: S,
+    pub states: Vec<S>,
+    pub transitions: Vec<TransitionDef<S, C>>,
+}
+
+// Manual impls so the context doesn't have to be `Clone`
+impl<S: Clone, C> Clone for TransitionDef<S, C> {
+    fn clone(&self) -> Self {
+        Self {
+            from: self.from.clone(),
+            to: self.to.clone(),
+            guard: self.guard.clone(),
+        }
+    }
+}
+
+impl<S: Clone, C> Clone for StateMachineDef<S, C> {
+    fn clone(&self) -> Self {
+        Self {
+            initial: self.initial.clone(),
+            states: self.states.clone(),
+            transitions: self.transitions.clone(),
+        }
+    }
+}
+
+impl<S: Clone + PartialEq + Debug, C> StateMachineDef<S, C> {
+    pub fn new(initial: S, states: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            initial,
+            states: states.into_iter().collect(),
+            transitions: Vec::new(),
+        }
+    }
+
+    /// Declare a transition from one state to another.
+    pub fn transition(
+        mut self,
+        from: S,
+        to: S,
+        guard: impl Fn(&C, &StateMachine<S>) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.transitions.push(TransitionDef {
+            from: Some(from),
+            to,
+            guard: Arc::new(guard),
+        });
+        self
+    }
+
+    /// Declare a transition from every other state.
+    pub fn transition_from_any(
+        mut self,
+        to: S,
+        guard: impl Fn(&C, &StateMachine<S>) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.transitions.push(TransitionDef {
+            from: None,
+            to,
+            guard: Arc::new(guard),
+        });
+        self
+    }
+
+    /// A machine in the initial state.
+    pub fn start(&self) -> StateMachine<S> {
+        StateMachine::new(self.initial.clone())
+    }
+
+    /// The state the first passing transition leads to, if any.
+    pub fn next(&self, machine: &StateMachine<S>, context: &C) -> Option<&S> {
+        self.transitions
+            .iter()
+            .filter(|t| match &t.from {
+                Some(from) => machine.is(from),
+                None => !machine.is(&t.to),
+            })
+            .find(|t| (t.guard)(context, machine))
+            .map(|t| &t.to)
+    }
+
+    /// Take the first passing transition, if any.
+    pub fn step(&self, machine: &mut StateMachine<S>, context: &C) -> Option<StateTransition<S>> {
+        let to = self.next(machine, context)?.clone();
+        Some(machine.enter(to))
+    }
+
+    /// Describe undeclared states used by the machine.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if !self.states.contains(&self.initial) {
+            problems.push(format!("Initial state {:?} is not declared", self.initial));
+        }
+        for (index, state) in self.states.iter().enumerate() {
+            if self.states[..index].contains(state) {
+                problems.push(format!("State {state:?} is declared twice"));
+            }
+        }
+        for transition in &self.transitions {
+            for state in transition.from.iter().chain([&transition.to]) {
+                if !self.states.contains(state) {
+                    problems.push(format!("Transition uses undeclared state {state:?}"));
+                }
+            }
+        }
+        problems
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+    enum Door {
+        Closed,
+        Open,
+        Locked,
+        Broken,
+    }
+
+    struct Context {
+        has_key: bool,
+        health: i32,
+    }
+
+    fn door() -> StateMachineDef<Door, Context> {
+        StateMachineDef::new(
+            Door::Locked,
+            [Door::Closed, Door::Open, Door::Locked, Door::Broken],
+        )
+        .transition_from_any(Door::Broken, |c: &Context, _| c.health <= 0)
+        .transition(Door::Locked, Door::Closed, |c, _| c.has_key)
+        .transition(Door::Closed, Door::Open, |_, _| true)
+        .transition(Door::Open, Door::Closed, |_, m| m.time_in_state() >= 2.0)
+    }
+
+    #[test]
+    fn guarded_transitions_follow_declaration_order() {
+        let def = door();
+        assert_eq!(def.validate(), Vec::<String>::new());
+        let mut machine = def.start();
+        let mut context = Context {
+            has_key: false,
+            health: 10,
+        };
+
+        assert_eq!(def.step(&mut machine, &context), None);
+        context.has_key = true;
+        assert_eq!(def.step(&mut machine, &context).unwrap().to, Door::Closed);
+        assert_eq!(def.step(&mut machine, &context).unwrap().to, Door::Open);
+
+        // Closes again only after staying open for a while
+        machine.tick(1.0);
+        assert_eq!(def.step(&mut machine, &context), None);
+        machine.tick(1.5);
+        let transition = def.step(&mut machine, &context).unwrap();
+        assert_eq!(transition.after, 2.5);
+
+        // Transitions from any state win when declared first, but never loop
+        context.health = 0;
+        assert_eq!(def.step(&mut machine, &context).unwrap().to, Door::Broken);
+        assert_eq!(def.step(&mut machine, &context), None);
+
+        let history: Vec<_> = machine.history().map(|t| (t.from, t.to)).collect();
+        assert_eq!(
+            history,
+            vec![
+                (Door::Locked, Door::Closed),
+                (Door::Closed, Door::Open),
+                (Door::Open, Door::Closed),
+                (Door::Closed, Door::Broken),
+            ]
+        );
+    }
+
+    #[test]
+    fn history_is_bounded_and_machines_serialize() {
+        let mut machine = StateMachine::new(Door::Closed);
+        for i in 0..HISTORY_LEN + 4 {
+            machine.enter(if i % 2 == 0 { Door::Open } else { Door::Closed });
+        }
+        assert_eq!(machine.history().count(), HISTORY_LEN);
+        machine.tick(0.5);
+
+        let json = serde_json::to_string(&machine).unwrap();
+        let restored: StateMachine<Door> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, machine);
+
+        let undeclared = StateMachineDef::<Door, Context>::new(Door::Closed, [Door::Open])
+            .transition(Door::Open, Door::Broken, |_, _| true);
+        assert_eq!(undeclared.validate().len(), 2);
+    }
+}
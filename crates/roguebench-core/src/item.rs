@@ -0,0 +1,86 @@
+//! Item definitions.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::ContentDef;
+
+/// How rare an item is. Drives loot weighting and display colour.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Rarity {
+    #[default]
+    Common,
+    Uncommon,
+    Rare,
+    Epic,
+    Legendary,
+}
+
+/// Equipment slot an item occupies when equipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EquipSlot {
+    Head,
+    Body,
+    MainHand,
+    OffHand,
+    Accessory,
+}
+
+/// What happens when an item is used from the inventory.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum UseEffect {
+    /// Restore health to the user.
+    Heal { amount: i32 },
+}
+
+/// Definition of an item as stored in the content database.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ItemDef {
+    pub id: Uuid,
+    pub name: String,
+    /// Maximum number of this item that fits in one inventory slot.
+    #[schemars(range(min = 1))]
+    pub max_stack: u32,
+    #[serde(default)]
+    pub rarity: Rarity,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub equip_slot: Option<EquipSlot>,
+    #[serde(default)]
+    pub use_effect: Option<UseEffect>,
+}
+
+impl ItemDef {
+    pub fn new(name: impl Into<String>, max_stack: u32) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            max_stack,
+            rarity: Rarity::default(),
+            tags: Vec::new(),
+            equip_slot: None,
+            use_effect: None,
+        }
+    }
+}
+
+impl ContentDef for ItemDef {
+    const KIND: &'static str = "item";
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.max_stack == 0 {
+            problems.push("Max stack must be at least 1".to_string());
+        }
+        problems
+    }
+}
@@ -2,30 +2,117 @@
 //!
 //! This crate contains pure data structures with no Bevy dependency.
 
+mod ai;
+mod content;
+mod currency;
+mod dialogue;
+mod encounter;
+mod faction;
+mod fsm;
+mod item;
+mod progression;
+mod quest;
+mod room;
+mod save;
+mod schema;
+mod shop;
+mod status;
+mod tileset;
+mod weapon;
+
+pub use ai::{AiProfile, AiState};
+pub use content::ContentDef;
+pub use currency::CurrencyDef;
+pub use dialogue::{
+    DialogueChoice, DialogueDef, DialogueEffect, DialogueIssue, DialogueNode, FlagCondition,
+};
+pub use encounter::{
+    ClearCondition, EncounterDef, MAX_WAVE_SPAWNS, SpawnSelection, WaveDef, WaveSpawn,
+};
+pub use faction::{FactionDef, Relation};
+pub use fsm::{Guard, HISTORY_LEN, StateMachine, StateMachineDef, StateTransition, TransitionDef};
+pub use item::{EquipSlot, ItemDef, Rarity, UseEffect};
+pub use progression::{
+    LifetimeStats, PlayerProfile, RunOutcome, RunSummary, UnlockCondition, UnlockDef,
+};
+pub use quest::{
+    Objective, ObjectiveOrder, QuestDef, QuestLog, QuestProgress, QuestReward, QuestTrigger,
+};
+pub use room::{Direction, RoomDef, RoomExit, SpawnPoint, TileKind, grid_index};
+pub use save::SaveData;
+pub use schema::{CONTENT_KIND_KEYWORD, content_schema};
+pub use shop::{Pricing, Restock, ShopDef, StockEntry};
+pub use status::{Stacking, Stat, StatModifier, StatusEffectDef};
+pub use tileset::{MAX_TILE_VARIANTS, MAX_TILE_WEIGHT, Sockets, TileVariant, TilesetDef};
+pub use weapon::WeaponDef;
+
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+/// Fixed timestep the server simulates and synchronizes at (60 Hz).
+pub const FIXED_TIMESTEP_HZ: f64 = 60.0;
+
 /// Definition of an entity as stored in the content database.
 ///
 /// This is the "template" that gets authored via the web editor.
 /// The runtime spawns game entities based on these definitions.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct EntityDef {
     pub id: Uuid,
+    /// Short handle tools can refer to the definition by, e.g. `goblin-archer`.
+    ///
+    /// Unlike the name it is unique among entity definitions.
+    #[serde(default)]
+    pub slug: Option<String>,
     pub name: String,
     pub health: i32,
+    /// Dialogue started when a player talks to this entity.
+    #[serde(default)]
+    #[schemars(extend("x-content-kind" = "dialogue"))]
+    pub dialogue: Option<Uuid>,
+    /// How the entity behaves on its own; `None` for entities that don't act.
+    #[serde(default)]
+    pub ai: Option<AiProfile>,
+    /// Weapon the entity spawns holding.
+    #[serde(default)]
+    #[schemars(extend("x-content-kind" = "weapon"))]
+    pub weapon: Option<Uuid>,
+    /// Faction the entity fights for; `None` for unaffiliated entities.
+    #[serde(default)]
+    #[schemars(extend("x-content-kind" = "faction"))]
+    pub faction: Option<Uuid>,
+    /// Shop the entity runs.
+    #[serde(default)]
+    #[schemars(extend("x-content-kind" = "shop"))]
+    pub shop: Option<Uuid>,
 }
 
 impl EntityDef {
     pub fn new(name: impl Into<String>, health: i32) -> Self {
         Self {
             id: Uuid::new_v4(),
+            slug: None,
             name: name.into(),
             health,
+            dialogue: None,
+            ai: None,
+            weapon: None,
+            faction: None,
+            shop: None,
         }
     }
 }
 
 pub mod prelude {
-    pub use crate::EntityDef;
+    pub use crate::{
+        AiProfile, AiState, ClearCondition, ContentDef, CurrencyDef, DialogueChoice, DialogueDef,
+        DialogueEffect, DialogueIssue, DialogueNode, Direction, EncounterDef, EntityDef, EquipSlot,
+        FactionDef, FlagCondition, ItemDef, LifetimeStats, Objective, ObjectiveOrder,
+        PlayerProfile, Pricing, QuestDef, QuestLog, QuestProgress, QuestReward, QuestTrigger,
+        Rarity, Relation, Restock, RoomDef, RoomExit, RunOutcome, RunSummary, SaveData, ShopDef,
+        Sockets, SpawnPoint, SpawnSelection, Stacking, Stat, StatModifier, StateMachine,
+        StateMachineDef, StateTransition, StatusEffectDef, StockEntry, TileKind, TileVariant,
+        TilesetDef, UnlockCondition, UnlockDef, UseEffect, WaveDef, WaveSpawn, WeaponDef,
+    };
 }
@@ -0,0 +1,245 @@
+//! Meta-progression: player profiles and unlockable content.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::ContentDef;
+
+/// How a run ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RunOutcome {
+    Won,
+    Died,
+    /// The run was given up or interrupted.
+    Abandoned,
+}
+
+/// What one player achieved during a run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunSummary {
+    pub outcome: RunOutcome,
+    pub kills: u32,
+    /// Balances of persistent currencies held when the run ended.
+    pub banked: BTreeMap<Uuid, u32>,
+}
+
+/// Totals kept across every run a player has played.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct LifetimeStats {
+    pub runs: u32,
+    pub wins: u32,
+    pub deaths: u32,
+    pub kills: u32,
+}
+
+/// Progress that survives between runs, keyed by a stable player ID.
+///
+/// Profiles are written by the engine at the end of every run.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PlayerProfile {
+    pub id: Uuid,
+    /// IDs of the [`UnlockDef`]s the player has earned.
+    #[serde(default)]
+    pub unlocked: BTreeSet<Uuid>,
+    #[serde(default)]
+    pub stats: LifetimeStats,
+    /// Balances of persistent currencies.
+    #[serde(default)]
+    pub currencies: BTreeMap<Uuid, u32>,
+}
+
+impl PlayerProfile {
+    pub fn new(id: Uuid) -> Self {
+        Self {
+            id,
+            ..Default::default()
+        }
+    }
+
+    /// Add a finished run to the lifetime stats and bank its currencies.
+    pub fn record_run(&mut self, run: &RunSummary) {
+        let stats = &mut self.stats;
+        stats.runs = stats.runs.saturating_add(1);
+        stats.kills = stats.kills.saturating_add(run.kills);
+        match run.outcome {
+            RunOutcome::Won => stats.wins = stats.wins.saturating_add(1),
+            RunOutcome::Died => stats.deaths = stats.deaths.saturating_add(1),
+            RunOutcome::Abandoned => {}
+        }
+        for (currency, amount) in &run.banked {
+            let balance = self.currencies.entry(*currency).or_insert(0);
+            *balance = balance.saturating_add(*amount);
+        }
+    }
+}
+
+impl ContentDef for PlayerProfile {
+    const KIND: &'static str = "profile";
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+}
+
+/// Something a player has to achieve to earn an unlock.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum UnlockCondition {
+    /// Finish at least `count` runs, however they ended.
+    Runs { count: u32 },
+    /// Win at least `count` runs.
+    Wins { count: u32 },
+    /// Kill at least `count` enemies over all runs.
+    Kills { count: u32 },
+    /// Kill at least `count` enemies in the run that just ended.
+    KillsInRun { count: u32 },
+    /// Win the run that just ended.
+    WinRun,
+    /// Bank at least `amount` of a persistent currency.
+    Currency {
+        #[schemars(extend("x-content-kind" = "currency"))]
+        currency: Uuid,
+        amount: u32,
+    },
+    /// Have earned another unlock first.
+    Unlocked {
+        #[schemars(extend("x-content-kind" = "unlock"))]
+        unlock: Uuid,
+    },
+}
+
+impl UnlockCondition {
+    /// Whether the condition holds for a profile that has just recorded `run`.
+    pub fn is_met(&self, profile: &PlayerProfile, run: &RunSummary) -> bool {
+        let stats = &profile.stats;
+        match self {
+            UnlockCondition::Runs { count } => stats.runs >= *count,
+            UnlockCondition::Wins { count } => stats.wins >= *count,
+            UnlockCondition::Kills { count } => stats.kills >= *count,
+            UnlockCondition::KillsInRun { count } => run.kills >= *count,
+            UnlockCondition::WinRun => run.outcome == RunOutcome::Won,
+            UnlockCondition::Currency { currency, amount } => {
+                profile.currencies.get(currency).copied().unwrap_or(0) >= *amount
+            }
+            UnlockCondition::Unlocked { unlock } => profile.unlocked.contains(unlock),
+        }
+    }
+}
+
+/// Definition of an unlock as stored in the content database.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct UnlockDef {
+    pub id: Uuid,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// All of these must hold at the end of a run to earn the unlock.
+    pub conditions: Vec<UnlockCondition>,
+    /// Content of any kind made available by the unlock.
+    #[serde(default)]
+    pub content: Vec<Uuid>,
+}
+
+impl UnlockDef {
+    pub fn new(name: impl Into<String>, conditions: Vec<UnlockCondition>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            description: String::new(),
+            conditions,
+            content: Vec::new(),
+        }
+    }
+
+    /// Whether every condition holds for a profile that has just recorded `run`.
+    pub fn is_met(&self, profile: &PlayerProfile, run: &RunSummary) -> bool {
+        self.conditions
+            .iter()
+            .all(|condition| condition.is_met(profile, run))
+    }
+}
+
+impl ContentDef for UnlockDef {
+    const KIND: &'static str = "unlock";
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.name.trim().is_empty() {
+            problems.push("Unlock needs a name".to_string());
+        }
+        if self.conditions.is_empty() {
+            problems.push("Unlock needs at least one condition".to_string());
+        }
+        let depends_on_itself = self.conditions.iter().any(
+            |condition| matches!(condition, UnlockCondition::Unlocked { unlock } if *unlock == self.id),
+        );
+        if depends_on_itself {
+            problems.push("Unlock can't require itself".to_string());
+        }
+        problems
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_accumulate_into_stats_that_meet_conditions() {
+        let souls = Uuid::new_v4();
+        let mut profile = PlayerProfile::new(Uuid::new_v4());
+        let run = |outcome, kills| RunSummary {
+            outcome,
+            kills,
+            banked: BTreeMap::from([(souls, 30)]),
+        };
+        let veteran = UnlockDef::new(
+            "Veteran",
+            vec![
+                UnlockCondition::Kills { count: 10 },
+                UnlockCondition::Currency {
+                    currency: souls,
+                    amount: 50,
+                },
+            ],
+        );
+
+        let first = run(RunOutcome::Died, 6);
+        profile.record_run(&first);
+        assert!(!veteran.is_met(&profile, &first));
+
+        let second = run(RunOutcome::Won, 5);
+        profile.record_run(&second);
+        assert_eq!(
+            profile.stats,
+            LifetimeStats {
+                runs: 2,
+                wins: 1,
+                deaths: 1,
+                kills: 11,
+            }
+        );
+        assert!(veteran.is_met(&profile, &second));
+        assert!(!UnlockCondition::KillsInRun { count: 6 }.is_met(&profile, &second));
+        assert!(!UnlockCondition::Unlocked { unlock: veteran.id }.is_met(&profile, &second));
+
+        // Totals stop at the maximum instead of wrapping
+        profile.stats.kills = u32::MAX - 1;
+        profile.record_run(&second);
+        assert_eq!(profile.stats.kills, u32::MAX);
+
+        let mut circular = UnlockDef::new("", Vec::new());
+        circular.conditions.push(UnlockCondition::Unlocked {
+            unlock: circular.id,
+        });
+        assert_eq!(circular.validate().len(), 2);
+    }
+}
@@ -0,0 +1,351 @@
+//! Quest definitions and per-player quest progress.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::ContentDef;
+
+/// Whether a quest's objectives must be completed one after another.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ObjectiveOrder {
+    /// Only the first unfinished objective makes progress.
+    #[default]
+    Ordered,
+    /// All objectives make progress at the same time.
+    Parallel,
+}
+
+/// Something the player has to do to advance a quest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum Objective {
+    /// Kill `count` entities spawned from the given entity template.
+    Kill {
+        #[schemars(extend("x-content-kind" = "entity"))]
+        template: Uuid,
+        #[schemars(range(min = 1))]
+        count: u32,
+    },
+    /// Pick up `count` of the given item.
+    Collect {
+        #[schemars(extend("x-content-kind" = "item"))]
+        item: Uuid,
+        #[schemars(range(min = 1))]
+        count: u32,
+    },
+    /// Enter the given room.
+    ReachRoom {
+        #[schemars(extend("x-content-kind" = "room"))]
+        room: Uuid,
+    },
+    /// Talk to an NPC spawned from the given entity template.
+    TalkTo {
+        #[schemars(extend("x-content-kind" = "entity"))]
+        npc: Uuid,
+    },
+}
+
+impl Objective {
+    /// How much progress is needed to complete the objective.
+    pub fn required(&self) -> u32 {
+        match self {
+            Objective::Kill { count, .. } | Objective::Collect { count, .. } => *count,
+            Objective::ReachRoom { .. } | Objective::TalkTo { .. } => 1,
+        }
+    }
+
+    /// How much progress a gameplay happening counts towards this objective.
+    pub fn progress_for(&self, trigger: &QuestTrigger) -> u32 {
+        match (self, trigger) {
+            (Objective::Kill { template, .. }, QuestTrigger::Killed { template: killed })
+                if template == killed =>
+            {
+                1
+            }
+            (
+                Objective::Collect { item, .. },
+                QuestTrigger::Collected {
+                    item: collected,
+                    count,
+                },
+            ) if item == collected => *count,
+            (Objective::ReachRoom { room }, QuestTrigger::EnteredRoom { room: entered })
+                if room == entered =>
+            {
+                1
+            }
+            (Objective::TalkTo { npc }, QuestTrigger::TalkedTo { npc: talked })
+                if npc == talked =>
+            {
+                1
+            }
+            _ => 0,
+        }
+    }
+}
+
+/// Gameplay happenings that can advance objectives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuestTrigger {
+    Killed { template: Uuid },
+    Collected { item: Uuid, count: u32 },
+    EnteredRoom { room: Uuid },
+    TalkedTo { npc: Uuid },
+}
+
+/// Reward granted when a quest is completed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum QuestReward {
+    /// Add items to the player's inventory.
+    Item {
+        #[schemars(extend("x-content-kind" = "item"))]
+        item: Uuid,
+        #[schemars(range(min = 1))]
+        count: u32,
+    },
+    /// Raise a story flag.
+    SetFlag { flag: String },
+}
+
+/// Definition of a quest as stored in the content database.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct QuestDef {
+    pub id: Uuid,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub order: ObjectiveOrder,
+    pub objectives: Vec<Objective>,
+    /// Quests that must be completed before this one can be started.
+    #[serde(default)]
+    #[schemars(extend("x-content-kind" = "quest"))]
+    pub prerequisites: Vec<Uuid>,
+    #[serde(default)]
+    pub rewards: Vec<QuestReward>,
+}
+
+impl QuestDef {
+    pub fn new(name: impl Into<String>, objectives: Vec<Objective>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            description: String::new(),
+            order: ObjectiveOrder::default(),
+            objectives,
+            prerequisites: Vec::new(),
+            rewards: Vec::new(),
+        }
+    }
+}
+
+impl ContentDef for QuestDef {
+    const KIND: &'static str = "quest";
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.objectives.is_empty() {
+            problems.push("Quest has no objectives".to_string());
+        }
+        for (index, objective) in self.objectives.iter().enumerate() {
+            if objective.required() == 0 {
+                problems.push(format!("Objective {index} requires a count of zero"));
+            }
+        }
+        if self.prerequisites.contains(&self.id) {
+            problems.push("Quest lists itself as a prerequisite".to_string());
+        }
+        problems
+    }
+}
+
+/// A player's progress on one active quest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct QuestProgress {
+    #[schemars(extend("x-content-kind" = "quest"))]
+    pub quest: Uuid,
+    /// Progress per objective, in the same order as [`QuestDef::objectives`].
+    pub counts: Vec<u32>,
+}
+
+impl QuestProgress {
+    pub fn new(quest: &QuestDef) -> Self {
+        Self {
+            quest: quest.id,
+            counts: vec![0; quest.objectives.len()],
+        }
+    }
+
+    /// Whether the objective at `index` has been completed.
+    pub fn is_objective_complete(&self, quest: &QuestDef, index: usize) -> bool {
+        match (quest.objectives.get(index), self.counts.get(index)) {
+            (Some(objective), Some(count)) => *count >= objective.required(),
+            _ => false,
+        }
+    }
+
+    /// Whether every objective has been completed.
+    pub fn is_complete(&self, quest: &QuestDef) -> bool {
+        (0..quest.objectives.len()).all(|index| self.is_objective_complete(quest, index))
+    }
+
+    /// Apply a gameplay happening to the objectives that can currently progress.
+    ///
+    /// Returns the indices of objectives completed by this trigger.
+    pub fn advance(&mut self, quest: &QuestDef, trigger: &QuestTrigger) -> Vec<usize> {
+        // Tolerate definitions that gained objectives since the progress was saved
+        self.counts.resize(quest.objectives.len(), 0);
+
+        let mut completed = Vec::new();
+        for (index, objective) in quest.objectives.iter().enumerate() {
+            let required = objective.required();
+            if self.counts[index] >= required {
+                continue;
+            }
+
+            let progress = objective.progress_for(trigger);
+            self.counts[index] = self.counts[index].saturating_add(progress).min(required);
+            if self.counts[index] >= required {
+                completed.push(index);
+            }
+
+            if quest.order == ObjectiveOrder::Ordered {
+                break;
+            }
+        }
+        completed
+    }
+}
+
+/// All of a player's quest progress.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct QuestLog {
+    pub active: Vec<QuestProgress>,
+    #[schemars(extend("x-content-kind" = "quest"))]
+    pub completed: Vec<Uuid>,
+}
+
+impl QuestLog {
+    pub fn is_active(&self, quest: Uuid) -> bool {
+        self.active.iter().any(|progress| progress.quest == quest)
+    }
+
+    pub fn is_completed(&self, quest: Uuid) -> bool {
+        self.completed.contains(&quest)
+    }
+
+    /// Whether the quest is neither active nor completed and all its
+    /// prerequisites have been completed.
+    pub fn can_start(&self, quest: &QuestDef) -> bool {
+        !self.is_active(quest.id)
+            && !self.is_completed(quest.id)
+            && quest
+                .prerequisites
+                .iter()
+                .all(|prerequisite| self.is_completed(*prerequisite))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ordered_objectives_progress_one_at_a_time() {
+        let goblin = Uuid::new_v4();
+        let room = Uuid::new_v4();
+        let quest = QuestDef::new(
+            "Clear the cave",
+            vec![
+                Objective::ReachRoom { room },
+                Objective::Kill {
+                    template: goblin,
+                    count: 2,
+                },
+            ],
+        );
+        let mut progress = QuestProgress::new(&quest);
+        let kill = QuestTrigger::Killed { template: goblin };
+
+        // Kills don't count until the room has been reached
+        assert!(progress.advance(&quest, &kill).is_empty());
+        assert_eq!(progress.counts, vec![0, 0]);
+
+        assert_eq!(
+            progress.advance(&quest, &QuestTrigger::EnteredRoom { room }),
+            vec![0]
+        );
+        assert!(progress.advance(&quest, &kill).is_empty());
+        assert_eq!(progress.advance(&quest, &kill), vec![1]);
+        assert!(progress.is_complete(&quest));
+
+        // Extra kills are ignored once complete
+        assert!(progress.advance(&quest, &kill).is_empty());
+        assert_eq!(progress.counts, vec![1, 2]);
+    }
+
+    #[test]
+    fn parallel_objectives_and_prerequisites() {
+        let herb = Uuid::new_v4();
+        let elder = Uuid::new_v4();
+        let mut quest = QuestDef::new(
+            "Gather herbs",
+            vec![
+                Objective::Collect {
+                    item: herb,
+                    count: 5,
+                },
+                Objective::TalkTo { npc: elder },
+            ],
+        );
+        quest.order = ObjectiveOrder::Parallel;
+        let mut progress = QuestProgress::new(&quest);
+
+        assert_eq!(
+            progress.advance(&quest, &QuestTrigger::TalkedTo { npc: elder }),
+            vec![1]
+        );
+        // Collecting more than needed clamps to the requirement
+        let pickup = QuestTrigger::Collected {
+            item: herb,
+            count: 7,
+        };
+        assert_eq!(progress.advance(&quest, &pickup), vec![0]);
+        assert_eq!(progress.counts, vec![5, 1]);
+
+        let mut sequel = QuestDef::new("Brew", vec![Objective::TalkTo { npc: elder }]);
+        sequel.prerequisites.push(quest.id);
+        let mut log = QuestLog::default();
+        assert!(!log.can_start(&sequel));
+        log.completed.push(quest.id);
+        assert!(log.can_start(&sequel));
+
+        sequel.prerequisites.push(sequel.id);
+        assert_eq!(sequel.validate().len(), 1);
+    }
+
+    #[test]
+    fn progress_saturates_instead_of_overflowing() {
+        let coin = Uuid::new_v4();
+        let quest = QuestDef::new(
+            "Hoard",
+            vec![Objective::Collect {
+                item: coin,
+                count: u32::MAX,
+            }],
+        );
+        let mut progress = QuestProgress::new(&quest);
+        let pickup = |count| QuestTrigger::Collected { item: coin, count };
+
+        assert!(progress.advance(&quest, &pickup(5)).is_empty());
+        assert_eq!(progress.advance(&quest, &pickup(u32::MAX)), vec![0]);
+        assert_eq!(progress.counts, vec![u32::MAX]);
+    }
+}
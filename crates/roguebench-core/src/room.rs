@@ -0,0 +1,265 @@
+//! Room and tile grid definitions.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::ContentDef;
+
+/// What occupies one cell of a room's tile grid.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TileKind {
+    #[default]
+    Floor,
+    /// Blocks movement and projectiles.
+    Wall,
+    /// Blocks movement but not projectiles.
+    Pit,
+    /// Walkable, but hurts whoever stands on it.
+    Hazard,
+}
+
+impl TileKind {
+    /// Whether entities can stand on this tile.
+    pub fn is_walkable(self) -> bool {
+        matches!(self, TileKind::Floor | TileKind::Hazard)
+    }
+
+    /// Whether projectiles are stopped by this tile.
+    pub fn blocks_projectiles(self) -> bool {
+        self == TileKind::Wall
+    }
+}
+
+/// Side of a room an exit leads out of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+    ];
+
+    /// The direction pointing the other way.
+    pub fn opposite(self) -> Direction {
+        match self {
+            Direction::North => Direction::South,
+            Direction::East => Direction::West,
+            Direction::South => Direction::North,
+            Direction::West => Direction::East,
+        }
+    }
+}
+
+/// A tile where entities can be spawned.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct SpawnPoint {
+    pub x: u32,
+    pub y: u32,
+    /// Free-form label used to pick spawn points (e.g. `"player"`, `"enemy"`).
+    #[serde(default)]
+    pub tag: String,
+}
+
+/// A tile on the edge of a room that leads to another room.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct RoomExit {
+    pub x: u32,
+    pub y: u32,
+    pub direction: Direction,
+}
+
+/// Index of cell `(x, y)` in a grid `width` tiles wide, stored row by row.
+///
+/// Computed in `usize` so it can't overflow for any grid that fits in memory.
+pub fn grid_index(width: u32, x: u32, y: u32) -> usize {
+    y as usize * width as usize + x as usize
+}
+
+/// Definition of a room as stored in the content database.
+///
+/// Tiles are stored row by row, starting from the bottom row (`y == 0`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct RoomDef {
+    pub id: Uuid,
+    pub name: String,
+    #[schemars(range(min = 1))]
+    pub width: u32,
+    #[schemars(range(min = 1))]
+    pub height: u32,
+    pub tiles: Vec<TileKind>,
+    #[serde(default)]
+    pub spawn_points: Vec<SpawnPoint>,
+    #[serde(default)]
+    pub exits: Vec<RoomExit>,
+}
+
+impl RoomDef {
+    /// Largest width or height a room can have, in tiles.
+    pub const MAX_SIDE: u32 = 256;
+
+    /// Create a room filled with floor tiles.
+    ///
+    /// # Panics
+    ///
+    /// If either side is longer than [`RoomDef::MAX_SIDE`].
+    pub fn new(name: impl Into<String>, width: u32, height: u32) -> Self {
+        assert!(
+            width <= Self::MAX_SIDE && height <= Self::MAX_SIDE,
+            "rooms are at most {0}x{0} tiles, not {width}x{height}",
+            Self::MAX_SIDE
+        );
+        Self {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            width,
+            height,
+            tiles: vec![TileKind::Floor; width as usize * height as usize],
+            spawn_points: Vec::new(),
+            exits: Vec::new(),
+        }
+    }
+
+    fn index(&self, x: u32, y: u32) -> Option<usize> {
+        (x < self.width && y < self.height).then(|| grid_index(self.width, x, y))
+    }
+
+    /// The tile at the given cell, or `None` outside the grid.
+    pub fn tile(&self, x: u32, y: u32) -> Option<TileKind> {
+        self.index(x, y).and_then(|i| self.tiles.get(i).copied())
+    }
+
+    /// Replace the tile at the given cell. Cells outside the grid are ignored.
+    pub fn set_tile(&mut self, x: u32, y: u32, kind: TileKind) {
+        if let Some(tile) = self.index(x, y).and_then(|i| self.tiles.get_mut(i)) {
+            *tile = kind;
+        }
+    }
+
+    /// Whether the cell lies on the edge the direction points at.
+    pub fn is_on_edge(&self, x: u32, y: u32, direction: Direction) -> bool {
+        match direction {
+            Direction::North => y + 1 == self.height,
+            Direction::East => x + 1 == self.width,
+            Direction::South => y == 0,
+            Direction::West => x == 0,
+        }
+    }
+}
+
+impl ContentDef for RoomDef {
+    const KIND: &'static str = "room";
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.width == 0 || self.height == 0 {
+            problems.push("Room must be at least one tile wide and high".to_string());
+        }
+        if self.width > Self::MAX_SIDE || self.height > Self::MAX_SIDE {
+            problems.push(format!("Room can be at most {0}x{0} tiles", Self::MAX_SIDE));
+            return problems;
+        }
+        let cells = (self.width as usize).checked_mul(self.height as usize);
+        if cells != Some(self.tiles.len()) {
+            problems.push(format!(
+                "Room has {} tiles but is {}x{}",
+                self.tiles.len(),
+                self.width,
+                self.height
+            ));
+            return problems;
+        }
+        for spawn in &self.spawn_points {
+            if !self
+                .tile(spawn.x, spawn.y)
+                .is_some_and(TileKind::is_walkable)
+            {
+                problems.push(format!(
+                    "Spawn point ({}, {}) is not on a walkable tile",
+                    spawn.x, spawn.y
+                ));
+            }
+        }
+        for exit in &self.exits {
+            if !self.tile(exit.x, exit.y).is_some_and(TileKind::is_walkable) {
+                problems.push(format!(
+                    "Exit ({}, {}) is not on a walkable tile",
+                    exit.x, exit.y
+                ));
+            } else if !self.is_on_edge(exit.x, exit.y, exit.direction) {
+                problems.push(format!(
+                    "Exit ({}, {}) is not on the {:?} edge",
+                    exit.x, exit.y, exit.direction
+                ));
+            }
+        }
+        problems
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn room_validation_checks_tiles_spawns_and_exits() {
+        let mut room = RoomDef::new("Hall", 4, 3);
+        room.set_tile(1, 1, TileKind::Wall);
+        room.spawn_points.push(SpawnPoint {
+            x: 2,
+            y: 1,
+            tag: "enemy".to_string(),
+        });
+        room.exits.push(RoomExit {
+            x: 3,
+            y: 1,
+            direction: Direction::East,
+        });
+        assert!(room.validate().is_empty());
+        assert_eq!(room.tile(1, 1), Some(TileKind::Wall));
+        assert_eq!(room.tile(4, 0), None);
+
+        room.spawn_points.push(SpawnPoint {
+            x: 1,
+            y: 1,
+            tag: String::new(),
+        });
+        room.exits.push(RoomExit {
+            x: 2,
+            y: 1,
+            direction: Direction::North,
+        });
+        assert_eq!(room.validate().len(), 2);
+
+        room.tiles.pop();
+        assert_eq!(room.validate().len(), 1);
+    }
+
+    #[test]
+    fn room_validation_rejects_oversized_rooms() {
+        let mut room = RoomDef::new("Hall", 4, 3);
+        room.width = 65536;
+        room.height = 65536;
+        assert_eq!(room.validate().len(), 1);
+        assert_eq!(room.tile(70000, 0), None);
+
+        room.width = RoomDef::MAX_SIDE;
+        room.height = 1;
+        room.tiles = vec![TileKind::Floor; RoomDef::MAX_SIDE as usize];
+        assert!(room.validate().is_empty());
+    }
+}
@@ -0,0 +1,38 @@
+//! Per-player save data.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{ContentDef, QuestLog};
+
+/// Everything persisted for one player between sessions.
+///
+/// Saves are stored as documents next to authored content, but are written
+/// by the engine rather than the editor.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SaveData {
+    pub id: Uuid,
+    /// Seed of the run the save was written during.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub quests: QuestLog,
+}
+
+impl SaveData {
+    pub fn new(id: Uuid) -> Self {
+        Self {
+            id,
+            ..Default::default()
+        }
+    }
+}
+
+impl ContentDef for SaveData {
+    const KIND: &'static str = "save";
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+}
@@ -0,0 +1,72 @@
+//! JSON Schemas for authored content.
+//!
+//! Schemas are generated from the content types, so field types, ranges,
+//! enums and doc comments stay in sync with the code. Uuid fields that point
+//! at other content carry a [`CONTENT_KIND_KEYWORD`] naming the kind they
+//! refer to, and the root of each schema names the kind it describes.
+
+use schemars::{JsonSchema, Schema, schema_for};
+
+/// Schema keyword naming a kind of content.
+pub const CONTENT_KIND_KEYWORD: &str = "x-content-kind";
+
+/// The JSON Schema of a content type stored under `kind`.
+pub fn content_schema<T: JsonSchema>(kind: &str) -> Schema {
+    let mut schema = schema_for!(T);
+    schema.insert(CONTENT_KIND_KEYWORD.to_string(), kind.into());
+    schema
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+
+    use crate::{ContentDef, ItemDef, QuestDef};
+
+    #[test]
+    fn schemas_describe_fields_ranges_and_enums() {
+        let schema = ItemDef::schema().to_value();
+        assert_eq!(schema["x-content-kind"], "item");
+        assert_eq!(schema["properties"]["max_stack"]["minimum"], 1);
+        assert_eq!(
+            schema["properties"]["max_stack"]["description"],
+            "Maximum number of this item that fits in one inventory slot."
+        );
+
+        let required = schema["required"].as_array().unwrap();
+        assert!(required.contains(&json!("name")));
+        assert!(!required.contains(&json!("rarity")));
+
+        let rarity = &schema["$defs"]["Rarity"]["enum"];
+        let variants = rarity.as_array().unwrap();
+        assert!(variants.contains(&json!("legendary")));
+    }
+
+    #[test]
+    fn schemas_reference_other_kinds() {
+        let schema = QuestDef::schema().to_value();
+        assert_eq!(schema["x-content-kind"], "quest");
+        assert_eq!(
+            schema["properties"]["prerequisites"]["x-content-kind"],
+            "quest"
+        );
+
+        // Every objective variant names the kind its id points at
+        let objectives = schema["$defs"]["Objective"]["oneOf"].as_array().unwrap();
+        let kinds: Vec<(&Value, &Value)> = objectives
+            .iter()
+            .flat_map(|variant| variant["properties"].as_object().unwrap().values())
+            .filter(|property| property.get("x-content-kind").is_some())
+            .map(|property| (&property["format"], &property["x-content-kind"]))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (&json!("uuid"), &json!("entity")),
+                (&json!("uuid"), &json!("item")),
+                (&json!("uuid"), &json!("room")),
+                (&json!("uuid"), &json!("entity")),
+            ]
+        );
+    }
+}
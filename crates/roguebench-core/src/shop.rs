@@ -0,0 +1,223 @@
+//! Shop definitions: stock, pricing and restocking.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{ContentDef, ItemDef, Rarity};
+
+fn default_rarity_multiplier() -> f32 {
+    2.0
+}
+
+fn default_sell_ratio() -> f32 {
+    0.5
+}
+
+/// One line of a shop's stock.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum StockEntry {
+    /// A specific item.
+    Item {
+        #[schemars(extend("x-content-kind" = "item"))]
+        item: Uuid,
+        /// Units on sale per restock; `None` for unlimited.
+        #[serde(default)]
+        #[schemars(range(min = 1))]
+        quantity: Option<u32>,
+        /// Fixed price overriding the shop's pricing.
+        #[serde(default)]
+        price: Option<u32>,
+    },
+    /// Items drawn at random from the item catalogue on every restock.
+    Random {
+        /// Only items with this tag.
+        #[serde(default)]
+        tag: Option<String>,
+        /// Only items of this rarity.
+        #[serde(default)]
+        rarity: Option<Rarity>,
+        /// Number of different items drawn.
+        #[schemars(range(min = 1))]
+        picks: u32,
+        /// Units of each drawn item on sale; `None` for unlimited.
+        #[serde(default)]
+        #[schemars(range(min = 1))]
+        quantity: Option<u32>,
+    },
+}
+
+impl StockEntry {
+    /// Whether an item can be drawn for this entry.
+    pub fn matches(&self, item: &ItemDef) -> bool {
+        match self {
+            StockEntry::Item { item: id, .. } => *id == item.id,
+            StockEntry::Random { tag, rarity, .. } => {
+                tag.as_ref().is_none_or(|tag| item.tags.contains(tag))
+                    && rarity.is_none_or(|rarity| rarity == item.rarity)
+            }
+        }
+    }
+}
+
+/// How a shop prices items.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Pricing {
+    /// Price of a common item.
+    pub base_price: u32,
+    /// Each rarity step above common multiplies the price by this.
+    #[serde(default = "default_rarity_multiplier")]
+    #[schemars(range(min = 0.0))]
+    pub rarity_multiplier: f32,
+    /// Fraction of the buy price the shop pays for items sold to it; 0 buys nothing.
+    #[serde(default = "default_sell_ratio")]
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub sell_ratio: f32,
+}
+
+impl Pricing {
+    pub fn new(base_price: u32) -> Self {
+        Self {
+            base_price,
+            rarity_multiplier: default_rarity_multiplier(),
+            sell_ratio: default_sell_ratio(),
+        }
+    }
+
+    /// Price a player pays for one unit of an item.
+    pub fn buy_price(&self, item: &ItemDef) -> u32 {
+        let steps = item.rarity as i32;
+        (self.base_price as f32 * self.rarity_multiplier.powi(steps)).round() as u32
+    }
+
+    /// Price the shop pays for one unit of an item.
+    pub fn sell_price(&self, item: &ItemDef) -> u32 {
+        (self.buy_price(item) as f32 * self.sell_ratio).floor() as u32
+    }
+}
+
+/// When a shop's stock is rolled again.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum Restock {
+    /// Stock is rolled once and sells out for good.
+    #[default]
+    Never,
+    /// Stock is rolled again every `seconds`.
+    Interval {
+        #[schemars(range(min = 0.0))]
+        seconds: f32,
+    },
+    /// Stock is rolled again whenever a new room is loaded.
+    EveryRoom,
+}
+
+/// Definition of a shop as stored in the content database.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ShopDef {
+    pub id: Uuid,
+    pub name: String,
+    /// Currency prices are paid in.
+    #[schemars(extend("x-content-kind" = "currency"))]
+    pub currency: Uuid,
+    #[serde(default)]
+    pub stock: Vec<StockEntry>,
+    pub pricing: Pricing,
+    #[serde(default)]
+    pub restock: Restock,
+}
+
+impl ShopDef {
+    pub fn new(name: impl Into<String>, currency: Uuid, pricing: Pricing) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            currency,
+            stock: Vec::new(),
+            pricing,
+            restock: Restock::default(),
+        }
+    }
+}
+
+impl ContentDef for ShopDef {
+    const KIND: &'static str = "shop";
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.name.trim().is_empty() {
+            problems.push("Shop needs a name".to_string());
+        }
+        for (index, entry) in self.stock.iter().enumerate() {
+            let (quantity, picks) = match entry {
+                StockEntry::Item { quantity, .. } => (quantity, None),
+                StockEntry::Random {
+                    quantity, picks, ..
+                } => (quantity, Some(*picks)),
+            };
+            if *quantity == Some(0) {
+                problems.push(format!("Stock entry {index} has a quantity of 0"));
+            }
+            if picks == Some(0) {
+                problems.push(format!("Stock entry {index} draws no items"));
+            }
+        }
+        let pricing = &self.pricing;
+        if pricing.rarity_multiplier.is_nan() || pricing.rarity_multiplier < 0.0 {
+            problems.push("Rarity multiplier can't be negative".to_string());
+        }
+        if !(0.0..=1.0).contains(&pricing.sell_ratio) {
+            problems.push("Sell ratio must be a fraction between 0 and 1".to_string());
+        }
+        let interval = match self.restock {
+            Restock::Interval { seconds } => Some(seconds),
+            _ => None,
+        };
+        if interval.is_some_and(|seconds| seconds.is_nan() || seconds <= 0.0) {
+            problems.push("Restock interval must be positive".to_string());
+        }
+        problems
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prices_scale_with_rarity_and_stock_filters_items() {
+        let pricing = Pricing::new(10);
+        let mut ring = ItemDef::new("Ring", 1);
+        ring.rarity = Rarity::Rare;
+        ring.tags.push("jewelry".to_string());
+        let potion = ItemDef::new("Potion", 5);
+
+        assert_eq!(pricing.buy_price(&potion), 10);
+        assert_eq!(pricing.sell_price(&potion), 5);
+        assert_eq!(pricing.buy_price(&ring), 40);
+
+        let jewelry = StockEntry::Random {
+            tag: Some("jewelry".to_string()),
+            rarity: None,
+            picks: 1,
+            quantity: None,
+        };
+        assert!(jewelry.matches(&ring));
+        assert!(!jewelry.matches(&potion));
+
+        let mut shop = ShopDef::new("Stall", Uuid::new_v4(), pricing);
+        shop.pricing.sell_ratio = 2.0;
+        shop.restock = Restock::Interval { seconds: 0.0 };
+        shop.stock.push(StockEntry::Item {
+            item: potion.id,
+            quantity: Some(0),
+            price: None,
+        });
+        assert_eq!(shop.validate().len(), 3);
+    }
+}
@@ -0,0 +1,168 @@
+//! Status effect definitions.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{ContentDef, FIXED_TIMESTEP_HZ};
+
+fn default_max_stacks() -> u32 {
+    1
+}
+
+/// What happens when an effect is applied to an entity that already has it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Stacking {
+    /// Restart the existing effect's duration.
+    #[default]
+    Refresh,
+    /// Add a stack to the existing effect and restart its duration.
+    Intensity,
+    /// Run another copy alongside the existing ones, each with its own timer.
+    Independent,
+}
+
+/// A stat that status effects can scale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Stat {
+    MoveSpeed,
+    /// How fast weapons cool down and melee attacks land; 0 stops attacks entirely.
+    AttackSpeed,
+    DamageDealt,
+    DamageTaken,
+}
+
+/// Multiplies one stat while an effect is active.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct StatModifier {
+    pub stat: Stat,
+    /// Factor applied once per stack, e.g. 0.5 halves the stat.
+    #[schemars(range(min = 0.0))]
+    pub multiplier: f32,
+}
+
+/// Definition of a status effect as stored in the content database.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct StatusEffectDef {
+    pub id: Uuid,
+    pub name: String,
+    /// Seconds the effect lasts.
+    #[schemars(range(min = 0.0))]
+    pub duration: f32,
+    /// Seconds between periodic ticks; 0 never ticks.
+    #[serde(default)]
+    #[schemars(range(min = 0.0))]
+    pub tick_interval: f32,
+    #[serde(default)]
+    pub stacking: Stacking,
+    /// Most stacks, or copies for independent effects, an entity can have.
+    #[serde(default = "default_max_stacks")]
+    #[schemars(range(min = 1))]
+    pub max_stacks: u32,
+    #[serde(default)]
+    pub modifiers: Vec<StatModifier>,
+    /// Damage dealt per tick and stack; negative values heal.
+    #[serde(default)]
+    pub tick_damage: i32,
+}
+
+impl StatusEffectDef {
+    /// Shortest tick interval allowed: one server tick.
+    pub const MIN_TICK_INTERVAL: f32 = (1.0 / FIXED_TIMESTEP_HZ) as f32;
+
+    pub fn new(name: impl Into<String>, duration: f32) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            duration,
+            tick_interval: 0.0,
+            stacking: Stacking::default(),
+            max_stacks: default_max_stacks(),
+            modifiers: Vec::new(),
+            tick_damage: 0,
+        }
+    }
+
+    /// Combined multiplier for a stat at the given number of stacks.
+    pub fn multiplier(&self, stat: Stat, stacks: u32) -> f32 {
+        self.modifiers
+            .iter()
+            .filter(|modifier| modifier.stat == stat)
+            .map(|modifier| modifier.multiplier.powi(stacks as i32))
+            .product()
+    }
+}
+
+impl ContentDef for StatusEffectDef {
+    const KIND: &'static str = "status_effect";
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.duration.is_nan() || self.duration <= 0.0 {
+            problems.push("Duration must be positive".to_string());
+        }
+        if self.tick_interval.is_nan() || self.tick_interval < 0.0 {
+            problems.push("Tick interval can't be negative".to_string());
+        }
+        if self.tick_damage != 0 && self.tick_interval <= 0.0 {
+            problems.push("Effects with tick damage need a tick interval".to_string());
+        }
+        if self.tick_interval > 0.0 && self.tick_interval < Self::MIN_TICK_INTERVAL {
+            problems.push(format!(
+                "Tick interval must be at least one server tick ({}s)",
+                Self::MIN_TICK_INTERVAL
+            ));
+        }
+        if self.max_stacks == 0 {
+            problems.push("Max stacks must be at least 1".to_string());
+        }
+        for modifier in &self.modifiers {
+            if modifier.multiplier.is_nan() || modifier.multiplier < 0.0 {
+                problems.push(format!("{:?} multiplier can't be negative", modifier.stat));
+            }
+        }
+        problems
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modifiers_compound_per_stack() {
+        let mut slow = StatusEffectDef::new("Slow", 4.0);
+        slow.modifiers = vec![
+            StatModifier {
+                stat: Stat::MoveSpeed,
+                multiplier: 0.5,
+            },
+            StatModifier {
+                stat: Stat::MoveSpeed,
+                multiplier: 0.8,
+            },
+        ];
+        assert!((slow.multiplier(Stat::MoveSpeed, 1) - 0.4).abs() < 1e-6);
+        assert!((slow.multiplier(Stat::MoveSpeed, 2) - 0.16).abs() < 1e-6);
+        assert_eq!(slow.multiplier(Stat::DamageTaken, 3), 1.0);
+
+        let mut poison = StatusEffectDef::new("Poison", 0.0);
+        poison.tick_damage = 2;
+        poison.max_stacks = 0;
+        assert_eq!(poison.validate().len(), 3);
+
+        // Ticks can't come faster than the server runs
+        let mut bleed = StatusEffectDef::new("Bleed", 2.0);
+        bleed.tick_damage = 1;
+        bleed.tick_interval = 1e-6;
+        assert_eq!(bleed.validate().len(), 1);
+        bleed.tick_interval = StatusEffectDef::MIN_TICK_INTERVAL;
+        assert!(bleed.validate().is_empty());
+    }
+}
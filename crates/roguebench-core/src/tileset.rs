@@ -0,0 +1,145 @@
+//! Tilesets for procedural room generation.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{ContentDef, Direction, TileKind};
+
+/// Most tile variants a tileset can hold.
+pub const MAX_TILE_VARIANTS: usize = 64;
+
+/// Largest weight a tile variant can have, so weights always sum to a finite total.
+pub const MAX_TILE_WEIGHT: f32 = 1_000_000.0;
+
+/// Socket labels on the four sides of a tile variant.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Sockets {
+    pub north: String,
+    pub east: String,
+    pub south: String,
+    pub west: String,
+}
+
+impl Sockets {
+    /// The same socket on every side.
+    pub fn uniform(socket: impl Into<String>) -> Self {
+        let socket = socket.into();
+        Self {
+            north: socket.clone(),
+            east: socket.clone(),
+            south: socket.clone(),
+            west: socket,
+        }
+    }
+
+    /// The socket on the given side.
+    pub fn side(&self, direction: Direction) -> &str {
+        match direction {
+            Direction::North => &self.north,
+            Direction::East => &self.east,
+            Direction::South => &self.south,
+            Direction::West => &self.west,
+        }
+    }
+}
+
+fn default_weight() -> f32 {
+    1.0
+}
+
+/// A tile the generator can place, with the sockets it exposes to its neighbours.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct TileVariant {
+    pub name: String,
+    pub kind: TileKind,
+    /// Relative likelihood of the variant being picked.
+    #[serde(default = "default_weight")]
+    #[schemars(range(min = 0.0, max = MAX_TILE_WEIGHT))]
+    pub weight: f32,
+    pub sockets: Sockets,
+}
+
+impl TileVariant {
+    pub fn new(name: impl Into<String>, kind: TileKind, sockets: Sockets) -> Self {
+        Self {
+            name: name.into(),
+            kind,
+            weight: default_weight(),
+            sockets,
+        }
+    }
+}
+
+/// Definition of a tileset as stored in the content database.
+///
+/// Two variants may sit side by side when the sockets facing each other are
+/// equal, or are listed together in `connections`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct TilesetDef {
+    pub id: Uuid,
+    pub name: String,
+    pub variants: Vec<TileVariant>,
+    /// Pairs of different sockets that may face each other.
+    #[serde(default)]
+    pub connections: Vec<[String; 2]>,
+}
+
+impl TilesetDef {
+    pub fn new(name: impl Into<String>, variants: Vec<TileVariant>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            variants,
+            connections: Vec::new(),
+        }
+    }
+
+    /// Whether two sockets may face each other.
+    pub fn connects(&self, a: &str, b: &str) -> bool {
+        a == b
+            || self
+                .connections
+                .iter()
+                .any(|[x, y]| (x == a && y == b) || (x == b && y == a))
+    }
+}
+
+impl ContentDef for TilesetDef {
+    const KIND: &'static str = "tileset";
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.variants.len() > MAX_TILE_VARIANTS {
+            problems.push(format!(
+                "Tileset has {} variants, at most {} are supported",
+                self.variants.len(),
+                MAX_TILE_VARIANTS
+            ));
+        }
+        if !self.variants.iter().any(|v| v.kind == TileKind::Wall) {
+            problems.push("Tileset needs a wall variant for room borders".to_string());
+        }
+        if !self.variants.iter().any(|v| v.kind == TileKind::Floor) {
+            problems.push("Tileset needs a floor variant for exits and spawns".to_string());
+        }
+        for variant in &self.variants {
+            if !variant.weight.is_finite() || variant.weight <= 0.0 {
+                problems.push(format!(
+                    "Variant '{}' needs a positive weight",
+                    variant.name
+                ));
+            } else if variant.weight > MAX_TILE_WEIGHT {
+                problems.push(format!(
+                    "Variant '{}' weighs more than {}",
+                    variant.name, MAX_TILE_WEIGHT
+                ));
+            }
+        }
+        problems
+    }
+}
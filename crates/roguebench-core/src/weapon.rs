@@ -0,0 +1,119 @@
+//! Weapon definitions.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::ContentDef;
+
+fn default_projectile_count() -> u32 {
+    1
+}
+
+/// Definition of a weapon as stored in the content database.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct WeaponDef {
+    pub id: Uuid,
+    pub name: String,
+    /// Shots per second.
+    #[schemars(range(min = 0.0))]
+    pub fire_rate: f32,
+    /// Width in degrees of the cone projectiles leave the muzzle in.
+    #[serde(default)]
+    #[schemars(range(min = 0.0, max = 360.0))]
+    pub spread: f32,
+    /// Projectiles fired per shot, fanned out evenly across the spread.
+    #[serde(default = "default_projectile_count")]
+    #[schemars(range(min = 1))]
+    pub projectile_count: u32,
+    /// Projectile speed in world units per second.
+    #[schemars(range(min = 0.0))]
+    pub speed: f32,
+    /// Distance in world units a projectile travels before it expires.
+    #[schemars(range(min = 0.0))]
+    pub range: f32,
+    /// Damage dealt by each projectile.
+    #[schemars(range(min = 0))]
+    pub damage: i32,
+    /// Shots per magazine; `None` for unlimited ammo.
+    #[serde(default)]
+    #[schemars(range(min = 1))]
+    pub ammo: Option<u32>,
+    /// Seconds it takes to refill an empty magazine.
+    #[serde(default)]
+    #[schemars(range(min = 0.0))]
+    pub reload_time: f32,
+    /// Collision radius of each projectile in world units.
+    #[serde(default)]
+    #[schemars(range(min = 0.0))]
+    pub projectile_radius: f32,
+}
+
+impl WeaponDef {
+    pub fn new(
+        name: impl Into<String>,
+        fire_rate: f32,
+        speed: f32,
+        range: f32,
+        damage: i32,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            fire_rate,
+            spread: 0.0,
+            projectile_count: default_projectile_count(),
+            speed,
+            range,
+            damage,
+            ammo: None,
+            reload_time: 0.0,
+            projectile_radius: 0.0,
+        }
+    }
+
+    /// Seconds between two shots.
+    pub fn cooldown(&self) -> f32 {
+        1.0 / self.fire_rate
+    }
+}
+
+impl ContentDef for WeaponDef {
+    const KIND: &'static str = "weapon";
+
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        for (field, value) in [
+            ("Fire rate", self.fire_rate),
+            ("Speed", self.speed),
+            ("Range", self.range),
+        ] {
+            if value.is_nan() || value <= 0.0 {
+                problems.push(format!("{field} must be positive"));
+            }
+        }
+        if !(0.0..=360.0).contains(&self.spread) {
+            problems.push("Spread must be between 0 and 360 degrees".to_string());
+        }
+        if self.projectile_count == 0 {
+            problems.push("Weapon must fire at least one projectile".to_string());
+        }
+        if self.damage < 0 {
+            problems.push("Damage can't be negative".to_string());
+        }
+        if self.ammo == Some(0) {
+            problems.push("Magazine must hold at least one shot".to_string());
+        }
+        if self.reload_time.is_nan() || self.reload_time < 0.0 {
+            problems.push("Reload time can't be negative".to_string());
+        }
+        if self.projectile_radius.is_nan() || self.projectile_radius < 0.0 {
+            problems.push("Projectile radius can't be negative".to_string());
+        }
+        problems
+    }
+}
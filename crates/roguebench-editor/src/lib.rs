@@ -6,18 +6,26 @@ use std::net::SocketAddr;
 use std::sync::Arc;
 
 use axum::{
-    extract::State,
-    http::StatusCode,
-    response::{Html, IntoResponse},
-    routing::get,
     Json, Router,
+    extract::{Path, State},
+    http::{StatusCode, header},
+    response::{Html, IntoResponse, Response},
+    routing::{get, post, put},
 };
-use roguebench_core::EntityDef;
-use roguebench_protocol::EditorMessage;
-use roguebench_storage::ContentStore;
+use lightyear::netcode::{ConnectToken, Key};
+use roguebench_core::{
+    AiProfile, ContentDef, CurrencyDef, DialogueDef, EncounterDef, EntityDef, FactionDef, ItemDef,
+    QuestDef, RoomDef, ShopDef, StatusEffectDef, TilesetDef, UnlockDef, WeaponDef, content_schema,
+};
+use roguebench_protocol::{
+    CONNECT_PATH, ContentChange, ENTITY_KIND, EditorMessage, PlayerSecret, ProtocolHash,
+    TOKEN_EXPIRY_SECS, TokenRequest, VersionMismatch,
+};
+use roguebench_storage::{ContentStore, ContentStoreExt, StorageError};
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc;
 use tower_http::cors::CorsLayer;
+use uuid::Uuid;
 
 /// Configuration for the editor.
 pub struct EditorConfig {
@@ -27,6 +35,33 @@ pub struct EditorConfig {
     pub message_tx: mpsc::UnboundedSender<EditorMessage>,
     /// Address to listen on.
     pub listen_addr: SocketAddr,
+    /// Issuer of connect tokens for the game server; no token endpoint when `None`.
+    pub tokens: Option<TokenIssuer>,
+}
+
+/// Issues netcode connect tokens for the game server.
+///
+/// Shares the server's private key, so only tokens handed out here let
+/// clients in.
+#[derive(Clone)]
+pub struct TokenIssuer {
+    /// Address of the game server the tokens are for.
+    pub server_addr: SocketAddr,
+    /// Protocol the game server speaks.
+    pub protocol: ProtocolHash,
+    /// Private key of the game server.
+    pub private_key: Key,
+}
+
+impl TokenIssuer {
+    /// Netcode client ID of a player.
+    ///
+    /// The same player always gets the same ID, so reconnecting finds their
+    /// old session and two clients can't play as one player at once.
+    pub fn client_id(player: Uuid) -> u64 {
+        let (high, low) = player.as_u64_pair();
+        high ^ low
+    }
 }
 
 /// Shared state for axum handlers.
@@ -38,15 +73,55 @@ struct AppState {
 
 #[derive(Deserialize)]
 struct CreateEntityRequest {
+    #[serde(default)]
+    slug: Option<String>,
     name: String,
     health: i32,
+    #[serde(default)]
+    dialogue: Option<Uuid>,
+    #[serde(default)]
+    weapon: Option<Uuid>,
+    #[serde(default)]
+    ai: Option<AiProfile>,
+    #[serde(default)]
+    faction: Option<Uuid>,
+    #[serde(default)]
+    shop: Option<Uuid>,
 }
 
 #[derive(Serialize, Deserialize)]
 struct EntityResponse {
     id: String,
+    #[serde(default)]
+    slug: Option<String>,
     name: String,
     health: i32,
+    #[serde(default)]
+    dialogue: Option<String>,
+    #[serde(default)]
+    weapon: Option<String>,
+    #[serde(default)]
+    ai: Option<AiProfile>,
+    #[serde(default)]
+    faction: Option<String>,
+    #[serde(default)]
+    shop: Option<String>,
+}
+
+impl From<EntityDef> for EntityResponse {
+    fn from(e: EntityDef) -> Self {
+        Self {
+            id: e.id.to_string(),
+            slug: e.slug,
+            name: e.name,
+            health: e.health,
+            dialogue: e.dialogue.map(|id| id.to_string()),
+            weapon: e.weapon.map(|id| id.to_string()),
+            ai: e.ai,
+            faction: e.faction.map(|id| id.to_string()),
+            shop: e.shop.map(|id| id.to_string()),
+        }
+    }
 }
 
 async fn index() -> Html<&'static str> {
@@ -102,41 +177,293 @@ async fn index() -> Html<&'static str> {
 async fn list_entities(State(state): State<AppState>) -> impl IntoResponse {
     match state.store.load_entities() {
         Ok(entities) => {
-            let response: Vec<EntityResponse> = entities
-                .into_iter()
-                .map(|e| EntityResponse {
-                    id: e.id.to_string(),
-                    name: e.name,
-                    health: e.health,
-                })
-                .collect();
+            let response: Vec<EntityResponse> =
+                entities.into_iter().map(EntityResponse::from).collect();
             Json(response).into_response()
         }
         Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
     }
 }
 
+/// Look up one entity definition by ID or slug, e.g. from the
+/// [`TemplateId`](roguebench_protocol::TemplateId) of a runtime entity.
+async fn get_entity(State(state): State<AppState>, Path(key): Path<String>) -> impl IntoResponse {
+    let id = key.parse::<Uuid>().ok();
+    match state.store.load_entities() {
+        Ok(entities) => match entities
+            .into_iter()
+            .find(|e| Some(e.id) == id || e.slug.as_deref() == Some(key.as_str()))
+        {
+            Some(entity) => Json(EntityResponse::from(entity)).into_response(),
+            None => StatusCode::NOT_FOUND.into_response(),
+        },
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
 async fn create_entity(
     State(state): State<AppState>,
     Json(req): Json<CreateEntityRequest>,
 ) -> impl IntoResponse {
-    let entity = EntityDef::new(req.name, req.health);
+    save_entity(&state, Uuid::new_v4(), req, ContentChange::Created)
+}
+
+async fn update_entity(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<CreateEntityRequest>,
+) -> impl IntoResponse {
+    save_entity(&state, id, req, ContentChange::Updated)
+}
+
+async fn delete_entity(State(state): State<AppState>, Path(id): Path<Uuid>) -> impl IntoResponse {
+    match state.store.delete_entity(id) {
+        Ok(()) => {
+            let _ = state.message_tx.send(EditorMessage::ContentChanged {
+                kind: ENTITY_KIND,
+                id,
+                change: ContentChange::Deleted,
+            });
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err(StorageError::NotFound(_)) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+fn save_entity(
+    state: &AppState,
+    id: Uuid,
+    req: CreateEntityRequest,
+    change: ContentChange,
+) -> Response {
+    let entities = match state.store.load_entities() {
+        Ok(entities) => entities,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+    if change == ContentChange::Updated && !entities.iter().any(|e| e.id == id) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let mut problems = req.ai.as_ref().map(AiProfile::validate).unwrap_or_default();
+    let taken = req.slug.as_ref().filter(|slug| {
+        entities
+            .iter()
+            .any(|e| e.id != id && e.slug.as_ref() == Some(*slug))
+    });
+    if let Some(slug) = taken {
+        problems.push(format!("slug '{slug}' is already taken"));
+    }
+    if !problems.is_empty() {
+        return (StatusCode::UNPROCESSABLE_ENTITY, Json(problems)).into_response();
+    }
+
+    let mut entity = EntityDef::new(req.name, req.health);
+    entity.id = id;
+    entity.slug = req.slug;
+    entity.dialogue = req.dialogue;
+    entity.weapon = req.weapon;
+    entity.ai = req.ai;
+    entity.faction = req.faction;
+    entity.shop = req.shop;
     match state.store.save_entity(&entity) {
         Ok(()) => {
-            let _ = state.message_tx.send(EditorMessage::ReloadEntities);
-            let response = EntityResponse {
-                id: entity.id.to_string(),
-                name: entity.name,
-                health: entity.health,
-            };
-            (StatusCode::CREATED, Json(response)).into_response()
+            let _ = state.message_tx.send(EditorMessage::ContentChanged {
+                kind: ENTITY_KIND,
+                id,
+                change,
+            });
+            (status_of(change), Json(EntityResponse::from(entity))).into_response()
+        }
+        // Taken by a concurrent save since the check above
+        Err(StorageError::SlugTaken(slug)) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(vec![format!("slug '{slug}' is already taken")]),
+        )
+            .into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// Status a successful save answers with.
+fn status_of(change: ContentChange) -> StatusCode {
+    match change {
+        ContentChange::Created => StatusCode::CREATED,
+        ContentChange::Updated | ContentChange::Deleted => StatusCode::OK,
+    }
+}
+
+async fn entity_schema() -> impl IntoResponse {
+    Json(content_schema::<EntityDef>("entity"))
+}
+
+async fn list_content<T: ContentDef>(State(state): State<AppState>) -> impl IntoResponse {
+    match state.store.load_all::<T>() {
+        Ok(defs) => Json(defs).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+async fn create_content<T: ContentDef>(
+    State(state): State<AppState>,
+    Json(mut body): Json<serde_json::Value>,
+) -> impl IntoResponse {
+    // Assign a fresh ID unless the client supplied one
+    if let Some(fields) = body.as_object_mut() {
+        fields
+            .entry("id")
+            .or_insert_with(|| Uuid::new_v4().to_string().into());
+    }
+    save_content::<T>(&state, body, ContentChange::Created)
+}
+
+async fn update_content<T: ContentDef>(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(mut body): Json<serde_json::Value>,
+) -> impl IntoResponse {
+    // The path is authoritative for the ID
+    if let Some(fields) = body.as_object_mut() {
+        fields.insert("id".to_string(), id.to_string().into());
+    }
+    save_content::<T>(&state, body, ContentChange::Updated)
+}
+
+async fn delete_content<T: ContentDef>(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    match state.store.delete::<T>(id) {
+        Ok(()) => {
+            let _ = state.message_tx.send(EditorMessage::ContentChanged {
+                kind: T::KIND,
+                id,
+                change: ContentChange::Deleted,
+            });
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err(StorageError::NotFound(_)) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+async fn schema<T: ContentDef>() -> impl IntoResponse {
+    Json(T::schema())
+}
+
+fn save_content<T: ContentDef>(
+    state: &AppState,
+    body: serde_json::Value,
+    change: ContentChange,
+) -> Response {
+    let def: T = match serde_json::from_value(body) {
+        Ok(def) => def,
+        Err(e) => return (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()).into_response(),
+    };
+    if change == ContentChange::Updated {
+        match state.store.load_document(T::KIND, def.id()) {
+            Ok(Some(_)) => {}
+            Ok(None) => return StatusCode::NOT_FOUND.into_response(),
+            Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        }
+    }
+    let problems = def.validate();
+    if !problems.is_empty() {
+        return (StatusCode::UNPROCESSABLE_ENTITY, Json(problems)).into_response();
+    }
+    match state.store.save(&def) {
+        Ok(()) => {
+            let _ = state.message_tx.send(EditorMessage::ContentChanged {
+                kind: T::KIND,
+                id: def.id(),
+                change,
+            });
+            (status_of(change), Json(def)).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// Build CRUD routes for one content kind under `/{path}`, and its
+/// JSON Schema under `/schema/{kind}`.
+fn content_routes<T: ContentDef>(path: &str) -> Router<AppState> {
+    Router::new()
+        .route(&format!("/schema/{}", T::KIND), get(schema::<T>))
+        .route(
+            &format!("/{path}"),
+            get(list_content::<T>).post(create_content::<T>),
+        )
+        .route(
+            &format!("/{path}/{{id}}"),
+            put(update_content::<T>).delete(delete_content::<T>),
+        )
+}
+
+#[derive(Clone)]
+struct TokenState {
+    issuer: TokenIssuer,
+    message_tx: mpsc::UnboundedSender<EditorMessage>,
+}
+
+async fn issue_token(
+    State(state): State<TokenState>,
+    Json(req): Json<TokenRequest>,
+) -> impl IntoResponse {
+    let issuer = &state.issuer;
+    if req.protocol != issuer.protocol {
+        let mismatch = VersionMismatch {
+            client: req.protocol,
+            server: issuer.protocol,
+        };
+        return (StatusCode::CONFLICT, Json(mismatch)).into_response();
+    }
+    if !req.secret.is_valid() {
+        let problem = format!(
+            "player secrets must be at least {} characters",
+            PlayerSecret::MIN_LEN
+        );
+        return (StatusCode::UNAUTHORIZED, problem).into_response();
+    }
+
+    // Players are whoever their secret says, never who the client claims
+    let player = req.secret.player();
+    let client_id = TokenIssuer::client_id(player);
+    let token = ConnectToken::build(
+        issuer.server_addr,
+        issuer.protocol.0,
+        client_id,
+        issuer.private_key,
+    )
+    .expire_seconds(TOKEN_EXPIRY_SECS)
+    .generate()
+    .and_then(|token| Ok(token.try_into_bytes()?));
+    match token {
+        Ok(bytes) => {
+            let _ = state
+                .message_tx
+                .send(EditorMessage::PlayerAuthorized { client_id, player });
+            let content_type = [(header::CONTENT_TYPE, "application/octet-stream")];
+            (content_type, bytes.to_vec()).into_response()
         }
         Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
     }
 }
 
+/// Build the router issuing connect tokens, served next to the editor.
+pub fn token_router(
+    issuer: TokenIssuer,
+    message_tx: mpsc::UnboundedSender<EditorMessage>,
+) -> Router {
+    Router::new()
+        .route(CONNECT_PATH, post(issue_token))
+        .with_state(TokenState { issuer, message_tx })
+}
+
 /// Build the editor router.
-pub fn router(storage: Arc<dyn ContentStore>, message_tx: mpsc::UnboundedSender<EditorMessage>) -> Router {
+pub fn router(
+    storage: Arc<dyn ContentStore>,
+    message_tx: mpsc::UnboundedSender<EditorMessage>,
+) -> Router {
     let state = AppState {
         store: storage,
         message_tx,
@@ -145,13 +472,33 @@ pub fn router(storage: Arc<dyn ContentStore>, message_tx: mpsc::UnboundedSender<
     Router::new()
         .route("/", get(index))
         .route("/entities", get(list_entities).post(create_entity))
+        .route(
+            "/entities/{key}",
+            get(get_entity).put(update_entity).delete(delete_entity),
+        )
+        .route("/schema/entity", get(entity_schema))
+        .merge(content_routes::<ItemDef>("items"))
+        .merge(content_routes::<DialogueDef>("dialogues"))
+        .merge(content_routes::<QuestDef>("quests"))
+        .merge(content_routes::<RoomDef>("rooms"))
+        .merge(content_routes::<EncounterDef>("encounters"))
+        .merge(content_routes::<TilesetDef>("tilesets"))
+        .merge(content_routes::<WeaponDef>("weapons"))
+        .merge(content_routes::<FactionDef>("factions"))
+        .merge(content_routes::<StatusEffectDef>("status-effects"))
+        .merge(content_routes::<CurrencyDef>("currencies"))
+        .merge(content_routes::<ShopDef>("shops"))
+        .merge(content_routes::<UnlockDef>("unlocks"))
         .layer(CorsLayer::permissive())
         .with_state(state)
 }
 
 /// Run the editor web server.
 pub async fn run(config: EditorConfig) {
-    let app = router(config.storage, config.message_tx);
+    let mut app = router(config.storage, config.message_tx.clone());
+    if let Some(issuer) = config.tokens {
+        app = app.merge(token_router(issuer, config.message_tx));
+    }
 
     tracing::info!("Web editor listening on http://{}", config.listen_addr);
 
@@ -164,7 +511,7 @@ pub async fn run(config: EditorConfig) {
 }
 
 pub mod prelude {
-    pub use crate::{router, run, EditorConfig};
+    pub use crate::{EditorConfig, TokenIssuer, router, run, token_router};
 }
 
 #[cfg(test)]
@@ -258,7 +605,14 @@ mod tests {
 
         // Verify message was sent to engine
         let msg = rx.try_recv().unwrap();
-        assert!(matches!(msg, EditorMessage::ReloadEntities));
+        assert!(matches!(
+            msg,
+            EditorMessage::ContentChanged {
+                kind: ENTITY_KIND,
+                id,
+                change: ContentChange::Created,
+            } if id == stored[0].id
+        ));
     }
 
     #[tokio::test]
@@ -300,4 +654,353 @@ mod tests {
         assert_eq!(goblin.health, 30);
         assert_eq!(orc.health, 80);
     }
+
+    #[tokio::test]
+    async fn entities_are_found_by_id_or_slug() {
+        let storage: Arc<dyn ContentStore> = Arc::new(MemoryStore::new());
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let app = router(Arc::clone(&storage), tx);
+        let create = || {
+            axum::http::Request::builder()
+                .method("POST")
+                .uri("/entities")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    r#"{"slug": "goblin-archer", "name": "Goblin", "health": 30}"#,
+                ))
+                .unwrap()
+        };
+        let get = |key: &str| {
+            axum::http::Request::builder()
+                .uri(format!("/entities/{key}"))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(create()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let created: EntityResponse = serde_json::from_slice(&body).unwrap();
+
+        // Slugs are unique
+        let response = app.clone().oneshot(create()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(storage.load_entities().unwrap().len(), 1);
+
+        for key in [created.id.as_str(), "goblin-archer"] {
+            let response = app.clone().oneshot(get(key)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            let found: EntityResponse = serde_json::from_slice(&body).unwrap();
+            assert_eq!(found.id, created.id);
+            assert_eq!(found.slug.as_deref(), Some("goblin-archer"));
+        }
+        let response = app.oneshot(get("goblin-king")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn update_and_delete_entity_send_changes() {
+        let storage: Arc<dyn ContentStore> = Arc::new(MemoryStore::new());
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let app = router(Arc::clone(&storage), tx);
+        let entity = EntityDef::new("Goblin", 30);
+        storage.save_entity(&entity).unwrap();
+        let request = |method: &str, body: &'static str| {
+            axum::http::Request::builder()
+                .method(method)
+                .uri(format!("/entities/{}", entity.id))
+                .header("content-type", "application/json")
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let response = app
+            .clone()
+            .oneshot(request("PUT", r#"{"name": "Hobgoblin", "health": 45}"#))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let stored = storage.load_entities().unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!((stored[0].id, stored[0].health), (entity.id, 45));
+
+        let response = app.clone().oneshot(request("DELETE", "")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert!(storage.load_entities().unwrap().is_empty());
+
+        // Gone now, so neither can be repeated
+        for (method, body) in [
+            ("PUT", r#"{"name": "Goblin", "health": 30}"#),
+            ("DELETE", ""),
+        ] {
+            let response = app.clone().oneshot(request(method, body)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        }
+
+        for change in [ContentChange::Updated, ContentChange::Deleted] {
+            let msg = rx.try_recv().unwrap();
+            assert!(matches!(
+                msg,
+                EditorMessage::ContentChanged { kind: ENTITY_KIND, id, change: c }
+                    if id == entity.id && c == change
+            ));
+        }
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn create_item_saves_and_sends_reload() {
+        let storage: Arc<dyn ContentStore> = Arc::new(MemoryStore::new());
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let app = router(Arc::clone(&storage), tx);
+
+        let response = app
+            .oneshot(
+                axum::http::Request::builder()
+                    .method("POST")
+                    .uri("/items")
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        r#"{"name": "Potion", "max_stack": 10, "rarity": "rare", "use_effect": {"type": "heal", "amount": 25}}"#,
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let created: ItemDef = serde_json::from_slice(&body).unwrap();
+        assert_eq!(created.name, "Potion");
+        assert_eq!(created.max_stack, 10);
+
+        let stored = storage.load_all::<ItemDef>().unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].id, created.id);
+
+        let msg = rx.try_recv().unwrap();
+        assert!(matches!(
+            msg,
+            EditorMessage::ContentChanged {
+                kind: "item",
+                id,
+                change: ContentChange::Created,
+            } if id == created.id
+        ));
+    }
+
+    #[tokio::test]
+    async fn update_and_delete_item() {
+        let storage: Arc<dyn ContentStore> = Arc::new(MemoryStore::new());
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let item = ItemDef::new("Sword", 1);
+        storage.save(&item).unwrap();
+
+        let response = router(Arc::clone(&storage), tx.clone())
+            .oneshot(
+                axum::http::Request::builder()
+                    .method("PUT")
+                    .uri(format!("/items/{}", item.id))
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        r#"{"name": "Greatsword", "max_stack": 1, "equip_slot": "main_hand"}"#,
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let stored = storage.load_all::<ItemDef>().unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].name, "Greatsword");
+
+        let delete = || {
+            axum::http::Request::builder()
+                .method("DELETE")
+                .uri(format!("/items/{}", item.id))
+                .body(Body::empty())
+                .unwrap()
+        };
+        let response = router(Arc::clone(&storage), tx.clone())
+            .oneshot(delete())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert!(storage.load_all::<ItemDef>().unwrap().is_empty());
+
+        let response = router(Arc::clone(&storage), tx.clone())
+            .oneshot(delete())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        // Updating never creates
+        let response = router(Arc::clone(&storage), tx)
+            .oneshot(
+                axum::http::Request::builder()
+                    .method("PUT")
+                    .uri(format!("/items/{}", item.id))
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"name": "Sword", "max_stack": 1}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert!(storage.load_all::<ItemDef>().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn create_item_rejects_empty_stacks() {
+        let storage: Arc<dyn ContentStore> = Arc::new(MemoryStore::new());
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        let response = router(Arc::clone(&storage), tx)
+            .oneshot(
+                axum::http::Request::builder()
+                    .method("POST")
+                    .uri("/items")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"name": "Nothing", "max_stack": 0}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(storage.load_all::<ItemDef>().unwrap().is_empty());
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn create_dialogue_rejects_invalid_graph() {
+        let storage: Arc<dyn ContentStore> = Arc::new(MemoryStore::new());
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let app = router(Arc::clone(&storage), tx);
+
+        let response = app
+            .oneshot(
+                axum::http::Request::builder()
+                    .method("POST")
+                    .uri("/dialogues")
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        r#"{"name": "Gate", "start": "greet", "nodes": [
+                            {"id": "greet", "speaker": "Guard", "text": "Halt!",
+                             "choices": [{"text": "Let me in", "target": "missing"}]}
+                        ]}"#,
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let problems: Vec<String> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("missing"));
+
+        // Nothing was saved and the engine wasn't told to reload
+        assert!(storage.load_all::<DialogueDef>().unwrap().is_empty());
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn schemas_are_served_per_kind() {
+        for kind in ["entity", "item", "quest", "room", "tileset"] {
+            let (app, _rx) = test_router();
+            let response = app
+                .oneshot(
+                    axum::http::Request::builder()
+                        .uri(format!("/schema/{kind}"))
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            let schema: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(schema["x-content-kind"], kind);
+            assert_eq!(schema["properties"]["id"]["format"], "uuid");
+        }
+
+        let (app, _rx) = test_router();
+        let response = app
+            .oneshot(
+                axum::http::Request::builder()
+                    .uri("/schema/spell")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn connect_tokens_are_only_issued_to_matching_protocols() {
+        let issuer = TokenIssuer {
+            server_addr: SocketAddr::from(([127, 0, 0, 1], 5000)),
+            protocol: ProtocolHash(42),
+            private_key: lightyear::netcode::generate_key(),
+        };
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let secret = PlayerSecret::generate();
+        let request = |secret: &PlayerSecret, protocol| {
+            let body = serde_json::to_string(&TokenRequest {
+                secret: secret.clone(),
+                protocol,
+            })
+            .unwrap();
+            axum::http::Request::builder()
+                .method("POST")
+                .uri(CONNECT_PATH)
+                .header("content-type", "application/json")
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let response = token_router(issuer.clone(), tx.clone())
+            .oneshot(request(&secret, ProtocolHash(41)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let mismatch: VersionMismatch = serde_json::from_slice(&body).unwrap();
+        assert_eq!(mismatch.server, ProtocolHash(42));
+        assert!(rx.try_recv().is_err());
+
+        // Guessable secrets prove nothing
+        let weak = PlayerSecret("hunter2".to_string());
+        let response = token_router(issuer.clone(), tx.clone())
+            .oneshot(request(&weak, ProtocolHash(42)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert!(rx.try_recv().is_err());
+
+        let response = token_router(issuer, tx)
+            .oneshot(request(&secret, ProtocolHash(42)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert!(ConnectToken::try_from_bytes(&body).is_ok());
+
+        // The engine learns whose token it was, going by the secret
+        let player = secret.player();
+        assert_ne!(player, PlayerSecret::generate().player());
+        let msg = rx.try_recv().unwrap();
+        assert!(matches!(
+            msg,
+            EditorMessage::PlayerAuthorized { client_id, player: p }
+                if p == player && client_id == TokenIssuer::client_id(player)
+        ));
+    }
 }
@@ -0,0 +1,470 @@
+//! Enemy AI: one shared state machine, tuned per entity by its [`AiProfile`].
+
+use bevy::prelude::*;
+use rand::Rng;
+use roguebench_core::{AiProfile, AiState, FactionDef, Stat, StateMachineDef};
+use roguebench_protocol::Health;
+
+use crate::combat::{ApplyDamage, Hitbox};
+use crate::content::ContentRegistry;
+use crate::faction::Team;
+use crate::fsm::{Fsm, FsmPlugin, drive_state_machines};
+use crate::progression::PlayerId;
+use crate::rng::{RngStream, RunRng};
+use crate::room::{CollisionMap, step};
+use crate::status::StatMultipliers;
+use crate::weapon::{FireWeapon, Weapon};
+
+/// Distance at which a movement goal counts as reached.
+const ARRIVE_DISTANCE: f32 = 4.0;
+
+/// Seconds an idle entity waits before wandering off.
+const IDLE_TIME: f32 = 1.5;
+
+/// Seconds an entity wanders before stopping, even if it hasn't arrived.
+const WANDER_TIME: f32 = 4.0;
+
+/// What an AI-driven entity knows about its surroundings.
+///
+/// Refreshed every fixed tick before the state machine runs, and read by its guards.
+#[derive(Component, Debug, Clone)]
+pub struct AiBrain {
+    pub profile: AiProfile,
+    /// Starting health of the definition the entity was built from.
+    pub(crate) max_health: i32,
+    /// Where the entity was first seen; wandering and leashing are relative to it.
+    pub home: Option<Vec2>,
+    /// Nearest visible hostile entity and its distance.
+    pub target: Option<(Entity, f32)>,
+    /// Set while heading home after straying past the leash range.
+    pub returning: bool,
+    /// Current health as a fraction of the entity's starting health.
+    pub health_fraction: f32,
+    wander_goal: Option<Vec2>,
+    attack_cooldown: f32,
+}
+
+impl AiBrain {
+    pub fn new(profile: AiProfile, max_health: i32) -> Self {
+        Self {
+            profile,
+            max_health,
+            home: None,
+            target: None,
+            returning: false,
+            health_fraction: 1.0,
+            wander_goal: None,
+            attack_cooldown: 0.0,
+        }
+    }
+
+    fn in_attack_range(&self) -> bool {
+        self.target
+            .is_some_and(|(_, distance)| distance <= self.profile.attack_range)
+    }
+
+    fn should_flee(&self) -> bool {
+        let Some((_, distance)) = self.target else {
+            return false;
+        };
+        distance < self.profile.keep_away || self.health_fraction < self.profile.flee_health
+    }
+}
+
+/// The state machine shared by every AI-driven entity.
+pub fn ai_state_machine() -> StateMachineDef<AiState, AiBrain> {
+    use AiState::*;
+
+    StateMachineDef::new(Idle, AiState::ALL)
+        .transition_from_any(Flee, |brain: &AiBrain, _| brain.should_flee())
+        .transition(Flee, Idle, |brain, _| !brain.should_flee())
+        .transition(Chase, Idle, |brain, _| brain.target.is_none())
+        .transition(Attack, Idle, |brain, _| brain.target.is_none())
+        .transition(Idle, Chase, |brain, _| brain.target.is_some())
+        .transition(Wander, Chase, |brain, _| brain.target.is_some())
+        .transition(Chase, Attack, |brain, _| brain.in_attack_range())
+        .transition(Attack, Chase, |brain, _| !brain.in_attack_range())
+        .transition(Idle, Wander, |brain, machine| {
+            brain.returning
+                || (brain.profile.wander_speed > 0.0 && machine.time_in_state() >= IDLE_TIME)
+        })
+        .transition(Wander, Idle, |brain, machine| {
+            !brain.returning
+                && (brain.wander_goal.is_none() || machine.time_in_state() >= WANDER_TIME)
+        })
+}
+
+/// Plugin running enemy AI each fixed tick.
+pub struct AiPlugin;
+
+impl Plugin for AiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(FsmPlugin {
+            def: ai_state_machine(),
+        });
+        app.add_systems(
+            FixedUpdate,
+            (
+                sense_targets.before(drive_state_machines::<AiState, AiBrain>),
+                act_on_ai_state.after(drive_state_machines::<AiState, AiBrain>),
+            ),
+        );
+    }
+}
+
+/// Pick each entity's nearest visible hostile target, and enforce leashes.
+///
+/// Players without a team are fair game for every AI-driven entity; everyone
+/// else is targeted as the faction matrix says. Dead entities see nothing.
+#[allow(clippy::type_complexity)]
+pub fn sense_targets(
+    collision: Option<Res<CollisionMap>>,
+    factions: Res<ContentRegistry<FactionDef>>,
+    mut brains: Query<(Entity, &mut AiBrain, &Transform, &Health, Option<&Team>)>,
+    candidates: Query<(Entity, &Transform, &Health, Option<&Team>, Has<PlayerId>)>,
+) {
+    for (entity, mut brain, transform, health, team) in brains.iter_mut() {
+        if health.0 <= 0 {
+            brain.target = None;
+            continue;
+        }
+        let position = transform.translation.truncate();
+        brain.health_fraction = health.0 as f32 / brain.max_health.max(1) as f32;
+
+        let home = *brain.home.get_or_insert(position);
+        if position.distance(home) > brain.profile.leash_range {
+            brain.returning = true;
+        }
+        if brain.returning {
+            brain.target = None;
+            continue;
+        }
+
+        let can_see = |to: Vec2| {
+            collision
+                .as_ref()
+                .is_none_or(|map| map.cast_ray(position, to).is_none())
+        };
+        brain.target = candidates
+            .iter()
+            .filter(|(other, _, health, ..)| *other != entity && health.0 > 0)
+            .filter(|(.., other_team, is_player)| match other_team {
+                None => *is_player,
+                Some(_) => factions.is_hostile(team, *other_team),
+            })
+            .map(|(other, transform, ..)| (other, transform.translation.truncate()))
+            .map(|(other, at)| (other, at, position.distance(at)))
+            .filter(|(_, at, distance)| *distance <= brain.profile.detection_radius && can_see(*at))
+            .min_by(|a, b| a.2.total_cmp(&b.2))
+            .map(|(other, _, distance)| (other, distance));
+    }
+}
+
+/// Move and attack according to each entity's current state; dead entities stay put.
+#[allow(clippy::type_complexity)]
+pub fn act_on_ai_state(
+    mut commands: Commands,
+    time: Res<Time>,
+    collision: Option<Res<CollisionMap>>,
+    mut rng: ResMut<RunRng>,
+    mut brains: Query<(
+        Entity,
+        &Fsm<AiState>,
+        &mut AiBrain,
+        &mut Transform,
+        &Health,
+        Option<&Hitbox>,
+        Option<&StatMultipliers>,
+        Has<Weapon>,
+    )>,
+    targets: Query<&Transform, Without<AiBrain>>,
+) {
+    let dt = time.delta_secs();
+    for (entity, fsm, mut brain, mut transform, health, hitbox, multipliers, armed) in
+        brains.iter_mut()
+    {
+        if health.0 <= 0 {
+            continue;
+        }
+        let stat = |stat: Stat| multipliers.map_or(1.0, |m| m.get(stat));
+        let (move_speed, attack_speed) = (stat(Stat::MoveSpeed), stat(Stat::AttackSpeed));
+        brain.attack_cooldown = (brain.attack_cooldown - dt * attack_speed).max(0.0);
+        let position = transform.translation.truncate();
+        let half_extents = Vec2::splat(hitbox.copied().unwrap_or_default().radius);
+        let target = brain.target.and_then(|(target, _)| {
+            let at = targets.get(target).ok()?.translation.truncate();
+            Some((target, at))
+        });
+        let mut walk = |towards: Vec2, speed: f32| {
+            let moved = step(
+                position,
+                towards,
+                speed * move_speed * dt,
+                collision.as_deref(),
+                half_extents,
+            );
+            transform.translation = moved.extend(transform.translation.z);
+        };
+
+        match fsm.current() {
+            AiState::Idle => {}
+            AiState::Wander => {
+                let home = brain.home.unwrap_or(position);
+                let radius = brain.profile.wander_radius;
+                let goal = match brain.wander_goal {
+                    Some(goal) => goal,
+                    None if brain.returning => home,
+                    None => {
+                        let rng = rng.stream(RngStream::Ai);
+                        let offset = Vec2::from_angle(rng.gen_range(0.0..std::f32::consts::TAU))
+                            * rng.gen_range(0.0..=radius);
+                        home + offset
+                    }
+                };
+                brain.wander_goal = Some(goal);
+
+                // Entities that never wander still walk home after a leash
+                let speed = if brain.profile.wander_speed > 0.0 {
+                    brain.profile.wander_speed
+                } else {
+                    brain.profile.chase_speed
+                };
+                walk(goal, speed);
+                if transform.translation.truncate().distance(goal) <= ARRIVE_DISTANCE {
+                    brain.wander_goal = None;
+                    brain.returning = false;
+                }
+            }
+            AiState::Chase => {
+                // Already in range: hold ground until the next tick starts the attack
+                if brain.in_attack_range() {
+                    continue;
+                }
+                if let Some((_, at)) = target {
+                    walk(at, brain.profile.chase_speed);
+                }
+            }
+            AiState::Attack => {
+                let Some((target, at)) = target else {
+                    continue;
+                };
+                // Stunned
+                if attack_speed <= 0.0 {
+                    continue;
+                }
+                if armed {
+                    commands.trigger(FireWeapon {
+                        shooter: entity,
+                        direction: at - position,
+                    });
+                } else if brain.attack_cooldown <= 0.0 {
+                    brain.attack_cooldown = brain.profile.attack_cooldown;
+                    commands.trigger(ApplyDamage {
+                        target,
+                        source: entity,
+                        amount: brain.profile.melee_damage,
+                    });
+                }
+            }
+            AiState::Flee => {
+                if let Some((_, at)) = target {
+                    walk(position * 2.0 - at, brain.profile.chase_speed);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::time::TimeUpdateStrategy;
+    use roguebench_core::{Relation, RoomDef, StateMachine, TileKind};
+    use roguebench_protocol::TILE_SIZE;
+    use std::time::Duration;
+    use uuid::Uuid;
+
+    use crate::combat::apply_damage;
+
+    const MONSTERS: Team = Team(Uuid::from_u128(1));
+    const HEROES: Team = Team(Uuid::from_u128(2));
+
+    #[derive(Resource, Default)]
+    struct Shots(u32);
+
+    fn test_app(room: &RoomDef) -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            100,
+        )));
+        app.insert_resource(CollisionMap::new(room));
+        app.insert_resource(RunRng::new(5));
+        let mut monsters = FactionDef::new("Monsters").with_relation(HEROES.0, Relation::Hostile);
+        monsters.id = MONSTERS.0;
+        let mut factions = ContentRegistry::<FactionDef>::default();
+        factions.insert(monsters);
+        app.insert_resource(factions);
+        app.add_plugins(AiPlugin);
+        app.add_observer(apply_damage);
+
+        app.init_resource::<Shots>();
+        app.add_observer(|_: On<FireWeapon>, mut shots: ResMut<Shots>| shots.0 += 1);
+        app
+    }
+
+    fn spawn_enemy(app: &mut App, profile: AiProfile, health: i32, at: Vec2) -> Entity {
+        app.world_mut()
+            .spawn((
+                AiBrain::new(profile, 10),
+                Fsm(StateMachine::new(AiState::Idle)),
+                MONSTERS,
+                Health(health),
+                Hitbox::default(),
+                Transform::from_translation(at.extend(0.0)),
+            ))
+            .id()
+    }
+
+    fn spawn_player(app: &mut App, at: Vec2) -> Entity {
+        app.world_mut()
+            .spawn((
+                HEROES,
+                Health(100),
+                Transform::from_translation(at.extend(0.0)),
+            ))
+            .id()
+    }
+
+    fn run(app: &mut App, updates: usize) {
+        for _ in 0..updates {
+            app.update();
+        }
+    }
+
+    fn state(app: &App, entity: Entity) -> AiState {
+        *app.world().get::<Fsm<AiState>>(entity).unwrap().current()
+    }
+
+    fn position(app: &App, entity: Entity) -> Vec2 {
+        app.world()
+            .get::<Transform>(entity)
+            .unwrap()
+            .translation
+            .truncate()
+    }
+
+    #[test]
+    fn grunts_chase_visible_players_and_attack_in_range() {
+        let mut room = RoomDef::new("Split", 20, 10);
+        for y in 0..10 {
+            room.set_tile(10, y, TileKind::Wall);
+        }
+        let mut app = test_app(&room);
+        let tile = |x: f32, y: f32| Vec2::new(x, y) * TILE_SIZE;
+
+        let grunt = spawn_enemy(&mut app, AiProfile::grunt(), 10, tile(3.5, 5.5));
+        let player = spawn_player(&mut app, tile(7.5, 5.5));
+        // Close enough to detect, but on the other side of the wall
+        let blind = spawn_enemy(&mut app, AiProfile::grunt(), 10, tile(12.5, 2.5));
+        run(&mut app, 30);
+
+        assert_eq!(state(&app, grunt), AiState::Attack);
+        assert!(position(&app, grunt).distance(position(&app, player)) <= 24.0);
+        assert!(app.world().get::<Health>(player).unwrap().0 < 100);
+
+        assert!(app.world().get::<AiBrain>(blind).unwrap().target.is_none());
+        assert!(position(&app, blind).x > 11.0 * TILE_SIZE);
+    }
+
+    #[test]
+    fn leashed_enemies_give_up_and_return_home() {
+        let room = RoomDef::new("Hall", 40, 10);
+        let mut app = test_app(&room);
+        let mut profile = AiProfile::grunt();
+        profile.detection_radius = 1000.0;
+        profile.leash_range = 100.0;
+        profile.attack_range = 10.0;
+
+        let home = Vec2::new(64.0, 160.0);
+        let grunt = spawn_enemy(&mut app, profile, 10, home);
+        spawn_player(&mut app, Vec2::new(1000.0, 160.0));
+
+        let mut furthest: f32 = 0.0;
+        for _ in 0..60 {
+            run(&mut app, 1);
+            furthest = furthest.max(position(&app, grunt).distance(home));
+        }
+        // Strays at most one tick past the leash before turning back
+        assert!(furthest > 100.0 && furthest < 105.0);
+
+        let fsm = app.world().get::<Fsm<AiState>>(grunt).unwrap();
+        let history: Vec<_> = fsm.history().map(|t| (t.from, t.to)).collect();
+        assert!(history.contains(&(AiState::Chase, AiState::Idle)));
+        assert!(history.contains(&(AiState::Idle, AiState::Wander)));
+    }
+
+    #[test]
+    fn profiles_change_behaviour_without_new_code() {
+        let room = RoomDef::new("Arena", 30, 30);
+        let mut app = test_app(&room);
+        let center = Vec2::splat(15.0 * TILE_SIZE);
+        spawn_player(&mut app, center);
+
+        // A hurt dasher runs, a healthy one charges in
+        let hurt = spawn_enemy(&mut app, AiProfile::dasher(), 2, center + Vec2::X * 100.0);
+        let healthy = spawn_enemy(&mut app, AiProfile::dasher(), 10, center - Vec2::X * 100.0);
+        // An archer too close backs off, then shoots from range
+        let archer = spawn_enemy(&mut app, AiProfile::archer(), 10, center + Vec2::Y * 50.0);
+        app.world_mut().entity_mut(archer).insert(Weapon {
+            def: uuid::Uuid::new_v4(),
+            ammo: None,
+            cooldown: 0.0,
+            reload: 0.0,
+        });
+        run(&mut app, 10);
+
+        // Runs until the player is out of sight, then settles down
+        let fled = app.world().get::<Fsm<AiState>>(hurt).unwrap();
+        assert!(fled.history().any(|t| t.to == AiState::Flee));
+        assert!(position(&app, hurt).distance(center) > 160.0);
+        assert_eq!(state(&app, healthy), AiState::Attack);
+
+        assert!(position(&app, archer).distance(center) >= 96.0);
+        assert_eq!(state(&app, archer), AiState::Attack);
+        assert!(app.world().resource::<Shots>().0 > 0);
+    }
+
+    #[test]
+    fn killed_enemies_stop_fighting() {
+        let room = RoomDef::new("Arena", 30, 30);
+        let mut app = test_app(&room);
+        let center = Vec2::splat(15.0 * TILE_SIZE);
+        let player = spawn_player(&mut app, center);
+        let grunt = spawn_enemy(&mut app, AiProfile::grunt(), 10, center + Vec2::X * 20.0);
+        let archer = spawn_enemy(&mut app, AiProfile::archer(), 10, center + Vec2::Y * 150.0);
+        app.world_mut().entity_mut(archer).insert(Weapon {
+            def: uuid::Uuid::new_v4(),
+            ammo: None,
+            cooldown: 0.0,
+            reload: 0.0,
+        });
+        run(&mut app, 10);
+        assert_eq!(state(&app, grunt), AiState::Attack);
+        assert!(app.world().resource::<Shots>().0 > 0);
+
+        for enemy in [grunt, archer] {
+            app.world_mut().get_mut::<Health>(enemy).unwrap().0 = 0;
+        }
+        run(&mut app, 1);
+        let health = app.world().get::<Health>(player).unwrap().0;
+        let shots = app.world().resource::<Shots>().0;
+        let at = [position(&app, grunt), position(&app, archer)];
+        run(&mut app, 20);
+
+        assert_eq!(app.world().get::<Health>(player).unwrap().0, health);
+        assert_eq!(app.world().resource::<Shots>().0, shots);
+        assert_eq!([position(&app, grunt), position(&app, archer)], at);
+        assert!(app.world().get::<AiBrain>(grunt).unwrap().target.is_none());
+    }
+}
@@ -0,0 +1,102 @@
+//! Hitboxes and damage.
+
+use bevy::prelude::*;
+use roguebench_core::{FactionDef, Relation, Stat};
+use roguebench_protocol::Health;
+
+use crate::content::ContentRegistry;
+use crate::events::EntityKilled;
+use crate::faction::Team;
+use crate::status::StatMultipliers;
+use crate::systems::EntityTemplate;
+
+/// Circle around an entity's position that attacks can hit.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Hitbox {
+    pub radius: f32,
+}
+
+impl Default for Hitbox {
+    fn default() -> Self {
+        Self { radius: 12.0 }
+    }
+}
+
+/// Health an entity starts with; healing can't go past it.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct MaxHealth(pub i32);
+
+/// Event requesting that damage is dealt to an entity.
+#[derive(Event, Debug, Clone)]
+pub struct ApplyDamage {
+    pub target: Entity,
+    /// The entity responsible for the damage.
+    pub source: Entity,
+    pub amount: i32,
+}
+
+/// Event fired after damage has been taken off an entity's health.
+#[derive(Event, Debug, Clone)]
+pub struct DamageDealt {
+    pub target: Entity,
+    pub source: Entity,
+    pub amount: i32,
+    /// Health left after the damage.
+    pub health: i32,
+}
+
+/// Plugin for dealing damage.
+pub struct CombatPlugin;
+
+impl Plugin for CombatPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_observer(apply_damage);
+    }
+}
+
+/// Take damage off the target's health, reporting kills of templated entities.
+///
+/// Damage between friendly factions is ignored, and status effects scale the rest.
+pub fn apply_damage(
+    trigger: On<ApplyDamage>,
+    mut commands: Commands,
+    factions: Res<ContentRegistry<FactionDef>>,
+    teams: Query<&Team>,
+    multipliers: Query<&StatMultipliers>,
+    mut targets: Query<(&mut Health, Option<&EntityTemplate>)>,
+) {
+    let Ok((mut health, template)) = targets.get_mut(trigger.target) else {
+        return;
+    };
+    // Already dead: nothing left to damage or kill
+    if health.0 <= 0 {
+        return;
+    }
+    let (source, target) = (
+        teams.get(trigger.source).ok(),
+        teams.get(trigger.target).ok(),
+    );
+    if trigger.source != trigger.target && factions.relation(source, target) == Relation::Friendly {
+        return;
+    }
+
+    let scale = |entity: Entity, stat: Stat| multipliers.get(entity).map_or(1.0, |m| m.get(stat));
+    let amount = (trigger.amount as f32
+        * scale(trigger.source, Stat::DamageDealt)
+        * scale(trigger.target, Stat::DamageTaken))
+    .round() as i32;
+
+    health.0 -= amount;
+    commands.trigger(DamageDealt {
+        target: trigger.target,
+        source: trigger.source,
+        amount,
+        health: health.0,
+    });
+    if let Some(template) = template.filter(|_| health.0 <= 0) {
+        commands.trigger(EntityKilled {
+            killer: trigger.source,
+            template: template.0,
+        });
+    }
+}
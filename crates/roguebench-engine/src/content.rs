@@ -0,0 +1,116 @@
+//! Generic registries for authored content kinds.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use roguebench_core::ContentDef;
+use roguebench_protocol::Definitions;
+use roguebench_storage::ContentStoreExt;
+use uuid::Uuid;
+
+use crate::definitions::DefinitionsPublished;
+use crate::resources::Storage;
+
+/// Event triggered when content of a kind should be reloaded from storage.
+#[derive(Event, Message, Debug, Clone)]
+pub struct ReloadContent {
+    /// The [`ContentDef::KIND`] that changed.
+    pub kind: &'static str,
+}
+
+/// Lookup table of all loaded definitions of one content kind.
+#[derive(Resource)]
+pub struct ContentRegistry<T: ContentDef> {
+    entries: HashMap<Uuid, T>,
+}
+
+impl<T: ContentDef> Default for ContentRegistry<T> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+        }
+    }
+}
+
+impl<T: ContentDef> ContentRegistry<T> {
+    /// Look up a definition by ID.
+    pub fn get(&self, id: Uuid) -> Option<&T> {
+        self.entries.get(&id)
+    }
+
+    /// Iterate over all loaded definitions.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.entries.values()
+    }
+
+    /// Number of loaded definitions.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no definitions are loaded.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Insert or replace a definition.
+    pub fn insert(&mut self, def: T) {
+        self.entries.insert(def.id(), def);
+    }
+
+    /// Replace all entries with the given definitions.
+    pub fn replace_all(&mut self, defs: impl IntoIterator<Item = T>) {
+        self.entries = defs.into_iter().map(|def| (def.id(), def)).collect();
+    }
+}
+
+/// Extension trait for registering content kinds on the app.
+pub trait ContentAppExt {
+    /// Register a content kind: adds its registry, loads it at startup and
+    /// reloads it whenever [`ReloadContent`] names its kind, publishing it to
+    /// clients each time.
+    fn register_content<T: ContentDef>(&mut self) -> &mut Self;
+}
+
+impl ContentAppExt for App {
+    fn register_content<T: ContentDef>(&mut self) -> &mut Self {
+        if self.world().contains_resource::<ContentRegistry<T>>() {
+            return self;
+        }
+        self.init_resource::<ContentRegistry<T>>()
+            .init_resource::<Definitions>()
+            .add_systems(Startup, initial_content_load::<T>)
+            .add_observer(reload_content::<T>)
+    }
+}
+
+fn initial_content_load<T: ContentDef>(mut commands: Commands) {
+    commands.trigger(ReloadContent { kind: T::KIND });
+}
+
+/// Reload one content kind from storage when triggered.
+pub fn reload_content<T: ContentDef>(
+    trigger: On<ReloadContent>,
+    mut commands: Commands,
+    storage: Res<Storage>,
+    mut registry: ResMut<ContentRegistry<T>>,
+    mut definitions: ResMut<Definitions>,
+) {
+    if trigger.kind != T::KIND {
+        return;
+    }
+
+    match storage.0.load_all::<T>() {
+        Ok(defs) => {
+            registry.replace_all(defs);
+            tracing::info!("Loaded {} {} definition(s)", registry.len(), T::KIND);
+            let defs = registry.entries.iter().map(|(id, def)| (*id, def));
+            if let Some(delta) = definitions.publish(T::KIND, defs) {
+                commands.trigger(DefinitionsPublished(delta));
+            }
+        }
+        Err(e) => {
+            tracing::error!("Failed to load {} definitions: {}", T::KIND, e);
+        }
+    }
+}
@@ -0,0 +1,142 @@
+//! Keeping clients' copies of the published definitions up to date.
+
+use bevy::prelude::*;
+use lightyear::prelude::server::ClientOf;
+use lightyear::prelude::{Connected, MessageReceiver, MessageSender};
+use roguebench_protocol::{DefinitionSync, Definitions, ReliableChannel, RequestDefinitions};
+
+/// Event fired when reloaded content changed the published definitions.
+#[derive(Event, Debug, Clone)]
+pub struct DefinitionsPublished(pub DefinitionSync);
+
+/// Revision of the last snapshot sent to a client.
+///
+/// Snapshots go over a reliable channel, so a client is sent at most one per
+/// published revision however often it asks.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SnapshotSent(pub u64);
+
+/// Plugin sending the published definitions to clients.
+pub struct DefinitionsPlugin;
+
+impl Plugin for DefinitionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Definitions>()
+            .add_systems(Update, resend_definitions)
+            .add_observer(send_definitions)
+            .add_observer(broadcast_definitions);
+    }
+}
+
+/// Send everything published to a client that has just connected.
+pub fn send_definitions(
+    trigger: On<Add, Connected>,
+    mut commands: Commands,
+    definitions: Res<Definitions>,
+    mut senders: Query<&mut MessageSender<DefinitionSync>, With<ClientOf>>,
+) {
+    if let Ok(mut sender) = senders.get_mut(trigger.entity) {
+        sender.send::<ReliableChannel>(definitions.snapshot());
+        commands
+            .entity(trigger.entity)
+            .insert(SnapshotSent(definitions.revision()));
+    }
+}
+
+/// Send a published change to every connected client.
+pub fn broadcast_definitions(
+    trigger: On<DefinitionsPublished>,
+    mut senders: Query<&mut MessageSender<DefinitionSync>, (With<ClientOf>, With<Connected>)>,
+) {
+    for mut sender in senders.iter_mut() {
+        sender.send::<ReliableChannel>(trigger.0.clone());
+    }
+}
+
+/// Send a fresh snapshot to clients whose copy fell behind.
+///
+/// Requests are only answered once the definitions have moved on from the
+/// last snapshot the client was sent; asking again changes nothing.
+#[allow(clippy::type_complexity)]
+pub fn resend_definitions(
+    mut commands: Commands,
+    definitions: Res<Definitions>,
+    mut clients: Query<(
+        Entity,
+        &mut MessageReceiver<RequestDefinitions>,
+        &mut MessageSender<DefinitionSync>,
+        Option<&SnapshotSent>,
+    )>,
+) {
+    let revision = definitions.revision();
+    for (client, mut receiver, mut sender, sent) in clients.iter_mut() {
+        if receiver.receive().count() == 0 || sent.is_some_and(|sent| sent.0 >= revision) {
+            continue;
+        }
+        sender.send::<ReliableChannel>(definitions.snapshot());
+        commands.entity(client).insert(SnapshotSent(revision));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use roguebench_core::{ContentDef, ItemDef};
+    use roguebench_protocol::StaleDefinitions;
+
+    #[test]
+    fn deltas_carry_only_what_changed() {
+        let potion = ItemDef::new("Potion", 10);
+        let mut sword = ItemDef::new("Sword", 50);
+        let mut server = Definitions::default();
+        let first = server
+            .publish(ItemDef::KIND, [&potion, &sword].map(|item| (item.id, item)))
+            .unwrap();
+        let mut client = Definitions::default();
+        client.apply(server.snapshot()).unwrap();
+        assert_eq!(client.revision(), 1);
+        assert_eq!(client.get::<ItemDef>(potion.id).unwrap().name, "Potion");
+
+        // Republishing the same content changes nothing
+        assert_eq!(
+            server.publish(ItemDef::KIND, [&potion, &sword].map(|item| (item.id, item))),
+            None
+        );
+
+        // Edit one, delete the other
+        sword.name = "Greatsword".to_string();
+        let delta = server.publish(ItemDef::KIND, [(sword.id, &sword)]).unwrap();
+        let DefinitionSync::Delta {
+            revision,
+            changed,
+            removed,
+        } = delta.clone()
+        else {
+            panic!("expected a delta, got {delta:?}");
+        };
+        assert_eq!(revision, 2);
+        assert_eq!(changed.len(), 1);
+        assert_eq!(removed, vec![(ItemDef::KIND.to_string(), potion.id)]);
+
+        client.apply(delta).unwrap();
+        assert_eq!(client.revision(), 2);
+        assert_eq!(client.get::<ItemDef>(sword.id).unwrap().name, "Greatsword");
+        assert!(client.get::<ItemDef>(potion.id).is_none());
+
+        // A delta arriving out of order is refused
+        let mut behind = Definitions::default();
+        assert_eq!(
+            behind.apply(first),
+            Ok(()),
+            "the first delta follows on from nothing"
+        );
+        let skipped = server
+            .publish(ItemDef::KIND, [(potion.id, &potion)])
+            .unwrap();
+        assert_eq!(
+            behind.apply(skipped),
+            Err(StaleDefinitions { have: 1, got: 3 })
+        );
+        assert_eq!(behind.revision(), 1);
+    }
+}
@@ -0,0 +1,490 @@
+//! Per-player dialogue runtime.
+
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use lightyear::prelude::{ControlledBy, ControlledByRemote, MessageReceiver, MessageSender};
+use roguebench_core::{DialogueDef, DialogueEffect};
+use roguebench_protocol::{
+    DialogueClosed, DialogueLine, PickDialogueChoice, ReliableChannel, TALK_RANGE, TalkToNpc,
+};
+use uuid::Uuid;
+
+use crate::content::{ContentAppExt, ContentRegistry};
+
+/// Dialogue an NPC starts when a player talks to it.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct DialogueRef(pub Uuid);
+
+/// Story flags raised for a player, checked by dialogue conditions.
+#[derive(Component, Debug, Clone, Default, PartialEq)]
+pub struct StoryFlags(pub HashSet<String>);
+
+impl StoryFlags {
+    /// Raise (`true`) or clear (`false`) a flag.
+    pub fn set(&mut self, flag: &str, value: bool) {
+        if value {
+            self.0.insert(flag.to_string());
+        } else {
+            self.0.remove(flag);
+        }
+    }
+
+    pub fn is_set(&self, flag: &str) -> bool {
+        self.0.contains(flag)
+    }
+}
+
+/// A player's active conversation.
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct Conversation {
+    pub dialogue: Uuid,
+    pub node: String,
+    pub npc: Entity,
+}
+
+/// Event requesting that `player` starts talking to `npc`.
+#[derive(Event, Debug, Clone)]
+pub struct StartDialogue {
+    pub player: Entity,
+    pub npc: Entity,
+}
+
+/// Event requesting that `player` picks one of the currently available choices.
+#[derive(Event, Debug, Clone)]
+pub struct ChooseDialogueOption {
+    pub player: Entity,
+    /// Index into the choices available to the player at the current node.
+    pub choice: usize,
+}
+
+/// Event fired when a player's conversation moves to a node.
+#[derive(Event, Debug, Clone)]
+pub struct DialogueNodeEntered {
+    pub player: Entity,
+    pub dialogue: Uuid,
+    pub node: String,
+}
+
+/// Event fired when a player's conversation ends.
+#[derive(Event, Debug, Clone)]
+pub struct DialogueEnded {
+    pub player: Entity,
+    pub dialogue: Uuid,
+}
+
+/// Plugin for dialogue content and the conversation runtime.
+pub struct DialoguePlugin;
+
+impl Plugin for DialoguePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_content::<DialogueDef>();
+
+        app.add_systems(Update, receive_dialogue_messages);
+
+        app.add_observer(start_dialogue);
+        app.add_observer(choose_dialogue_option);
+        app.add_observer(send_dialogue_line);
+        app.add_observer(send_dialogue_closed);
+    }
+}
+
+/// Start a conversation with an NPC that references a dialogue.
+///
+/// The player must be within [`TALK_RANGE`] of the NPC.
+pub fn start_dialogue(
+    trigger: On<StartDialogue>,
+    mut commands: Commands,
+    dialogues: Res<ContentRegistry<DialogueDef>>,
+    npcs: Query<(&DialogueRef, &Transform)>,
+    mut players: Query<(Option<&Conversation>, &mut StoryFlags, &Transform)>,
+) {
+    let StartDialogue { player, npc } = *trigger;
+
+    let Ok((DialogueRef(dialogue_id), npc_at)) = npcs.get(npc) else {
+        return;
+    };
+    let Ok((conversation, mut flags, player_at)) = players.get_mut(player) else {
+        return;
+    };
+    if conversation.is_some() {
+        return;
+    }
+    let distance = player_at
+        .translation
+        .truncate()
+        .distance(npc_at.translation.truncate());
+    if distance > TALK_RANGE {
+        return;
+    }
+    let Some(dialogue) = dialogues.get(*dialogue_id) else {
+        tracing::warn!("NPC {npc:?} references unknown dialogue {dialogue_id}");
+        return;
+    };
+
+    enter_node(
+        &mut commands,
+        player,
+        npc,
+        dialogue,
+        dialogue.start.clone(),
+        &mut flags,
+    );
+}
+
+/// Advance a conversation by one of the choices available to the player.
+pub fn choose_dialogue_option(
+    trigger: On<ChooseDialogueOption>,
+    mut commands: Commands,
+    dialogues: Res<ContentRegistry<DialogueDef>>,
+    mut players: Query<(&Conversation, &mut StoryFlags)>,
+) {
+    let ChooseDialogueOption { player, choice } = *trigger;
+
+    let Ok((conversation, mut flags)) = players.get_mut(player) else {
+        return;
+    };
+    let conversation = conversation.clone();
+    let Some(dialogue) = dialogues.get(conversation.dialogue) else {
+        end_conversation(&mut commands, player, conversation.dialogue);
+        return;
+    };
+    let Some(node) = dialogue.node(&conversation.node) else {
+        end_conversation(&mut commands, player, dialogue.id);
+        return;
+    };
+    let Some(picked) = node
+        .choices
+        .iter()
+        .filter(|c| c.is_available(&flags.0))
+        .nth(choice)
+    else {
+        return;
+    };
+
+    apply_effects(&picked.effects, &mut flags);
+    match &picked.target {
+        Some(target) => enter_node(
+            &mut commands,
+            player,
+            conversation.npc,
+            dialogue,
+            target.clone(),
+            &mut flags,
+        ),
+        None => end_conversation(&mut commands, player, dialogue.id),
+    }
+}
+
+fn enter_node(
+    commands: &mut Commands,
+    player: Entity,
+    npc: Entity,
+    dialogue: &DialogueDef,
+    node_id: String,
+    flags: &mut StoryFlags,
+) {
+    let Some(node) = dialogue.node(&node_id) else {
+        tracing::warn!("Dialogue '{}' has no node '{}'", dialogue.name, node_id);
+        end_conversation(commands, player, dialogue.id);
+        return;
+    };
+
+    apply_effects(&node.effects, flags);
+    commands.entity(player).insert(Conversation {
+        dialogue: dialogue.id,
+        node: node_id.clone(),
+        npc,
+    });
+    commands.trigger(DialogueNodeEntered {
+        player,
+        dialogue: dialogue.id,
+        node: node_id,
+    });
+}
+
+fn end_conversation(commands: &mut Commands, player: Entity, dialogue: Uuid) {
+    commands.entity(player).remove::<Conversation>();
+    commands.trigger(DialogueEnded { player, dialogue });
+}
+
+fn apply_effects(effects: &[DialogueEffect], flags: &mut StoryFlags) {
+    for effect in effects {
+        match effect {
+            DialogueEffect::SetFlag { flag, value } => flags.set(flag, *value),
+        }
+    }
+}
+
+/// Turn dialogue messages from clients into events for the players they control.
+pub fn receive_dialogue_messages(
+    mut commands: Commands,
+    mut links: Query<(
+        &ControlledByRemote,
+        &mut MessageReceiver<TalkToNpc>,
+        &mut MessageReceiver<PickDialogueChoice>,
+    )>,
+    players: Query<(), With<StoryFlags>>,
+) {
+    for (controlled, mut talk_receiver, mut choice_receiver) in links.iter_mut() {
+        let Some(player) = controlled
+            .collection()
+            .iter()
+            .copied()
+            .find(|entity| players.contains(*entity))
+        else {
+            talk_receiver.receive().for_each(drop);
+            choice_receiver.receive().for_each(drop);
+            continue;
+        };
+
+        for TalkToNpc { npc } in talk_receiver.receive() {
+            commands.trigger(StartDialogue { player, npc });
+        }
+        for PickDialogueChoice { choice } in choice_receiver.receive() {
+            commands.trigger(ChooseDialogueOption { player, choice });
+        }
+    }
+}
+
+/// Send the current line to the client controlling the player.
+pub fn send_dialogue_line(
+    trigger: On<DialogueNodeEntered>,
+    dialogues: Res<ContentRegistry<DialogueDef>>,
+    players: Query<(&ControlledBy, &StoryFlags)>,
+    mut senders: Query<&mut MessageSender<DialogueLine>>,
+) {
+    let Ok((controlled_by, flags)) = players.get(trigger.player) else {
+        return;
+    };
+    let Ok(mut sender) = senders.get_mut(controlled_by.owner) else {
+        return;
+    };
+    let Some(node) = dialogues
+        .get(trigger.dialogue)
+        .and_then(|dialogue| dialogue.node(&trigger.node))
+    else {
+        return;
+    };
+
+    sender.send::<ReliableChannel>(DialogueLine {
+        speaker: node.speaker.clone(),
+        text: node.text.clone(),
+        choices: node
+            .choices
+            .iter()
+            .filter(|c| c.is_available(&flags.0))
+            .map(|c| c.text.clone())
+            .collect(),
+    });
+}
+
+/// Tell the client controlling the player that its conversation ended.
+pub fn send_dialogue_closed(
+    trigger: On<DialogueEnded>,
+    players: Query<&ControlledBy>,
+    mut senders: Query<&mut MessageSender<DialogueClosed>>,
+) {
+    let Ok(controlled_by) = players.get(trigger.player) else {
+        return;
+    };
+    if let Ok(mut sender) = senders.get_mut(controlled_by.owner) {
+        sender.send::<ReliableChannel>(DialogueClosed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use roguebench_core::{DialogueChoice, DialogueNode, FlagCondition};
+
+    #[derive(Resource, Default)]
+    struct Log(Vec<String>);
+
+    fn choice(text: &str, target: Option<&str>) -> DialogueChoice {
+        DialogueChoice {
+            text: text.to_string(),
+            target: target.map(str::to_string),
+            conditions: Vec::new(),
+            effects: Vec::new(),
+        }
+    }
+
+    fn gate_dialogue() -> DialogueDef {
+        let mut bribe = choice("Here's some gold", Some("open"));
+        bribe.conditions.push(FlagCondition {
+            flag: "has_gold".to_string(),
+            value: true,
+        });
+
+        let mut dialogue = DialogueDef::new("Gate", "greet");
+        dialogue.nodes = vec![
+            DialogueNode {
+                id: "greet".to_string(),
+                speaker: "Guard".to_string(),
+                text: "Halt!".to_string(),
+                effects: Vec::new(),
+                choices: vec![bribe, choice("Goodbye", None)],
+            },
+            DialogueNode {
+                id: "open".to_string(),
+                speaker: "Guard".to_string(),
+                text: "Go on through.".to_string(),
+                effects: vec![DialogueEffect::SetFlag {
+                    flag: "gate_open".to_string(),
+                    value: true,
+                }],
+                choices: vec![choice("Thanks", None)],
+            },
+        ];
+        dialogue
+    }
+
+    fn test_app(dialogue: &DialogueDef) -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+
+        let mut registry = ContentRegistry::<DialogueDef>::default();
+        registry.insert(dialogue.clone());
+        app.insert_resource(registry);
+        app.init_resource::<Log>();
+
+        app.add_observer(start_dialogue);
+        app.add_observer(choose_dialogue_option);
+        app.add_observer(|trigger: On<DialogueNodeEntered>, mut log: ResMut<Log>| {
+            log.0.push(trigger.node.clone());
+        });
+        app.add_observer(|_: On<DialogueEnded>, mut log: ResMut<Log>| {
+            log.0.push("<end>".to_string());
+        });
+        app
+    }
+
+    #[test]
+    fn conditions_gate_choices_and_effects_set_flags() {
+        let dialogue = gate_dialogue();
+        let mut app = test_app(&dialogue);
+
+        let npc = app
+            .world_mut()
+            .spawn((DialogueRef(dialogue.id), Transform::default()))
+            .id();
+        let player = app
+            .world_mut()
+            .spawn((StoryFlags::default(), Transform::default()))
+            .id();
+
+        app.world_mut()
+            .commands()
+            .trigger(StartDialogue { player, npc });
+        app.update();
+        assert_eq!(
+            app.world().get::<Conversation>(player).unwrap().node,
+            "greet"
+        );
+
+        // Without gold the bribe is hidden, so choice 0 is "Goodbye"
+        app.world_mut()
+            .commands()
+            .trigger(ChooseDialogueOption { player, choice: 0 });
+        app.update();
+        assert!(app.world().get::<Conversation>(player).is_none());
+
+        // With gold the bribe is offered first and opens the gate
+        app.world_mut()
+            .get_mut::<StoryFlags>(player)
+            .unwrap()
+            .set("has_gold", true);
+        app.world_mut()
+            .commands()
+            .trigger(StartDialogue { player, npc });
+        app.update();
+        app.world_mut()
+            .commands()
+            .trigger(ChooseDialogueOption { player, choice: 0 });
+        app.update();
+
+        assert_eq!(
+            app.world().get::<Conversation>(player).unwrap().node,
+            "open"
+        );
+        assert!(
+            app.world()
+                .get::<StoryFlags>(player)
+                .unwrap()
+                .is_set("gate_open")
+        );
+        assert_eq!(
+            app.world().resource::<Log>().0,
+            vec!["greet", "<end>", "greet", "open"]
+        );
+    }
+
+    #[test]
+    fn conversations_are_tracked_per_player() {
+        let dialogue = gate_dialogue();
+        let mut app = test_app(&dialogue);
+
+        let npc = app
+            .world_mut()
+            .spawn((DialogueRef(dialogue.id), Transform::default()))
+            .id();
+        let rich = app
+            .world_mut()
+            .spawn((
+                StoryFlags(HashSet::from(["has_gold".to_string()])),
+                Transform::default(),
+            ))
+            .id();
+        let poor = app
+            .world_mut()
+            .spawn((StoryFlags::default(), Transform::default()))
+            .id();
+
+        for player in [rich, poor] {
+            app.world_mut()
+                .commands()
+                .trigger(StartDialogue { player, npc });
+        }
+        app.update();
+
+        // The same choice index means different things to each player
+        for player in [rich, poor] {
+            app.world_mut()
+                .commands()
+                .trigger(ChooseDialogueOption { player, choice: 0 });
+        }
+        app.update();
+
+        assert_eq!(app.world().get::<Conversation>(rich).unwrap().node, "open");
+        assert!(app.world().get::<Conversation>(poor).is_none());
+    }
+
+    #[test]
+    fn npcs_only_talk_to_players_nearby() {
+        let dialogue = gate_dialogue();
+        let mut app = test_app(&dialogue);
+
+        let npc = app
+            .world_mut()
+            .spawn((DialogueRef(dialogue.id), Transform::default()))
+            .id();
+        let mut spawn_player = |x: f32| {
+            app.world_mut()
+                .spawn((StoryFlags::default(), Transform::from_xyz(x, 0.0, 0.0)))
+                .id()
+        };
+        let near = spawn_player(TALK_RANGE);
+        let far = spawn_player(TALK_RANGE + 1.0);
+        for player in [near, far] {
+            app.world_mut()
+                .commands()
+                .trigger(StartDialogue { player, npc });
+        }
+        app.update();
+
+        assert!(app.world().get::<Conversation>(near).is_some());
+        assert!(app.world().get::<Conversation>(far).is_none());
+        assert_eq!(app.world().resource::<Log>().0, vec!["greet"]);
+    }
+}
@@ -0,0 +1,502 @@
+//! Wave-based arena encounters.
+
+use std::collections::{HashMap, VecDeque};
+
+use bevy::prelude::*;
+use lightyear::prelude::{NetworkTarget, Replicate};
+use rand::Rng;
+use roguebench_core::{
+    ClearCondition, EncounterDef, MAX_WAVE_SPAWNS, RoomDef, SpawnSelection, WaveDef, WaveSpawn,
+};
+use roguebench_protocol::{EncounterPhase, Health, RoomLayout, WaveProgress};
+use uuid::Uuid;
+
+use crate::content::{ContentAppExt, ContentRegistry};
+use crate::resources::EntityTemplates;
+use crate::rng::{RngStream, RunRng};
+use crate::room::ActiveRoom;
+use crate::systems::spawn_from_template;
+
+/// Server-side state of a running encounter.
+///
+/// Lives on the same entity as its replicated [`WaveProgress`].
+#[derive(Component, Debug, Clone)]
+pub struct Encounter {
+    /// ID of the [`EncounterDef`] being run.
+    pub def: Uuid,
+    /// Countdown to the wave start while waiting, or to the next spawn while spawning.
+    timer: f32,
+    /// Seconds since the current wave started spawning.
+    elapsed: f32,
+    /// Templates of the current wave still to be spawned.
+    pending: VecDeque<Uuid>,
+    /// Position in the cycle of spawn points.
+    spawn_cursor: usize,
+}
+
+/// Marks an entity spawned by an encounter, pointing at the encounter entity.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct EncounterMember(pub Entity);
+
+/// Event requesting that an encounter starts in the active room.
+#[derive(Event, Debug, Clone)]
+pub struct StartEncounter {
+    pub encounter: Uuid,
+}
+
+/// Event fired when a wave starts spawning.
+#[derive(Event, Debug, Clone)]
+pub struct WaveStarted {
+    pub encounter: Entity,
+    pub wave: u32,
+}
+
+/// Event fired when a wave's clear condition is met.
+#[derive(Event, Debug, Clone)]
+pub struct WaveCleared {
+    pub encounter: Entity,
+    pub wave: u32,
+}
+
+/// Event fired when the last wave of an encounter is cleared.
+#[derive(Event, Debug, Clone)]
+pub struct EncounterCleared {
+    pub encounter: Entity,
+}
+
+/// Plugin for encounter content and the encounter state machine.
+pub struct EncounterPlugin;
+
+impl Plugin for EncounterPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_content::<EncounterDef>();
+
+        app.add_systems(FixedUpdate, run_encounters);
+
+        app.add_observer(start_encounter);
+    }
+}
+
+/// Spawn an encounter entity waiting for its first wave.
+pub fn start_encounter(
+    trigger: On<StartEncounter>,
+    mut commands: Commands,
+    encounters: Res<ContentRegistry<EncounterDef>>,
+) {
+    let Some(def) = encounters.get(trigger.encounter) else {
+        tracing::warn!("Cannot start unknown encounter {}", trigger.encounter);
+        return;
+    };
+    let Some(first) = def.waves.first() else {
+        tracing::warn!("Encounter '{}' has no waves", def.name);
+        return;
+    };
+    tracing::info!("Starting encounter: {}", def.name);
+
+    commands.spawn((
+        Encounter {
+            def: def.id,
+            timer: first.start_delay,
+            elapsed: 0.0,
+            pending: VecDeque::new(),
+            spawn_cursor: 0,
+        },
+        WaveProgress {
+            wave: 0,
+            total_waves: def.waves.len() as u32,
+            phase: EncounterPhase::Waiting,
+            remaining: first.total(),
+        },
+        Replicate::to_clients(NetworkTarget::All),
+    ));
+}
+
+/// Advance every running encounter by one fixed tick.
+#[allow(clippy::too_many_arguments)]
+pub fn run_encounters(
+    mut commands: Commands,
+    time: Res<Time>,
+    defs: Res<ContentRegistry<EncounterDef>>,
+    templates: Res<EntityTemplates>,
+    room: Option<Res<ActiveRoom>>,
+    mut rng: ResMut<RunRng>,
+    mut encounters: Query<(Entity, &mut Encounter, &mut WaveProgress)>,
+    members: Query<(&EncounterMember, Option<&Health>)>,
+) {
+    let dt = time.delta_secs();
+    let room = room.as_deref().map(|active| &active.0);
+
+    let mut alive: HashMap<Entity, u32> = HashMap::new();
+    for (member, health) in members.iter() {
+        if health.is_none_or(|health| health.0 > 0) {
+            *alive.entry(member.0).or_default() += 1;
+        }
+    }
+
+    for (entity, mut encounter, mut progress) in encounters.iter_mut() {
+        let Some(def) = defs.get(encounter.def) else {
+            continue;
+        };
+        let Some(wave) = def.waves.get(progress.wave as usize) else {
+            continue;
+        };
+
+        let mut next = progress.clone();
+        let alive = alive.get(&entity).copied().unwrap_or(0);
+
+        // One transition per tick, so members spawned this tick are counted
+        // before the wave's clear condition is checked.
+        match progress.phase {
+            EncounterPhase::Waiting => {
+                encounter.timer -= dt;
+                if encounter.timer <= 0.0 {
+                    encounter.pending = expand_spawns(&wave.spawns);
+                    encounter.timer = 0.0;
+                    encounter.elapsed = 0.0;
+                    next.phase = EncounterPhase::Spawning;
+                    commands.trigger(WaveStarted {
+                        encounter: entity,
+                        wave: progress.wave,
+                    });
+                }
+            }
+            EncounterPhase::Spawning => {
+                encounter.elapsed += dt;
+                encounter.timer -= dt;
+                while encounter.timer <= 0.0 {
+                    let Some(template) = encounter.pending.pop_front() else {
+                        break;
+                    };
+                    let position =
+                        spawn_position(room, wave, &mut encounter.spawn_cursor, &mut rng);
+                    match templates.0.get(&template) {
+                        Some(entity_def) => {
+                            spawn_from_template(&mut commands, entity_def).insert((
+                                EncounterMember(entity),
+                                Transform::from_translation(position.extend(0.0)),
+                            ));
+                        }
+                        None => tracing::warn!("Encounter spawns unknown entity {template}"),
+                    }
+                    encounter.timer += wave.spawn_delay;
+                }
+                if encounter.pending.is_empty() {
+                    next.phase = EncounterPhase::Active;
+                }
+            }
+            EncounterPhase::Active => {
+                encounter.elapsed += dt;
+                let cleared = match wave.clear {
+                    ClearCondition::AllDefeated => alive == 0,
+                    ClearCondition::Remaining { count } => alive <= count,
+                    ClearCondition::Survive { seconds } => encounter.elapsed >= seconds,
+                };
+                if cleared {
+                    commands.trigger(WaveCleared {
+                        encounter: entity,
+                        wave: progress.wave,
+                    });
+                    match def.waves.get(progress.wave as usize + 1) {
+                        Some(following) => {
+                            next.wave += 1;
+                            next.phase = EncounterPhase::Waiting;
+                            encounter.timer = following.start_delay;
+                        }
+                        None => {
+                            next.phase = EncounterPhase::Cleared;
+                            commands.trigger(EncounterCleared { encounter: entity });
+                        }
+                    }
+                }
+            }
+            EncounterPhase::Cleared => {}
+        }
+
+        next.remaining = match next.phase {
+            EncounterPhase::Cleared => 0,
+            _ if next.wave != progress.wave => def.waves[next.wave as usize].total(),
+            EncounterPhase::Waiting => wave.total(),
+            _ => alive + encounter.pending.len() as u32,
+        };
+        progress.set_if_neq(next);
+    }
+}
+
+/// Queue up a wave's spawns, no more than a valid wave could hold.
+fn expand_spawns(spawns: &[WaveSpawn]) -> VecDeque<Uuid> {
+    spawns
+        .iter()
+        .flat_map(|spawn| std::iter::repeat_n(spawn.entity, spawn.count as usize))
+        .take(MAX_WAVE_SPAWNS as usize)
+        .collect()
+}
+
+/// Pick the world position of the next spawn from the wave's matching spawn points.
+///
+/// Falls back to the middle of the room, or the origin without one.
+fn spawn_position(
+    room: Option<&RoomDef>,
+    wave: &WaveDef,
+    cursor: &mut usize,
+    rng: &mut RunRng,
+) -> Vec2 {
+    let Some(room) = room else {
+        return Vec2::ZERO;
+    };
+    let candidates: Vec<_> = room
+        .spawn_points
+        .iter()
+        .filter(|point| match &wave.spawn_selection {
+            SpawnSelection::Sequential | SpawnSelection::Random => true,
+            SpawnSelection::Tagged { tag } => &point.tag == tag,
+        })
+        .collect();
+
+    if candidates.is_empty() {
+        return RoomLayout::tile_center(room.width / 2, room.height / 2);
+    }
+    let index = match wave.spawn_selection {
+        SpawnSelection::Random => rng
+            .stream(RngStream::Worldgen)
+            .gen_range(0..candidates.len()),
+        _ => {
+            let index = *cursor % candidates.len();
+            *cursor += 1;
+            index
+        }
+    };
+    let point = candidates[index];
+    RoomLayout::tile_center(point.x, point.y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::time::TimeUpdateStrategy;
+    use roguebench_core::{EntityDef, SpawnPoint};
+    use std::time::Duration;
+
+    #[derive(Resource, Default)]
+    struct Log(Vec<String>);
+
+    fn test_app(encounter: &EncounterDef, entities: &[EntityDef], room: &RoomDef) -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            100,
+        )));
+
+        let mut registry = ContentRegistry::<EncounterDef>::default();
+        registry.insert(encounter.clone());
+        app.insert_resource(registry);
+        app.insert_resource(ActiveRoom(room.clone()));
+        app.insert_resource(RunRng::new(7));
+        app.insert_resource(EntityTemplates(
+            entities.iter().map(|def| (def.id, def.clone())).collect(),
+        ));
+
+        app.add_systems(FixedUpdate, run_encounters);
+        app.add_observer(start_encounter);
+
+        app.init_resource::<Log>();
+        app.add_observer(|t: On<WaveStarted>, mut log: ResMut<Log>| {
+            log.0.push(format!("started {}", t.wave));
+        });
+        app.add_observer(|t: On<WaveCleared>, mut log: ResMut<Log>| {
+            log.0.push(format!("cleared {}", t.wave));
+        });
+        app.add_observer(|_: On<EncounterCleared>, mut log: ResMut<Log>| {
+            log.0.push("encounter cleared".to_string());
+        });
+        app
+    }
+
+    fn progress(app: &mut App) -> WaveProgress {
+        let mut query = app.world_mut().query::<&WaveProgress>();
+        query.single(app.world()).unwrap().clone()
+    }
+
+    fn members(app: &mut App) -> Vec<(Entity, Vec2)> {
+        let mut query = app
+            .world_mut()
+            .query_filtered::<(Entity, &Transform), With<EncounterMember>>();
+        query
+            .iter(app.world())
+            .map(|(entity, transform)| (entity, transform.translation.truncate()))
+            .collect()
+    }
+
+    #[test]
+    fn encounter_runs_waves_until_cleared() {
+        let goblin = EntityDef::new("Goblin", 10);
+        let orc = EntityDef::new("Orc", 40);
+        let mut room = RoomDef::new("Arena", 5, 5);
+        for (x, y) in [(0, 0), (4, 4)] {
+            room.spawn_points.push(SpawnPoint {
+                x,
+                y,
+                tag: "enemy".to_string(),
+            });
+        }
+        let mut second = WaveDef::new(vec![WaveSpawn {
+            entity: orc.id,
+            count: 1,
+        }]);
+        second.start_delay = 1.0;
+        let encounter = EncounterDef::new(
+            "Ambush",
+            vec![
+                WaveDef::new(vec![WaveSpawn {
+                    entity: goblin.id,
+                    count: 3,
+                }]),
+                second,
+            ],
+        );
+        let mut app = test_app(&encounter, &[goblin, orc], &room);
+
+        app.world_mut().commands().trigger(StartEncounter {
+            encounter: encounter.id,
+        });
+        for _ in 0..3 {
+            app.update();
+        }
+
+        let spawned = members(&mut app);
+        assert_eq!(spawned.len(), 3);
+        assert_eq!(progress(&mut app).phase, EncounterPhase::Active);
+        assert_eq!(progress(&mut app).remaining, 3);
+        // Spawn points are used in turn
+        let first = RoomLayout::tile_center(0, 0);
+        let last = RoomLayout::tile_center(4, 4);
+        assert_eq!(spawned.iter().filter(|(_, p)| *p == first).count(), 2);
+        assert_eq!(spawned.iter().filter(|(_, p)| *p == last).count(), 1);
+
+        // Killing the wave moves on to the second one after its delay
+        for (entity, _) in spawned {
+            app.world_mut().get_mut::<Health>(entity).unwrap().0 = 0;
+        }
+        app.update();
+        assert_eq!(progress(&mut app).wave, 1);
+        assert_eq!(progress(&mut app).phase, EncounterPhase::Waiting);
+        for _ in 0..15 {
+            app.update();
+        }
+        assert_eq!(progress(&mut app).phase, EncounterPhase::Active);
+        assert_eq!(members(&mut app).len(), 4);
+
+        let (orc_entity, _) = *members(&mut app)
+            .iter()
+            .find(|(entity, _)| app.world().get::<Health>(*entity).unwrap().0 > 0)
+            .unwrap();
+        app.world_mut().entity_mut(orc_entity).despawn();
+        app.update();
+
+        assert_eq!(progress(&mut app).phase, EncounterPhase::Cleared);
+        assert_eq!(
+            app.world().resource::<Log>().0,
+            vec![
+                "started 0",
+                "cleared 0",
+                "started 1",
+                "cleared 1",
+                "encounter cleared"
+            ]
+        );
+    }
+
+    #[test]
+    fn wave_spawns_at_tagged_points_and_clears_on_survival() {
+        let bat = EntityDef::new("Bat", 5);
+        let mut room = RoomDef::new("Cave", 5, 5);
+        room.spawn_points.push(SpawnPoint {
+            x: 1,
+            y: 1,
+            tag: "player".to_string(),
+        });
+        room.spawn_points.push(SpawnPoint {
+            x: 3,
+            y: 2,
+            tag: "ceiling".to_string(),
+        });
+        let mut wave = WaveDef::new(vec![WaveSpawn {
+            entity: bat.id,
+            count: 2,
+        }]);
+        wave.spawn_selection = SpawnSelection::Tagged {
+            tag: "ceiling".to_string(),
+        };
+        wave.spawn_delay = 0.5;
+        wave.clear = ClearCondition::Survive { seconds: 2.0 };
+        let encounter = EncounterDef::new("Swarm", vec![wave]);
+        let mut app = test_app(&encounter, std::slice::from_ref(&bat), &room);
+
+        app.world_mut().commands().trigger(StartEncounter {
+            encounter: encounter.id,
+        });
+        for _ in 0..3 {
+            app.update();
+        }
+        // The second bat waits for the spawn delay
+        assert_eq!(members(&mut app).len(), 1);
+        assert_eq!(progress(&mut app).phase, EncounterPhase::Spawning);
+
+        for _ in 0..10 {
+            app.update();
+        }
+        let spawned = members(&mut app);
+        assert_eq!(spawned.len(), 2);
+        assert!(
+            spawned
+                .iter()
+                .all(|(_, p)| *p == RoomLayout::tile_center(3, 2))
+        );
+
+        // Nobody has died, but surviving long enough clears the wave
+        for _ in 0..15 {
+            app.update();
+        }
+        assert_eq!(progress(&mut app).phase, EncounterPhase::Cleared);
+    }
+
+    #[test]
+    fn random_spawns_are_identical_for_identical_seeds() {
+        let rat = EntityDef::new("Rat", 1);
+        let mut room = RoomDef::new("Sewer", 6, 6);
+        for x in 0..6 {
+            room.spawn_points.push(SpawnPoint {
+                x,
+                y: x,
+                tag: String::new(),
+            });
+        }
+        let mut wave = WaveDef::new(vec![WaveSpawn {
+            entity: rat.id,
+            count: 20,
+        }]);
+        wave.spawn_selection = SpawnSelection::Random;
+        let encounter = EncounterDef::new("Rats", vec![wave]);
+
+        let run = |seed| {
+            let mut app = test_app(&encounter, std::slice::from_ref(&rat), &room);
+            app.insert_resource(RunRng::new(seed));
+            app.world_mut().commands().trigger(StartEncounter {
+                encounter: encounter.id,
+            });
+            for _ in 0..3 {
+                app.update();
+            }
+            let mut positions = members(&mut app);
+            positions.sort_by_key(|(entity, _)| *entity);
+            positions
+                .into_iter()
+                .map(|(_, position)| position)
+                .collect::<Vec<_>>()
+        };
+
+        let first = run(1234);
+        assert_eq!(first.len(), 20);
+        assert_eq!(first, run(1234));
+        assert_ne!(first, run(4321));
+    }
+}
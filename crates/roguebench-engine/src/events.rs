@@ -0,0 +1,20 @@
+//! Gameplay events shared between plugins.
+
+use bevy::prelude::*;
+use uuid::Uuid;
+
+/// Event fired when an entity spawned from a template is killed.
+#[derive(Event, Debug, Clone)]
+pub struct EntityKilled {
+    /// The entity credited with the kill.
+    pub killer: Entity,
+    /// ID of the [`EntityDef`](roguebench_core::EntityDef) the victim was spawned from.
+    pub template: Uuid,
+}
+
+/// Event fired when a player enters a room.
+#[derive(Event, Debug, Clone)]
+pub struct RoomEntered {
+    pub player: Entity,
+    pub room: Uuid,
+}
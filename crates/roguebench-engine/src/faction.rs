@@ -0,0 +1,194 @@
+//! Teams and faction relationships.
+
+use bevy::prelude::*;
+use roguebench_core::{FactionDef, Relation};
+use uuid::Uuid;
+
+use crate::content::{ContentAppExt, ContentRegistry};
+
+/// The faction an entity fights for.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Team(pub Uuid);
+
+impl ContentRegistry<FactionDef> {
+    /// How an entity on team `a` regards one on team `b`.
+    ///
+    /// Teammates are friendly. Otherwise `a`'s own row of the matrix wins,
+    /// falling back to `b`'s so one-sided entries apply both ways. Entities
+    /// without a team are neutral to everyone.
+    pub fn relation(&self, a: Option<&Team>, b: Option<&Team>) -> Relation {
+        let (Some(a), Some(b)) = (a, b) else {
+            return Relation::Neutral;
+        };
+        let row = |from: &Team, to: &Team| self.get(from.0).and_then(|def| def.relation_to(to.0));
+        if a == b {
+            return Relation::Friendly;
+        }
+        row(a, b).or_else(|| row(b, a)).unwrap_or_default()
+    }
+
+    /// Whether an entity on team `a` attacks one on team `b` on sight.
+    pub fn is_hostile(&self, a: Option<&Team>, b: Option<&Team>) -> bool {
+        self.relation(a, b) == Relation::Hostile
+    }
+}
+
+/// Plugin for faction content.
+pub struct FactionPlugin;
+
+impl Plugin for FactionPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_content::<FactionDef>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::time::TimeUpdateStrategy;
+    use roguebench_core::{AiProfile, AiState, RoomDef, StateMachine};
+    use roguebench_protocol::{Health, Projectile};
+    use std::time::Duration;
+
+    use crate::ai::{AiBrain, AiPlugin};
+    use crate::combat::{ApplyDamage, Hitbox, apply_damage};
+    use crate::fsm::Fsm;
+    use crate::projectile::{ProjectileFlight, move_projectiles, spawn_projectile};
+    use crate::rng::RunRng;
+    use crate::room::CollisionMap;
+
+    struct Factions {
+        heroes: Team,
+        monsters: Team,
+        villagers: Team,
+    }
+
+    fn test_app() -> (App, Factions) {
+        let villagers = FactionDef::new("Villagers");
+        let heroes = FactionDef::new("Heroes").with_relation(villagers.id, Relation::Friendly);
+        // Only the monsters' row mentions the heroes; the lookup falls back to it
+        let monsters = FactionDef::new("Monsters").with_relation(heroes.id, Relation::Hostile);
+        let factions = Factions {
+            heroes: Team(heroes.id),
+            monsters: Team(monsters.id),
+            villagers: Team(villagers.id),
+        };
+
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            100,
+        )));
+        let mut registry = ContentRegistry::<FactionDef>::default();
+        registry.replace_all([heroes, monsters, villagers]);
+        app.insert_resource(registry);
+        app.insert_resource(CollisionMap::new(&RoomDef::new("Field", 30, 30)));
+        app.insert_resource(RunRng::new(3));
+
+        app.add_plugins(AiPlugin);
+        app.add_systems(FixedUpdate, move_projectiles);
+        app.add_observer(apply_damage);
+        app.update();
+        (app, factions)
+    }
+
+    fn spawn(app: &mut App, team: Team, at: Vec2) -> Entity {
+        app.world_mut()
+            .spawn((
+                team,
+                Health(20),
+                Hitbox::default(),
+                Transform::from_translation(at.extend(0.0)),
+            ))
+            .id()
+    }
+
+    fn health(app: &App, entity: Entity) -> i32 {
+        app.world().get::<Health>(entity).unwrap().0
+    }
+
+    #[test]
+    fn relations_fall_back_across_the_matrix() {
+        let (app, factions) = test_app();
+        let registry = app.world().resource::<ContentRegistry<FactionDef>>();
+        let Factions {
+            heroes,
+            monsters,
+            villagers,
+        } = &factions;
+
+        assert!(registry.is_hostile(Some(heroes), Some(monsters)));
+        assert!(registry.is_hostile(Some(monsters), Some(heroes)));
+        assert_eq!(
+            registry.relation(Some(villagers), Some(heroes)),
+            Relation::Friendly
+        );
+        assert_eq!(
+            registry.relation(Some(monsters), Some(monsters)),
+            Relation::Friendly
+        );
+        assert_eq!(
+            registry.relation(Some(monsters), Some(villagers)),
+            Relation::Neutral
+        );
+        assert_eq!(registry.relation(None, Some(heroes)), Relation::Neutral);
+    }
+
+    #[test]
+    fn friendly_fire_is_blocked_but_neutrals_can_be_hurt() {
+        let (mut app, factions) = test_app();
+        let hero = spawn(&mut app, factions.heroes, Vec2::new(100.0, 100.0));
+        let ally = spawn(&mut app, factions.villagers, Vec2::new(150.0, 100.0));
+        let monster = spawn(&mut app, factions.monsters, Vec2::new(200.0, 100.0));
+        let bystander = spawn(&mut app, factions.villagers, Vec2::new(100.0, 200.0));
+
+        // The shot passes through the friendly villager and hits the monster
+        let mut commands = app.world_mut().commands();
+        spawn_projectile(
+            &mut commands,
+            Projectile {
+                origin: Vec2::new(100.0, 100.0),
+                velocity: Vec2::new(2_000.0, 0.0),
+            },
+            ProjectileFlight {
+                shooter: hero,
+                damage: 5,
+                radius: 2.0,
+                remaining: 500.0,
+            },
+        );
+        commands.trigger(ApplyDamage {
+            target: ally,
+            source: hero,
+            amount: 5,
+        });
+        commands.trigger(ApplyDamage {
+            target: bystander,
+            source: monster,
+            amount: 5,
+        });
+        app.world_mut().flush();
+        app.update();
+
+        assert_eq!(health(&app, ally), 20);
+        assert_eq!(health(&app, monster), 15);
+        assert_eq!(health(&app, bystander), 15);
+    }
+
+    #[test]
+    fn ai_only_targets_hostile_factions() {
+        let (mut app, factions) = test_app();
+        let grunt = spawn(&mut app, factions.monsters, Vec2::new(300.0, 300.0));
+        app.world_mut().entity_mut(grunt).insert((
+            AiBrain::new(AiProfile::grunt(), 20),
+            Fsm(StateMachine::new(AiState::Idle)),
+        ));
+        // The neutral villager is closer, but only the hero is worth chasing
+        spawn(&mut app, factions.villagers, Vec2::new(340.0, 300.0));
+        let hero = spawn(&mut app, factions.heroes, Vec2::new(300.0, 420.0));
+        app.update();
+
+        let brain = app.world().get::<AiBrain>(grunt).unwrap();
+        assert_eq!(brain.target.map(|(target, _)| target), Some(hero));
+    }
+}
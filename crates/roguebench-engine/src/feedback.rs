@@ -0,0 +1,170 @@
+//! Telling clients about gameplay events near their players.
+
+use bevy::prelude::*;
+use lightyear::prelude::{Channel, ControlledBy, Message, MessageSender};
+use roguebench_protocol::{
+    EntityDied, FeedbackChannel, HitLanded, ItemCollected, ReliableChannel, WaveComplete,
+    WaveProgress,
+};
+
+use crate::combat::DamageDealt;
+use crate::encounter::WaveCleared;
+use crate::interest::{CELL_SIZE, VIEW_DISTANCE};
+use crate::inventory::ItemPickedUp;
+use crate::progression::PlayerId;
+
+/// How far from a player events are still worth telling its client about.
+///
+/// However the player stands in its cell, everything this close is in a cell
+/// its client sees, so the client knows the entities an event refers to.
+pub const FEEDBACK_RADIUS: f32 = VIEW_DISTANCE as f32 * CELL_SIZE;
+
+/// Plugin sending gameplay events to the clients they matter to.
+pub struct FeedbackPlugin;
+
+impl Plugin for FeedbackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_observer(send_damage_feedback)
+            .add_observer(send_pickup_feedback)
+            .add_observer(send_wave_feedback);
+    }
+}
+
+/// Clients whose player is within [`FEEDBACK_RADIUS`] of `at`, or is one of
+/// the entities `involved`.
+pub fn audience(
+    at: Vec2,
+    involved: &[Entity],
+    players: impl IntoIterator<Item = (Entity, Vec2, Entity)>,
+) -> Vec<Entity> {
+    players
+        .into_iter()
+        .filter(|(player, position, _)| {
+            involved.contains(player) || position.distance(at) <= FEEDBACK_RADIUS
+        })
+        .map(|(_, _, client)| client)
+        .collect()
+}
+
+/// Players and the clients controlling them.
+type PlayerQuery<'w, 's> =
+    Query<'w, 's, (Entity, &'static Transform, &'static ControlledBy), With<PlayerId>>;
+
+/// Each player with its position and client.
+fn positions<'a>(players: &'a PlayerQuery) -> impl Iterator<Item = (Entity, Vec2, Entity)> + 'a {
+    players.iter().map(|(player, transform, controlled_by)| {
+        (
+            player,
+            transform.translation.truncate(),
+            controlled_by.owner,
+        )
+    })
+}
+
+/// Send `message` to each of `clients` that can take it.
+fn send_to<M: Message + Clone, C: Channel>(
+    senders: &mut Query<&mut MessageSender<M>>,
+    clients: &[Entity],
+    message: M,
+) {
+    for client in clients {
+        if let Ok(mut sender) = senders.get_mut(*client) {
+            sender.send::<C>(message.clone());
+        }
+    }
+}
+
+/// Show hits, and deaths, to the clients near the target.
+pub fn send_damage_feedback(
+    trigger: On<DamageDealt>,
+    targets: Query<&Transform>,
+    players: PlayerQuery,
+    mut hits: Query<&mut MessageSender<HitLanded>>,
+    mut deaths: Query<&mut MessageSender<EntityDied>>,
+) {
+    let Ok(transform) = targets.get(trigger.target) else {
+        return;
+    };
+    let at = transform.translation.truncate();
+    let clients = audience(at, &[trigger.target, trigger.source], positions(&players));
+
+    let hit = HitLanded {
+        target: trigger.target,
+        source: trigger.source,
+        amount: trigger.amount,
+    };
+    send_to::<_, FeedbackChannel>(&mut hits, &clients, hit);
+    if trigger.health <= 0 {
+        let death = EntityDied {
+            entity: trigger.target,
+        };
+        send_to::<_, ReliableChannel>(&mut deaths, &clients, death);
+    }
+}
+
+/// Show pickups to the clients near the player picking up.
+pub fn send_pickup_feedback(
+    trigger: On<ItemPickedUp>,
+    pickers: Query<&Transform>,
+    players: PlayerQuery,
+    mut senders: Query<&mut MessageSender<ItemCollected>>,
+) {
+    let Ok(transform) = pickers.get(trigger.picker) else {
+        return;
+    };
+    let at = transform.translation.truncate();
+    let clients = audience(at, &[trigger.picker], positions(&players));
+    let collected = ItemCollected {
+        picker: trigger.picker,
+        item: trigger.item,
+        count: trigger.count,
+    };
+    send_to::<_, ReliableChannel>(&mut senders, &clients, collected);
+}
+
+/// Tell everyone in the room that a wave was cleared.
+///
+/// There is a single active room, so that is every player.
+pub fn send_wave_feedback(
+    trigger: On<WaveCleared>,
+    encounters: Query<&WaveProgress>,
+    players: PlayerQuery,
+    mut senders: Query<&mut MessageSender<WaveComplete>>,
+) {
+    let last = encounters
+        .get(trigger.encounter)
+        .is_ok_and(|progress| progress.wave + 1 >= progress.total_waves);
+    let clients: Vec<_> = positions(&players).map(|(_, _, client)| client).collect();
+    let complete = WaveComplete {
+        wave: trigger.wave,
+        last,
+    };
+    send_to::<_, ReliableChannel>(&mut senders, &clients, complete);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_clients_near_the_event_or_involved_in_it_hear_of_it() {
+        let mut world = World::new();
+        let [near, far, involved] = [(); 3].map(|_| world.spawn_empty().id());
+        let [near_client, far_client, involved_client] = [(); 3].map(|_| world.spawn_empty().id());
+        let players = [
+            (near, Vec2::new(FEEDBACK_RADIUS - 1.0, 0.0), near_client),
+            (far, Vec2::new(FEEDBACK_RADIUS + 1.0, 0.0), far_client),
+            (
+                involved,
+                Vec2::new(0.0, 10.0 * FEEDBACK_RADIUS),
+                involved_client,
+            ),
+        ];
+
+        assert_eq!(audience(Vec2::ZERO, &[], players), vec![near_client]);
+        assert_eq!(
+            audience(Vec2::ZERO, &[involved], players),
+            vec![near_client, involved_client]
+        );
+    }
+}
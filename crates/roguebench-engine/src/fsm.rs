@@ -0,0 +1,203 @@
+//! State machines attached to entities.
+//!
+//! An [`Fsm`] component holds the runtime state; an [`FsmPlugin`] drives every
+//! machine of one state type each fixed tick, reading guards against a context
+//! component on the same entity.
+
+use std::fmt::Debug;
+
+use bevy::prelude::*;
+use roguebench_core::{StateMachine, StateMachineDef};
+
+/// Types usable as the states of an entity's state machine.
+pub trait FsmState: Clone + PartialEq + Debug + Send + Sync + 'static {}
+
+impl<T: Clone + PartialEq + Debug + Send + Sync + 'static> FsmState for T {}
+
+/// A state machine on an entity.
+#[derive(Component, Debug, Clone, PartialEq, Deref, DerefMut)]
+pub struct Fsm<S: FsmState>(pub StateMachine<S>);
+
+/// The declared states and transitions driving every [`Fsm<S>`] with context `C`.
+#[derive(Resource, Deref)]
+pub struct FsmRules<S: FsmState, C: Component>(pub StateMachineDef<S, C>);
+
+/// Event fired when an entity's machine enters a state, including its
+/// initial state when the machine is added.
+#[derive(Event, Debug, Clone)]
+pub struct StateEntered<S: FsmState> {
+    pub entity: Entity,
+    pub state: S,
+    /// The state left, or `None` for the initial state.
+    pub from: Option<S>,
+}
+
+/// Event fired when an entity's machine leaves a state, before the next one is entered.
+#[derive(Event, Debug, Clone)]
+pub struct StateExited<S: FsmState> {
+    pub entity: Entity,
+    pub state: S,
+    /// Seconds spent in the state.
+    pub after: f32,
+}
+
+/// Plugin driving state machines of type `S` from context component `C`.
+pub struct FsmPlugin<S: FsmState, C: Component> {
+    pub def: StateMachineDef<S, C>,
+}
+
+impl<S: FsmState, C: Component> Plugin for FsmPlugin<S, C> {
+    fn build(&self, app: &mut App) {
+        let problems = self.def.validate();
+        assert!(problems.is_empty(), "Invalid state machine: {problems:?}");
+
+        app.insert_resource(FsmRules(self.def.clone()));
+        app.add_systems(FixedUpdate, drive_state_machines::<S, C>);
+        app.add_observer(enter_initial_state::<S>);
+    }
+}
+
+/// Move an entity's machine to a state, firing the exit and enter events.
+///
+/// For systems that decide transitions themselves instead of through guards.
+pub fn enter_state<S: FsmState>(commands: &mut Commands, entity: Entity, fsm: &mut Fsm<S>, to: S) {
+    let transition = fsm.enter(to);
+    commands.trigger(StateExited {
+        entity,
+        state: transition.from.clone(),
+        after: transition.after,
+    });
+    commands.trigger(StateEntered {
+        entity,
+        state: transition.to,
+        from: Some(transition.from),
+    });
+}
+
+/// Advance every machine and take at most one passing transition each tick.
+pub fn drive_state_machines<S: FsmState, C: Component>(
+    mut commands: Commands,
+    time: Res<Time>,
+    rules: Res<FsmRules<S, C>>,
+    mut machines: Query<(Entity, &mut Fsm<S>, &C)>,
+) {
+    let dt = time.delta_secs();
+    for (entity, mut fsm, context) in machines.iter_mut() {
+        fsm.tick(dt);
+        if let Some(to) = rules.next(&fsm, context).cloned() {
+            enter_state(&mut commands, entity, &mut fsm, to);
+        }
+    }
+}
+
+fn enter_initial_state<S: FsmState>(
+    trigger: On<Add, Fsm<S>>,
+    mut commands: Commands,
+    machines: Query<&Fsm<S>>,
+) {
+    let entity = trigger.event_target();
+    if let Ok(fsm) = machines.get(entity) {
+        commands.trigger(StateEntered {
+            entity,
+            state: fsm.current().clone(),
+            from: None,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::time::TimeUpdateStrategy;
+    use std::time::Duration;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Stance {
+        Idle,
+        Alert,
+        Fleeing,
+    }
+
+    #[derive(Component)]
+    struct Senses {
+        threat: bool,
+        health: i32,
+    }
+
+    #[derive(Resource, Default)]
+    struct Log(Vec<String>);
+
+    fn test_app() -> App {
+        let def =
+            StateMachineDef::new(Stance::Idle, [Stance::Idle, Stance::Alert, Stance::Fleeing])
+                .transition_from_any(Stance::Fleeing, |s: &Senses, _| s.health < 5)
+                .transition(Stance::Idle, Stance::Alert, |s, _| s.threat)
+                .transition(Stance::Alert, Stance::Idle, |s, m| {
+                    !s.threat && m.time_in_state() >= 0.3
+                });
+
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            100,
+        )));
+        app.add_plugins(FsmPlugin { def });
+
+        app.init_resource::<Log>();
+        app.add_observer(|t: On<StateEntered<Stance>>, mut log: ResMut<Log>| {
+            log.0.push(format!("enter {:?}", t.state));
+        });
+        app.add_observer(|t: On<StateExited<Stance>>, mut log: ResMut<Log>| {
+            log.0.push(format!("exit {:?}", t.state));
+        });
+        app
+    }
+
+    fn run_ticks(app: &mut App, ticks: usize) {
+        for _ in 0..ticks {
+            app.update();
+        }
+    }
+
+    #[test]
+    fn machines_follow_guards_and_fire_events() {
+        let mut app = test_app();
+        let rules = app.world().resource::<FsmRules<Stance, Senses>>();
+        let fsm = Fsm(rules.start());
+        let entity = app
+            .world_mut()
+            .spawn((
+                fsm,
+                Senses {
+                    threat: false,
+                    health: 10,
+                },
+            ))
+            .id();
+        run_ticks(&mut app, 3);
+
+        app.world_mut().get_mut::<Senses>(entity).unwrap().threat = true;
+        run_ticks(&mut app, 3);
+        app.world_mut().get_mut::<Senses>(entity).unwrap().threat = false;
+        run_ticks(&mut app, 6);
+
+        app.world_mut().get_mut::<Senses>(entity).unwrap().health = 1;
+        run_ticks(&mut app, 3);
+
+        let fsm = app.world().get::<Fsm<Stance>>(entity).unwrap();
+        assert!(fsm.is(&Stance::Fleeing));
+        assert_eq!(fsm.history().count(), 3);
+        assert_eq!(
+            app.world().resource::<Log>().0,
+            vec![
+                "enter Idle",
+                "exit Idle",
+                "enter Alert",
+                "exit Alert",
+                "enter Idle",
+                "exit Idle",
+                "enter Fleeing",
+            ]
+        );
+    }
+}
@@ -0,0 +1,299 @@
+//! Interest management: which replicated entities each client receives.
+//!
+//! Replicated entities with a transform belong to the square cell of the
+//! world they stand in; a client receives those in the cells around its
+//! player. The rest, such as the room layout, belong to the room as a whole
+//! and go to every client playing in it.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use lightyear::prelude::{
+    ControlledBy, Replicate, ReplicationSender, Room, RoomEvent, RoomPlugin, RoomTarget,
+};
+use roguebench_protocol::TILE_SIZE;
+
+use crate::progression::PlayerId;
+
+/// Side of an interest cell.
+pub const CELL_SIZE: f32 = 8.0 * TILE_SIZE;
+
+/// How many cells past the one its player stands in a client can see.
+pub const VIEW_DISTANCE: i32 = 2;
+
+/// Plugin limiting replication to what each client's player is near.
+pub struct InterestPlugin;
+
+impl Plugin for InterestPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<RoomPlugin>() {
+            app.add_plugins(RoomPlugin);
+        }
+        app.init_resource::<InterestRooms>()
+            .add_systems(
+                Update,
+                (join_room, track_cells, update_views, drop_empty_cells).chain(),
+            )
+            .add_observer(enter_room)
+            .add_observer(leave_room)
+            .add_observer(leave_cell);
+    }
+}
+
+/// Cell of the world containing `position`.
+pub fn cell_at(position: Vec2) -> IVec2 {
+    (position / CELL_SIZE).floor().as_ivec2()
+}
+
+/// Cells a client whose player stands in `center` can see.
+pub fn cells_in_view(center: IVec2) -> impl Iterator<Item = IVec2> {
+    let range = -VIEW_DISTANCE..=VIEW_DISTANCE;
+    range
+        .clone()
+        .flat_map(move |y| range.clone().map(move |x| center + IVec2::new(x, y)))
+}
+
+/// Interest cell a replicated entity belongs to.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell(pub IVec2);
+
+/// Marks replicated entities belonging to the room as a whole.
+#[derive(Component, Debug)]
+pub struct RoomWide;
+
+/// Cell a client's view is centred on.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct View(pub IVec2);
+
+/// Lightyear rooms backing the game room and each cell in use.
+#[derive(Resource, Debug)]
+pub struct InterestRooms {
+    /// Room of entities every client in the game room receives.
+    pub room: Entity,
+    cells: HashMap<IVec2, Entity>,
+}
+
+impl FromWorld for InterestRooms {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            room: world.spawn(Room::default()).id(),
+            cells: HashMap::new(),
+        }
+    }
+}
+
+impl InterestRooms {
+    /// Room of a cell, made the first time the cell is used.
+    fn cell(&mut self, cell: IVec2, commands: &mut Commands) -> Entity {
+        *self
+            .cells
+            .entry(cell)
+            .or_insert_with(|| commands.spawn(Room::default()).id())
+    }
+}
+
+/// Let clients receive room-wide entities once replication starts.
+pub fn enter_room(
+    trigger: On<Add, ReplicationSender>,
+    mut commands: Commands,
+    rooms: Res<InterestRooms>,
+) {
+    commands.trigger(RoomEvent {
+        room: rooms.room,
+        target: RoomTarget::AddSender(trigger.entity),
+    });
+}
+
+/// Put replicated entities without a transform in the game room.
+#[allow(clippy::type_complexity)]
+pub fn join_room(
+    mut commands: Commands,
+    rooms: Res<InterestRooms>,
+    entities: Query<Entity, (With<Replicate>, Without<Transform>, Without<RoomWide>)>,
+) {
+    for entity in entities.iter() {
+        commands.entity(entity).insert(RoomWide);
+        commands.trigger(RoomEvent {
+            room: rooms.room,
+            target: RoomTarget::AddEntity(entity),
+        });
+    }
+}
+
+/// Move replicated entities with a transform into the cell they stand in.
+#[allow(clippy::type_complexity)]
+pub fn track_cells(
+    mut commands: Commands,
+    mut rooms: ResMut<InterestRooms>,
+    entities: Query<
+        (Entity, &Transform, Option<&Cell>, Has<RoomWide>),
+        (With<Replicate>, Or<(Changed<Transform>, Without<Cell>)>),
+    >,
+) {
+    for (entity, transform, cell, room_wide) in entities.iter() {
+        let now = cell_at(transform.translation.truncate());
+        if cell.is_some_and(|cell| cell.0 == now) {
+            continue;
+        }
+        // Gained a transform since joining the room
+        if room_wide {
+            commands.entity(entity).remove::<RoomWide>();
+        }
+        if let Some(Cell(before)) = cell {
+            let room = rooms.cell(*before, &mut commands);
+            commands.trigger(RoomEvent {
+                room,
+                target: RoomTarget::RemoveEntity(entity),
+            });
+        }
+        let room = rooms.cell(now, &mut commands);
+        commands.trigger(RoomEvent {
+            room,
+            target: RoomTarget::AddEntity(entity),
+        });
+        commands.entity(entity).insert(Cell(now));
+    }
+}
+
+/// Take entities out of the game room when they stop being tracked, such as
+/// on despawn.
+pub fn leave_room(
+    trigger: On<Remove, RoomWide>,
+    mut commands: Commands,
+    rooms: Res<InterestRooms>,
+) {
+    commands.trigger(RoomEvent {
+        room: rooms.room,
+        target: RoomTarget::RemoveEntity(trigger.entity),
+    });
+}
+
+/// Take entities out of their cell when they stop being tracked, such as on
+/// despawn.
+pub fn leave_cell(
+    trigger: On<Remove, Cell>,
+    mut commands: Commands,
+    mut rooms: ResMut<InterestRooms>,
+    cells: Query<&Cell>,
+) {
+    let Ok(Cell(cell)) = cells.get(trigger.entity) else {
+        return;
+    };
+    let room = rooms.cell(*cell, &mut commands);
+    commands.trigger(RoomEvent {
+        room,
+        target: RoomTarget::RemoveEntity(trigger.entity),
+    });
+}
+
+/// Centre each client's view on the cell its player stands in.
+pub fn update_views(
+    mut commands: Commands,
+    mut rooms: ResMut<InterestRooms>,
+    players: Query<(&ControlledBy, &Cell), With<PlayerId>>,
+    clients: Query<Option<&View>, With<ReplicationSender>>,
+) {
+    for (controlled_by, Cell(center)) in players.iter() {
+        let client = controlled_by.owner;
+        let Ok(view) = clients.get(client) else {
+            continue;
+        };
+        if view.is_some_and(|view| view.0 == *center) {
+            continue;
+        }
+
+        let seen: Vec<_> = cells_in_view(*center).collect();
+        let before: Vec<_> = view
+            .map(|view| cells_in_view(view.0).collect())
+            .unwrap_or_default();
+        for cell in before.iter().filter(|cell| !seen.contains(cell)) {
+            let room = rooms.cell(*cell, &mut commands);
+            commands.trigger(RoomEvent {
+                room,
+                target: RoomTarget::RemoveSender(client),
+            });
+        }
+        for cell in seen.iter().filter(|cell| !before.contains(cell)) {
+            let room = rooms.cell(*cell, &mut commands);
+            commands.trigger(RoomEvent {
+                room,
+                target: RoomTarget::AddSender(client),
+            });
+        }
+        commands.entity(client).insert(View(*center));
+    }
+}
+
+/// Despawn the rooms of cells nobody stands in or looks at any more, so
+/// wandering the world doesn't leave a trail of them behind.
+pub fn drop_empty_cells(
+    mut commands: Commands,
+    mut rooms: ResMut<InterestRooms>,
+    cells: Query<&Room>,
+) {
+    rooms.cells.retain(|_, room| {
+        let empty = cells
+            .get(*room)
+            .is_ok_and(|cell| cell.clients.is_empty() && cell.entities.is_empty());
+        if empty {
+            commands.entity(*room).despawn();
+        }
+        !empty
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lightyear::prelude::NetworkTarget;
+
+    #[test]
+    fn clients_see_the_cells_around_their_player() {
+        assert_eq!(cell_at(Vec2::ZERO), IVec2::ZERO);
+        assert_eq!(cell_at(Vec2::new(CELL_SIZE, -1.0)), IVec2::new(1, -1));
+
+        let seen: Vec<_> = cells_in_view(IVec2::new(5, 5)).collect();
+        let side = 2 * VIEW_DISTANCE + 1;
+        assert_eq!(seen.len() as i32, side * side);
+        assert!(seen.contains(&IVec2::new(5 + VIEW_DISTANCE, 5 - VIEW_DISTANCE)));
+        assert!(!seen.contains(&IVec2::new(5 + VIEW_DISTANCE + 1, 5)));
+    }
+
+    #[test]
+    fn cells_left_empty_are_dropped() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.add_plugins(InterestPlugin);
+        let mover = app
+            .world_mut()
+            .spawn((
+                Replicate::to_clients(NetworkTarget::All),
+                Transform::default(),
+            ))
+            .id();
+        app.update();
+        let cells = |app: &App| -> Vec<IVec2> {
+            app.world()
+                .resource::<InterestRooms>()
+                .cells
+                .keys()
+                .copied()
+                .collect()
+        };
+        assert_eq!(cells(&app), [IVec2::ZERO]);
+        let first = app.world().resource::<InterestRooms>().cells[&IVec2::ZERO];
+
+        // Walking away leaves only the cell the entity is in
+        let far = Vec3::new(10.0 * CELL_SIZE, 0.0, 0.0);
+        app.world_mut()
+            .entity_mut(mover)
+            .insert(Transform::from_translation(far));
+        app.update();
+        assert_eq!(cells(&app), [IVec2::new(10, 0)]);
+        assert!(app.world().get_entity(first).is_err());
+
+        app.world_mut().despawn(mover);
+        app.update();
+        assert!(cells(&app).is_empty());
+    }
+}
@@ -0,0 +1,285 @@
+//! Server-authoritative inventories and world pickups.
+
+use bevy::prelude::*;
+use lightyear::prelude::{NetworkTarget, Replicate};
+use roguebench_core::ItemDef;
+use roguebench_protocol::{Health, Inventory, Pickup, TILE_SIZE};
+use uuid::Uuid;
+
+use crate::content::{ContentAppExt, ContentRegistry};
+use crate::progression::PlayerId;
+use crate::replication::replicate_to_owner_only;
+
+/// How close a player has to walk to a pickup to collect it.
+pub const PICKUP_RANGE: f32 = TILE_SIZE / 2.0;
+
+/// Event requesting that `picker` collects the pickup entity `pickup`.
+#[derive(Event, Debug, Clone)]
+pub struct PickupItem {
+    pub picker: Entity,
+    pub pickup: Entity,
+}
+
+/// Event fired after items were moved from a pickup into an inventory.
+#[derive(Event, Debug, Clone)]
+pub struct ItemPickedUp {
+    pub picker: Entity,
+    pub item: Uuid,
+    pub count: u32,
+}
+
+/// Plugin for item content, inventories and pickups.
+pub struct InventoryPlugin;
+
+impl Plugin for InventoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_content::<ItemDef>();
+
+        app.add_systems(FixedUpdate, collect_pickups);
+
+        app.add_observer(pickup_item);
+        app.add_observer(replicate_pickups);
+        app.add_observer(replicate_to_owner_only::<Inventory>);
+    }
+}
+
+/// Have living players pick up what they walk over.
+///
+/// A pickup within [`PICKUP_RANGE`] of several players goes to the nearest.
+#[allow(clippy::type_complexity)]
+pub fn collect_pickups(
+    mut commands: Commands,
+    players: Query<(Entity, &Transform, &Health), (With<PlayerId>, With<Inventory>)>,
+    pickups: Query<(Entity, &Transform), With<Pickup>>,
+) {
+    for (pickup, transform) in pickups.iter() {
+        let at = transform.translation.truncate();
+        let nearest = players
+            .iter()
+            .filter(|(.., health)| health.0 > 0)
+            .map(|(player, transform, _)| (player, transform.translation.truncate().distance(at)))
+            .filter(|(_, distance)| *distance <= PICKUP_RANGE)
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((picker, _)) = nearest {
+            commands.trigger(PickupItem { picker, pickup });
+        }
+    }
+}
+
+/// Move items from a pickup into the picker's inventory.
+///
+/// The pickup is despawned once empty; anything that doesn't fit stays on it.
+pub fn pickup_item(
+    trigger: On<PickupItem>,
+    mut commands: Commands,
+    items: Res<ContentRegistry<ItemDef>>,
+    mut inventories: Query<&mut Inventory>,
+    mut pickups: Query<&mut Pickup>,
+) {
+    let PickupItem { picker, pickup } = *trigger;
+
+    let (Ok(mut inventory), Ok(mut pickup_data)) =
+        (inventories.get_mut(picker), pickups.get_mut(pickup))
+    else {
+        return;
+    };
+    let Some(item) = items.get(pickup_data.item) else {
+        tracing::warn!("Pickup references unknown item {}", pickup_data.item);
+        return;
+    };
+
+    let leftover = inventory.add(item, pickup_data.count);
+    let taken = pickup_data.count - leftover;
+    if taken == 0 {
+        return;
+    }
+
+    if leftover == 0 {
+        commands.entity(pickup).despawn();
+    } else {
+        pickup_data.count = leftover;
+    }
+
+    commands.trigger(ItemPickedUp {
+        picker,
+        item: item.id,
+        count: taken,
+    });
+}
+
+/// Replicate pickups to every client so they can be rendered.
+pub fn replicate_pickups(trigger: On<Add, Pickup>, mut commands: Commands) {
+    commands
+        .entity(trigger.entity)
+        .insert(Replicate::to_clients(NetworkTarget::All));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::time::TimeUpdateStrategy;
+    use lightyear::prelude::{ComponentReplicationOverrides, ControlledBy, Lifetime};
+    use std::time::Duration;
+
+    fn test_app(items: &[ItemDef]) -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+
+        let mut registry = ContentRegistry::<ItemDef>::default();
+        for item in items {
+            registry.insert(item.clone());
+        }
+        app.insert_resource(registry);
+
+        app.add_observer(pickup_item);
+        app.add_observer(replicate_to_owner_only::<Inventory>);
+        app
+    }
+
+    #[test]
+    fn inventory_stacks_splits_and_removes() {
+        let potion = ItemDef::new("Potion", 5);
+        let mut inventory = Inventory::new(3);
+
+        // 7 potions fill one stack of 5 and start a second
+        assert_eq!(inventory.add(&potion, 7), 0);
+        assert_eq!(inventory.slots[0].unwrap().count, 5);
+        assert_eq!(inventory.slots[1].unwrap().count, 2);
+
+        // Topping up fills the partial stack first, overflow is returned
+        assert_eq!(inventory.add(&potion, 12), 4);
+        assert_eq!(inventory.count(potion.id), 15);
+
+        // Split needs an empty slot
+        assert_eq!(inventory.split(0, 2), None);
+        assert_eq!(inventory.remove(potion.id, 6), 6);
+        assert_eq!(inventory.count(potion.id), 9);
+        assert_eq!(inventory.slots[2], None);
+
+        let target = inventory.split(0, 2).unwrap();
+        assert_eq!(target, 2);
+        assert_eq!(inventory.slots[0].unwrap().count, 3);
+        assert_eq!(inventory.slots[2].unwrap().count, 2);
+
+        // Stacking merges back up to the max stack size
+        assert_eq!(inventory.stack(&potion, 2, 0), 2);
+        assert_eq!(inventory.slots[0].unwrap().count, 5);
+        assert_eq!(inventory.slots[2], None);
+
+        // Removing more than held removes what there is
+        assert_eq!(inventory.remove(potion.id, 100), 9);
+        assert!(inventory.slots.iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn pickup_moves_items_into_inventory() {
+        let sword = ItemDef::new("Sword", 1);
+        let mut app = test_app(std::slice::from_ref(&sword));
+
+        let picker = app.world_mut().spawn(Inventory::new(1)).id();
+        let pickup = app
+            .world_mut()
+            .spawn(Pickup {
+                item: sword.id,
+                count: 2,
+            })
+            .id();
+
+        // Only one sword fits; the other stays on the ground
+        app.world_mut()
+            .commands()
+            .trigger(PickupItem { picker, pickup });
+        app.update();
+
+        let inventory = app.world().get::<Inventory>(picker).unwrap();
+        assert_eq!(inventory.count(sword.id), 1);
+        assert_eq!(app.world().get::<Pickup>(pickup).unwrap().count, 1);
+
+        // Make room and pick up the rest, which despawns the pickup
+        app.world_mut()
+            .get_mut::<Inventory>(picker)
+            .unwrap()
+            .slots
+            .push(None);
+        app.world_mut()
+            .commands()
+            .trigger(PickupItem { picker, pickup });
+        app.update();
+
+        let inventory = app.world().get::<Inventory>(picker).unwrap();
+        assert_eq!(inventory.count(sword.id), 2);
+        assert!(app.world().get_entity(pickup).is_err());
+    }
+
+    #[test]
+    fn players_collect_pickups_they_walk_over() {
+        let coin = ItemDef::new("Coin", 99);
+        let mut app = test_app(std::slice::from_ref(&coin));
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            100,
+        )));
+        app.add_systems(FixedUpdate, collect_pickups);
+
+        let mut spawn_player = |x: f32, health: i32| {
+            app.world_mut()
+                .spawn((
+                    PlayerId(Uuid::new_v4()),
+                    Inventory::new(4),
+                    Health(health),
+                    Transform::from_xyz(x, 0.0, 0.0),
+                ))
+                .id()
+        };
+        let near = spawn_player(0.0, 10);
+        let dead = spawn_player(100.0, 0);
+        let mut spawn_pickup = |x: f32| {
+            app.world_mut()
+                .spawn((
+                    Pickup {
+                        item: coin.id,
+                        count: 3,
+                    },
+                    Transform::from_xyz(x, 0.0, 0.0),
+                ))
+                .id()
+        };
+        let underfoot = spawn_pickup(PICKUP_RANGE - 1.0);
+        let out_of_reach = spawn_pickup(-PICKUP_RANGE - 1.0);
+        let by_the_dead = spawn_pickup(100.0);
+        app.update();
+        app.update();
+
+        let count = |player| app.world().get::<Inventory>(player).unwrap().count(coin.id);
+        assert_eq!(count(near), 3);
+        assert_eq!(count(dead), 0);
+        assert!(app.world().get_entity(underfoot).is_err());
+        assert!(app.world().get_entity(out_of_reach).is_ok());
+        assert!(app.world().get_entity(by_the_dead).is_ok());
+    }
+
+    #[test]
+    fn inventory_replicates_only_to_owner() {
+        let mut app = test_app(&[]);
+
+        let owner = app.world_mut().spawn_empty().id();
+        let other = app.world_mut().spawn_empty().id();
+        let player = app
+            .world_mut()
+            .spawn((
+                Inventory::new(4),
+                ControlledBy {
+                    owner,
+                    lifetime: Lifetime::default(),
+                },
+            ))
+            .id();
+        app.update();
+
+        let overrides = app
+            .world()
+            .get::<ComponentReplicationOverrides<Inventory>>(player)
+            .unwrap();
+        assert!(overrides.get_overrides(owner).unwrap().enable);
+        assert!(overrides.get_overrides(other).unwrap().disable);
+    }
+}
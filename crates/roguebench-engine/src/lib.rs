@@ -3,15 +3,98 @@
 //! Provides the core game systems for entity spawning, reloading, and
 //! integration with the content storage layer.
 
+mod ai;
+mod combat;
+mod content;
+mod definitions;
+mod dialogue;
+mod encounter;
+mod events;
+mod faction;
+mod feedback;
+mod fsm;
+mod interest;
+mod inventory;
+mod player;
+mod progression;
+mod projectile;
+mod quest;
+mod replication;
 mod resources;
+mod rng;
+mod room;
+mod save;
+mod shop;
+mod spatial;
+mod status;
 mod systems;
-
-pub use resources::{EditorReceiver, EngineConfig, Storage};
+mod weapon;
+mod worldgen;
+
+pub use ai::{AiBrain, AiPlugin, act_on_ai_state, ai_state_machine, sense_targets};
+pub use combat::{ApplyDamage, CombatPlugin, DamageDealt, Hitbox, MaxHealth, apply_damage};
+pub use content::{ContentAppExt, ContentRegistry, ReloadContent};
+pub use definitions::{
+    DefinitionsPlugin, DefinitionsPublished, SnapshotSent, broadcast_definitions,
+    resend_definitions, send_definitions,
+};
+pub use dialogue::{
+    ChooseDialogueOption, Conversation, DialogueEnded, DialogueNodeEntered, DialoguePlugin,
+    DialogueRef, StartDialogue, StoryFlags,
+};
+pub use encounter::{
+    Encounter, EncounterCleared, EncounterMember, EncounterPlugin, StartEncounter, WaveCleared,
+    WaveStarted,
+};
+pub use events::{EntityKilled, RoomEntered};
+pub use faction::{FactionPlugin, Team};
+pub use feedback::{
+    FEEDBACK_RADIUS, FeedbackPlugin, audience, send_damage_feedback, send_pickup_feedback,
+    send_wave_feedback,
+};
+pub use fsm::{
+    Fsm, FsmPlugin, FsmRules, FsmState, StateEntered, StateExited, drive_state_machines,
+    enter_state,
+};
+pub use interest::{
+    CELL_SIZE, Cell, InterestPlugin, InterestRooms, RoomWide, VIEW_DISTANCE, View, cell_at,
+    cells_in_view,
+};
+pub use inventory::{InventoryPlugin, ItemPickedUp, PICKUP_RANGE, PickupItem, collect_pickups};
+pub use player::{
+    PlayerIdentities, PlayerPlugin, PlayerSettings, Players, move_players, player_attacks,
+    release_player, spawn_player,
+};
+pub use progression::{
+    ContentUnlocked, EndRun, PlayerId, Profile, ProgressionPlugin, RunStats, end_run,
+};
+pub use projectile::{
+    ProjectileFlight, ProjectileHit, ProjectilePlugin, move_projectiles, spawn_projectile,
+};
+pub use quest::{QuestCompleted, QuestObjectiveCompleted, QuestPlugin, QuestStarted, StartQuest};
+pub use replication::replicate_to_owner_only;
+pub use resources::{EditorReceiver, EngineConfig, EntityTemplates, Storage};
+pub use rng::{RngPlugin, RngStream, RunRng, StartRun};
+pub use room::{ActiveRoom, CollisionMap, LoadRoom, RoomLoaded, RoomPlugin, activate_room};
+pub use save::{SaveGame, SavePlugin, SaveSlot};
+pub use shop::{
+    BuyFromShop, ItemBought, ItemSold, RestockTimer, SellToShop, ShopPlugin, ShopRef, TradeFailed,
+    roll_stock,
+};
+pub use spatial::{SpatialPlugin, sync_spatial};
+pub use status::{
+    ActiveEffect, ActiveEffects, ApplyStatus, StatMultipliers, StatusApplied, StatusExpired,
+    StatusPlugin, apply_status, tick_status_effects,
+};
+pub use systems::{EntityTemplate, TemplateChanged, spawn_from_template};
+pub use weapon::{FireWeapon, Weapon, WeaponFired, WeaponPlugin};
+pub use worldgen::{GenerateRoom, WorldgenPlugin};
 
 use std::net::SocketAddr;
 use std::sync::Arc;
 
 use bevy::prelude::*;
+use lightyear::prelude::{ConnectionSystems, LinkSystems};
 use roguebench_protocol::EditorMessage;
 use roguebench_storage::ContentStore;
 use tokio::sync::mpsc;
@@ -26,6 +109,9 @@ pub struct EnginePlugin {
 
 impl EnginePlugin {
     /// Create a new engine plugin with the given configuration.
+    ///
+    /// The server gets a fresh private key; set one in the config to keep
+    /// honouring connect tokens across restarts.
     pub fn new(
         storage: Arc<dyn ContentStore>,
         editor_receiver: mpsc::UnboundedReceiver<EditorMessage>,
@@ -37,6 +123,10 @@ impl EnginePlugin {
                 storage,
                 editor_receiver: Mutex::new(Some(editor_receiver)),
                 server_addr,
+                private_key: lightyear::netcode::generate_key(),
+                seed: None,
+                player_template: None,
+                player_lifetime: Default::default(),
             },
         }
     }
@@ -47,6 +137,7 @@ impl Plugin for EnginePlugin {
         // Insert resources from config
         app.insert_resource(Storage(self.config.storage.clone()));
         app.insert_resource(resources::ServerAddr(self.config.server_addr));
+        app.insert_resource(resources::ServerKey(self.config.private_key));
 
         // Take ownership of the receiver (uses interior mutability)
         let receiver = self
@@ -57,37 +148,83 @@ impl Plugin for EnginePlugin {
             .take()
             .expect("EnginePlugin can only be added once");
         app.insert_resource(EditorReceiver(receiver));
+        app.init_resource::<EntityTemplates>();
+        app.insert_resource(PlayerSettings {
+            template: self.config.player_template,
+            lifetime: self.config.player_lifetime,
+        });
 
         // Register messages (events)
         app.add_message::<systems::ReloadEntities>();
+        app.add_message::<ReloadContent>();
+
+        // Add gameplay plugins
+        app.add_plugins(RngPlugin {
+            seed: self.config.seed,
+        });
+        app.add_plugins(DefinitionsPlugin);
+        app.add_plugins(InventoryPlugin);
+        app.add_plugins(ShopPlugin);
+        app.add_plugins(DialoguePlugin);
+        app.add_plugins(QuestPlugin);
+        app.add_plugins(RoomPlugin);
+        app.add_plugins(WorldgenPlugin);
+        app.add_plugins(EncounterPlugin);
+        app.add_plugins(FactionPlugin);
+        app.add_plugins(CombatPlugin);
+        app.add_plugins(StatusPlugin);
+        app.add_plugins(WeaponPlugin);
+        app.add_plugins(ProjectilePlugin);
+        app.add_plugins(AiPlugin);
+        app.add_plugins(PlayerPlugin);
+        app.add_plugins(SpatialPlugin);
+        app.add_plugins(SavePlugin);
+        app.add_plugins(ProgressionPlugin);
+        app.add_plugins(FeedbackPlugin);
+        app.add_plugins(InterestPlugin);
 
         // Add systems
         app.add_systems(Startup, systems::spawn_server);
         app.add_systems(Startup, systems::initial_load);
         app.add_systems(Update, systems::check_editor_messages);
+        app.add_systems(
+            PreUpdate,
+            systems::turn_away_mismatched_clients
+                .after(LinkSystems::Receive)
+                .before(ConnectionSystems::Receive),
+        );
+        app.add_systems(
+            PostUpdate,
+            systems::send_version_mismatches
+                .after(ConnectionSystems::Send)
+                .before(LinkSystems::Send),
+        );
 
         // Add observers
         app.add_observer(systems::reload_entities);
+        app.add_observer(systems::patch_entities);
+        app.add_observer(systems::start_replication);
         app.add_observer(systems::log_connections);
     }
 }
 
 pub mod prelude {
-    pub use crate::{EngineConfig, EnginePlugin};
+    pub use crate::{ContentAppExt, ContentRegistry, EngineConfig, EnginePlugin};
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use roguebench_core::EntityDef;
-    use roguebench_protocol::{EntityName, Health};
-    use roguebench_storage::MemoryStore;
+    use lightyear::prelude::{Link, LinkOf};
+    use roguebench_core::{AiProfile, ContentDef, EntityDef, ItemDef};
+    use roguebench_protocol::{
+        ContentChange, Definitions, ENTITY_KIND, EntityName, Health, ProtocolHash, TemplateId,
+    };
+    use roguebench_storage::{ContentStoreExt, MemoryStore};
     use systems::{ReloadEntities, SpawnedEntity};
 
     /// Create a minimal test app with storage and editor receiver.
-    fn test_app(
-        storage: Arc<dyn ContentStore>,
-    ) -> (App, mpsc::UnboundedSender<EditorMessage>) {
+    fn test_app(storage: Arc<dyn ContentStore>) -> (App, mpsc::UnboundedSender<EditorMessage>) {
         let mut app = App::new();
         app.add_plugins(MinimalPlugins);
 
@@ -97,11 +234,17 @@ mod tests {
         // Add editor channel
         let (tx, rx) = mpsc::unbounded_channel();
         app.insert_resource(EditorReceiver(rx));
+        app.init_resource::<EntityTemplates>();
+        app.init_resource::<PlayerIdentities>();
+        app.init_resource::<PlayerSettings>();
+        app.init_resource::<Definitions>();
 
         // Register the message type and add systems/observers
         app.add_message::<ReloadEntities>();
+        app.add_message::<ReloadContent>();
         app.add_systems(Update, systems::check_editor_messages);
         app.add_observer(systems::reload_entities);
+        app.add_observer(systems::patch_entities);
 
         (app, tx)
     }
@@ -176,6 +319,68 @@ mod tests {
         assert!(names.contains(&"Orc".to_string()));
     }
 
+    #[test]
+    fn reloaded_entities_carry_their_template_id() {
+        let storage = Arc::new(MemoryStore::new());
+        let mut archer = EntityDef::new("Goblin", 30);
+        archer.slug = Some("goblin-archer".to_string());
+        let brute = EntityDef::new("Goblin", 60);
+        storage.save_entity(&archer).unwrap();
+        storage.save_entity(&brute).unwrap();
+        let (mut app, _tx) = test_app(storage);
+
+        app.world_mut().commands().trigger(ReloadEntities);
+        app.update();
+
+        // Same name, told apart by their template
+        let mut query = app.world_mut().query::<(&TemplateId, &Health)>();
+        let mut spawned: Vec<_> = query
+            .iter(app.world())
+            .map(|(template, health)| (template.clone(), health.0))
+            .collect();
+        spawned.sort_by_key(|(_, health)| *health);
+        assert_eq!(
+            spawned,
+            vec![
+                (TemplateId::of(&archer), 30),
+                (
+                    TemplateId {
+                        id: brute.id,
+                        slug: None
+                    },
+                    60
+                ),
+            ]
+        );
+        assert_eq!(spawned[0].0.slug.as_deref(), Some("goblin-archer"));
+    }
+
+    #[test]
+    fn reload_entities_publishes_templates() {
+        let storage = Arc::new(MemoryStore::new());
+        let goblin = EntityDef::new("Goblin", 30);
+        storage.save_entity(&goblin).unwrap();
+        let (mut app, _tx) = test_app(storage.clone());
+
+        app.world_mut().commands().trigger(ReloadEntities);
+        app.update();
+        let definitions = app.world().resource::<Definitions>();
+        assert_eq!(definitions.revision(), 1);
+        assert_eq!(definitions.entity(goblin.id).unwrap().name, "Goblin");
+
+        // Reloading unchanged templates publishes nothing new
+        app.world_mut().commands().trigger(ReloadEntities);
+        app.update();
+        assert_eq!(app.world().resource::<Definitions>().revision(), 1);
+
+        storage.delete_entity(goblin.id).unwrap();
+        app.world_mut().commands().trigger(ReloadEntities);
+        app.update();
+        let definitions = app.world().resource::<Definitions>();
+        assert_eq!(definitions.revision(), 2);
+        assert!(definitions.entity(goblin.id).is_none());
+    }
+
     #[test]
     fn check_editor_messages_triggers_reload() {
         let storage = Arc::new(MemoryStore::new());
@@ -186,10 +391,15 @@ mod tests {
 
         let (mut app, tx) = test_app(storage);
 
-        // Send reload message through channel
-        tx.send(EditorMessage::ReloadEntities).unwrap();
+        // Send change message through channel
+        tx.send(EditorMessage::ContentChanged {
+            kind: ENTITY_KIND,
+            id: goblin.id,
+            change: ContentChange::Created,
+        })
+        .unwrap();
 
-        // Run update - should process message and trigger reload
+        // Run update - should process message and spawn the entity
         app.update();
 
         // Verify entity was spawned (proves the message was processed)
@@ -197,4 +407,205 @@ mod tests {
         let count = query.iter(app.world()).count();
         assert_eq!(count, 1);
     }
+
+    #[test]
+    fn check_editor_messages_reloads_content_kind() {
+        let storage = Arc::new(MemoryStore::new());
+        let (mut app, tx) = test_app(storage.clone());
+        app.register_content::<ItemDef>();
+
+        // Startup load of an empty store
+        app.update();
+        assert!(
+            app.world()
+                .resource::<ContentRegistry<ItemDef>>()
+                .is_empty()
+        );
+
+        let potion = ItemDef::new("Potion", 10);
+        storage.save(&potion).unwrap();
+
+        // Other kinds don't touch the item registry
+        let changed = |kind| EditorMessage::ContentChanged {
+            kind,
+            id: potion.id,
+            change: ContentChange::Created,
+        };
+        tx.send(changed("other")).unwrap();
+        app.update();
+        assert!(
+            app.world()
+                .resource::<ContentRegistry<ItemDef>>()
+                .is_empty()
+        );
+
+        tx.send(changed(ItemDef::KIND)).unwrap();
+        app.update();
+
+        let registry = app.world().resource::<ContentRegistry<ItemDef>>();
+        assert_eq!(registry.len(), 1);
+        assert_eq!(registry.get(potion.id).unwrap().name, "Potion");
+    }
+
+    #[test]
+    fn entity_changes_patch_only_the_entities_affected() {
+        let storage = Arc::new(MemoryStore::new());
+        let mut goblin = EntityDef::new("Goblin", 30);
+        goblin.ai = Some(AiProfile::grunt());
+        let orc = EntityDef::new("Orc", 80);
+        storage.save_entity(&goblin).unwrap();
+        storage.save_entity(&orc).unwrap();
+        let (mut app, tx) = test_app(storage.clone());
+        app.world_mut().commands().trigger(ReloadEntities);
+        app.update();
+
+        let mut spawned = app
+            .world_mut()
+            .query_filtered::<(Entity, &EntityName, &Health), With<SpawnedEntity>>();
+        let mut find = |app: &mut App, name: &str| {
+            spawned
+                .iter(app.world())
+                .find(|(_, n, _)| n.0 == name)
+                .map(|(entity, _, health)| (entity, health.0))
+        };
+        let (goblin_entity, _) = find(&mut app, "Goblin").unwrap();
+        let (orc_entity, _) = find(&mut app, "Orc").unwrap();
+        app.world_mut().get_mut::<Health>(goblin_entity).unwrap().0 = 10;
+
+        // Edit the goblin: same entity, new name, damage kept
+        goblin.name = "Hobgoblin".to_string();
+        goblin.health = 50;
+        storage.save_entity(&goblin).unwrap();
+        let changed = |id, change| EditorMessage::ContentChanged {
+            kind: ENTITY_KIND,
+            id,
+            change,
+        };
+        tx.send(changed(goblin.id, ContentChange::Updated)).unwrap();
+        app.update();
+        assert_eq!(find(&mut app, "Hobgoblin"), Some((goblin_entity, 10)));
+        assert_eq!(
+            app.world().get::<MaxHealth>(goblin_entity).map(|max| max.0),
+            Some(50)
+        );
+        let brain = app.world().get::<ai::AiBrain>(goblin_entity).unwrap();
+        assert_eq!(brain.max_health, 50);
+        let definitions = app.world().resource::<Definitions>();
+        assert_eq!(definitions.entity(goblin.id).unwrap().name, "Hobgoblin");
+
+        // Delete the orc and add a troll; the goblin is left alone
+        let troll = EntityDef::new("Troll", 120);
+        storage.delete_entity(orc.id).unwrap();
+        storage.save_entity(&troll).unwrap();
+        tx.send(changed(orc.id, ContentChange::Deleted)).unwrap();
+        tx.send(changed(troll.id, ContentChange::Created)).unwrap();
+        app.update();
+        assert!(app.world().get_entity(orc_entity).is_err());
+        assert!(find(&mut app, "Troll").is_some_and(|(_, health)| health == 120));
+        assert_eq!(find(&mut app, "Hobgoblin"), Some((goblin_entity, 10)));
+        assert_eq!(spawned.iter(app.world()).count(), 2);
+    }
+
+    #[test]
+    fn the_player_template_only_shapes_players() {
+        let storage = Arc::new(MemoryStore::new());
+        let mut knight = EntityDef::new("Knight", 100);
+        let goblin = EntityDef::new("Goblin", 30);
+        storage.save_entity(&knight).unwrap();
+        storage.save_entity(&goblin).unwrap();
+        let (mut app, tx) = test_app(storage.clone());
+        app.insert_resource(PlayerSettings {
+            template: Some(knight.id),
+            ..default()
+        });
+        app.world_mut().commands().trigger(ReloadEntities);
+        app.update();
+
+        let mut world_names = app
+            .world_mut()
+            .query_filtered::<&EntityName, With<SpawnedEntity>>();
+        let mut names = |app: &App| -> Vec<String> {
+            world_names
+                .iter(app.world())
+                .map(|name| name.0.clone())
+                .collect()
+        };
+        assert_eq!(names(&app), ["Goblin"]);
+
+        // Players built from the template follow edits to it
+        let mut commands = app.world_mut().commands();
+        let player = spawn_from_template(&mut commands, &knight)
+            .insert(PlayerId(uuid::Uuid::new_v4()))
+            .id();
+        app.world_mut().flush();
+        knight.name = "Paladin".to_string();
+        knight.health = 150;
+        storage.save_entity(&knight).unwrap();
+        let changed = |change| EditorMessage::ContentChanged {
+            kind: ENTITY_KIND,
+            id: knight.id,
+            change,
+        };
+        tx.send(changed(ContentChange::Updated)).unwrap();
+        app.update();
+        assert_eq!(app.world().get::<EntityName>(player).unwrap().0, "Paladin");
+        assert_eq!(app.world().get::<Health>(player).unwrap().0, 150);
+
+        // Deleting the template doesn't kick the player out
+        storage.delete_entity(knight.id).unwrap();
+        tx.send(changed(ContentChange::Deleted)).unwrap();
+        app.update();
+        assert!(app.world().get_entity(player).is_ok());
+
+        // Nor does creating it put a stray player into the world
+        app.world_mut().despawn(player);
+        storage.save_entity(&knight).unwrap();
+        tx.send(changed(ContentChange::Created)).unwrap();
+        app.update();
+        assert_eq!(names(&app), ["Goblin"]);
+    }
+
+    #[test]
+    fn only_full_requests_are_turned_away_and_only_so_many() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.insert_resource(ProtocolHash(1));
+        app.add_systems(
+            Update,
+            (
+                systems::turn_away_mismatched_clients,
+                systems::send_version_mismatches,
+            )
+                .chain(),
+        );
+        let server = app.world_mut().spawn_empty().id();
+        let request = |protocol: u64, len: usize| {
+            let mut packet = b"\0NETCODE 1.02\0".to_vec();
+            packet.extend_from_slice(&protocol.to_be_bytes());
+            packet.resize(len, 0);
+            packet
+        };
+        let mut connect = |packet: Vec<u8>| {
+            let mut link = Link::new(None);
+            link.recv.push_raw(packet.into());
+            app.world_mut().spawn((link, LinkOf { server })).id()
+        };
+        let truncated = connect(request(2, 64));
+        let matching = connect(request(1, 1078));
+        let mismatched: Vec<_> = (0..12).map(|_| connect(request(2, 1078))).collect();
+        app.update();
+
+        let link = |app: &App, entity| {
+            let link = app.world().get::<Link>(entity).unwrap();
+            (link.recv.len(), link.send.len())
+        };
+        // Anything but a mismatched full request is left for netcode
+        assert_eq!(link(&app, truncated), (1, 0));
+        assert_eq!(link(&app, matching), (1, 0));
+        let replies = mismatched
+            .iter()
+            .filter(|entity| link(&app, **entity) == (0, 1))
+            .count();
+        assert_eq!(replies, 8);
+    }
 }
@@ -0,0 +1,536 @@
+//! Players: entities driven by the input of the client controlling them.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use lightyear::prelude::input::native::ActionState;
+use lightyear::prelude::server::ClientOf;
+use lightyear::prelude::{
+    ControlledBy, Disconnected, InterpolationTarget, Lifetime, NetworkTarget, PeerId,
+    PredictionTarget, RemoteId, ReplicationSender,
+};
+use roguebench_core::{EntityDef, RoomDef, Stat};
+use roguebench_protocol::{
+    Health, Inventory, PLAYER_SPEED, PlayerInput, RoomLayout, TOKEN_EXPIRY_SECS, Wallet,
+};
+use uuid::Uuid;
+
+use crate::combat::Hitbox;
+use crate::dialogue::StoryFlags;
+use crate::progression::PlayerId;
+use crate::resources::EntityTemplates;
+use crate::room::{ActiveRoom, CollisionMap, step};
+use crate::save::SaveSlot;
+use crate::status::StatMultipliers;
+use crate::systems::spawn_from_template;
+use crate::weapon::FireWeapon;
+
+/// Health of players when no player template is configured.
+const PLAYER_HEALTH: i32 = 100;
+
+/// Number of inventory slots every player starts with.
+const PLAYER_INVENTORY_SLOTS: usize = 12;
+
+/// Tag of the room spawn points players enter at.
+const PLAYER_SPAWN_TAG: &str = "player";
+
+/// How players are spawned for connecting clients.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct PlayerSettings {
+    /// ID of the [`EntityDef`] players are built from; a bare default player
+    /// when `None`.
+    pub template: Option<Uuid>,
+    /// Whether a player is despawned when their client disconnects, or kept
+    /// for them to take back on reconnect.
+    pub lifetime: Lifetime,
+}
+
+/// Who is playing behind each client ID, as vouched for by connect tokens.
+///
+/// An entry lasts only as long as its token: it is used up by the client
+/// connecting, and dropped once the token has expired unused.
+#[derive(Resource, Debug, Default)]
+pub struct PlayerIdentities(HashMap<PeerId, (Uuid, Duration)>);
+
+impl PlayerIdentities {
+    /// Vouch for `player` behind `peer` for as long as a token issued `now` lasts,
+    /// forgetting the players of tokens that have expired by then.
+    pub fn authorize(&mut self, peer: PeerId, player: Uuid, now: Duration) {
+        self.0.retain(|_, (_, expires)| *expires > now);
+        let expiry = Duration::from_secs(TOKEN_EXPIRY_SECS.unsigned_abs().into());
+        self.0.insert(peer, (player, now + expiry));
+    }
+
+    /// Use up the player vouched for behind `peer`, if any.
+    pub fn take(&mut self, peer: &PeerId) -> Option<Uuid> {
+        self.0.remove(peer).map(|(player, _)| player)
+    }
+
+    /// Whether no player is waiting to connect.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// The player entity of every client, by client ID.
+#[derive(Resource, Debug, Default)]
+pub struct Players(pub HashMap<PeerId, Entity>);
+
+/// Plugin spawning players for clients and applying their input on the server.
+pub struct PlayerPlugin;
+
+impl Plugin for PlayerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PlayerSettings>();
+        app.init_resource::<Players>();
+        app.init_resource::<PlayerIdentities>();
+
+        app.add_observer(spawn_player);
+        app.add_observer(release_player);
+        app.add_systems(FixedUpdate, (move_players, player_attacks));
+    }
+}
+
+/// Give a client their player once the world starts replicating to them.
+///
+/// A client reconnecting to a player that was kept takes control of it again;
+/// otherwise a new player is built from the configured template. The client
+/// predicts its own player and interpolates everyone else's.
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_player(
+    trigger: On<Add, ReplicationSender>,
+    mut commands: Commands,
+    settings: Res<PlayerSettings>,
+    templates: Res<EntityTemplates>,
+    mut identities: ResMut<PlayerIdentities>,
+    room: Option<Res<ActiveRoom>>,
+    mut players: ResMut<Players>,
+    clients: Query<&RemoteId, With<ClientOf>>,
+    existing: Query<(), With<PlayerId>>,
+) {
+    let link = trigger.entity;
+    let Ok(RemoteId(peer)) = clients.get(link) else {
+        return;
+    };
+    let vouched = identities.take(peer);
+    let controlled = ControlledBy {
+        owner: link,
+        lifetime: settings.lifetime,
+    };
+
+    let kept = players.0.get(peer).copied();
+    if let Some(player) = kept.filter(|&player| existing.contains(player)) {
+        tracing::info!("Client {} took back player {:?}", peer, player);
+        commands.entity(player).insert(controlled);
+        return;
+    }
+
+    let fallback;
+    let entity_def = match settings.template.and_then(|id| templates.0.get(&id)) {
+        Some(entity_def) => entity_def,
+        None => {
+            if let Some(id) = settings.template {
+                tracing::warn!("Player template {} not found, using a default player", id);
+            }
+            fallback = EntityDef::new("Player", PLAYER_HEALTH);
+            &fallback
+        }
+    };
+    let position = room.map_or(Vec2::ZERO, |room| spawn_position(&room.0));
+    // Clients that connected without a token are only known by their client ID
+    let id = vouched.unwrap_or_else(|| Uuid::from_u64_pair(0, peer.to_bits()));
+
+    let player = spawn_from_template(&mut commands, entity_def)
+        .insert((
+            Transform::from_translation(position.extend(0.0)),
+            controlled,
+            PredictionTarget::to_clients(NetworkTarget::Single(*peer)),
+            InterpolationTarget::to_clients(NetworkTarget::AllExceptSingle(*peer)),
+            Wallet::default(),
+            Inventory::new(PLAYER_INVENTORY_SLOTS),
+            StoryFlags::default(),
+            PlayerId(id),
+            SaveSlot(id),
+        ))
+        .id();
+    tracing::info!("Spawned player {:?} for client {}", player, peer);
+    players.0.insert(*peer, player);
+}
+
+/// Forget the player of a disconnecting client, unless it is kept for them.
+///
+/// Lightyear despawns players that live only as long as their session.
+pub fn release_player(
+    trigger: On<Add, Disconnected>,
+    settings: Res<PlayerSettings>,
+    mut players: ResMut<Players>,
+    clients: Query<&RemoteId, With<ClientOf>>,
+) {
+    let Ok(RemoteId(peer)) = clients.get(trigger.entity) else {
+        return;
+    };
+    if settings.lifetime == Lifetime::SessionBased {
+        players.0.remove(peer);
+    }
+}
+
+/// Where players enter a room: its first player spawn point, or its centre.
+fn spawn_position(room: &RoomDef) -> Vec2 {
+    match room
+        .spawn_points
+        .iter()
+        .find(|point| point.tag == PLAYER_SPAWN_TAG)
+    {
+        Some(point) => RoomLayout::tile_center(point.x, point.y),
+        None => RoomLayout::tile_center(room.width / 2, room.height / 2),
+    }
+}
+
+/// Walk every living player in the direction their client is pushing.
+///
+/// Players slide along tiles that block the way, like AI-driven entities.
+/// Clients predict their own movement with [`PlayerInput::walk`] too; status
+/// effects changing the speed and walls are only known here, and clients catch
+/// up by rolling back when the replicated position disagrees.
+#[allow(clippy::type_complexity)]
+pub fn move_players(
+    time: Res<Time>,
+    collision: Option<Res<CollisionMap>>,
+    mut players: Query<
+        (
+            &ActionState<PlayerInput>,
+            &mut Transform,
+            &Health,
+            Option<&Hitbox>,
+            Option<&StatMultipliers>,
+        ),
+        With<ControlledBy>,
+    >,
+) {
+    let dt = time.delta_secs();
+    for (input, mut transform, health, hitbox, multipliers) in players.iter_mut() {
+        if health.0 <= 0 {
+            continue;
+        }
+        let speed = PLAYER_SPEED * multipliers.map_or(1.0, |m| m.get(Stat::MoveSpeed));
+        let from = transform.translation.truncate();
+        let towards = input.walk(from, speed, dt);
+        let half_extents = Vec2::splat(hitbox.copied().unwrap_or_default().radius);
+        let at = step(
+            from,
+            towards,
+            from.distance(towards),
+            collision.as_deref(),
+            half_extents,
+        );
+        transform.translation = at.extend(transform.translation.z);
+    }
+}
+
+/// Fire the weapon of every living player holding the attack button, where they aim.
+///
+/// Unarmed players and weapons still cooling down ignore the request.
+pub fn player_attacks(
+    mut commands: Commands,
+    players: Query<(Entity, &ActionState<PlayerInput>, &Health), With<ControlledBy>>,
+) {
+    for (shooter, input, health) in players.iter() {
+        if input.attack && health.0 > 0 {
+            commands.trigger(FireWeapon {
+                shooter,
+                direction: input.aim,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::time::TimeUpdateStrategy;
+    use lightyear::connection::client::PeerMetadata;
+    use lightyear::prelude::SendUpdatesMode;
+    use roguebench_core::{AiProfile, FactionDef, SpawnPoint, TileKind};
+    use roguebench_protocol::{EntityName, TILE_SIZE};
+
+    use crate::ai::AiPlugin;
+    use crate::combat::apply_damage;
+    use crate::content::ContentRegistry;
+    use crate::rng::RunRng;
+
+    fn player_app(settings: PlayerSettings) -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        // Lightyear bookkeeping touched when a client disconnects
+        app.init_resource::<PeerMetadata>();
+        app.insert_resource(settings);
+        app.init_resource::<Players>();
+        app.init_resource::<PlayerIdentities>();
+        app.init_resource::<EntityTemplates>();
+        app.add_observer(spawn_player);
+        app.add_observer(release_player);
+        app
+    }
+
+    /// Connect a client and start replicating to it, returning its link.
+    fn connect(app: &mut App, peer: PeerId) -> Entity {
+        let sender = ReplicationSender::new(Duration::ZERO, SendUpdatesMode::SinceLastAck, false);
+        app.world_mut()
+            .spawn((ClientOf, RemoteId(peer), sender))
+            .id()
+    }
+
+    fn player_of(app: &App, peer: PeerId) -> Option<Entity> {
+        app.world().resource::<Players>().0.get(&peer).copied()
+    }
+
+    #[test]
+    fn players_walk_where_their_input_points() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        // One fixed tick per update
+        let tick = Duration::from_micros(15_625);
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(tick));
+        app.add_systems(FixedUpdate, move_players);
+        app.update();
+
+        let owner = app.world_mut().spawn_empty().id();
+        let controlled = ControlledBy {
+            owner,
+            lifetime: Lifetime::default(),
+        };
+        let input = ActionState(PlayerInput {
+            movement: Vec2::new(3.0, 4.0),
+            ..default()
+        });
+        let player = app
+            .world_mut()
+            .spawn((controlled, input.clone(), Transform::default(), Health(100)))
+            .id();
+        // Nobody controls it, so input is ignored
+        let bystander = app
+            .world_mut()
+            .spawn((input, Transform::default(), Health(100)))
+            .id();
+        app.update();
+
+        let at = |entity| app.world().get::<Transform>(entity).unwrap().translation;
+        // Diagonal input is capped at full speed
+        let step = PLAYER_SPEED * tick.as_secs_f32();
+        assert!((at(player).truncate() - Vec2::new(0.6, 0.8) * step).length() < 1e-4);
+        assert_eq!(at(bystander), Vec3::ZERO);
+    }
+
+    #[test]
+    fn players_slide_along_walls_and_ignore_broken_input() {
+        let mut room = RoomDef::new("Hall", 4, 3);
+        for y in 0..3 {
+            room.set_tile(2, y, TileKind::Wall);
+        }
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            100,
+        )));
+        app.insert_resource(CollisionMap::new(&room));
+        app.add_systems(FixedUpdate, move_players);
+        app.update();
+
+        let owner = app.world_mut().spawn_empty().id();
+        let mut spawn = |movement| {
+            let controlled = ControlledBy {
+                owner,
+                lifetime: Lifetime::default(),
+            };
+            let input = ActionState(PlayerInput {
+                movement,
+                ..default()
+            });
+            let at = Transform::from_translation(RoomLayout::tile_center(1, 1).extend(0.0));
+            app.world_mut()
+                .spawn((controlled, input, at, Hitbox::default(), Health(100)))
+                .id()
+        };
+        let walker = spawn(Vec2::ONE);
+        let broken = spawn(Vec2::new(f32::NAN, 1.0));
+        for _ in 0..10 {
+            app.update();
+        }
+
+        let at = |entity| {
+            app.world()
+                .get::<Transform>(entity)
+                .unwrap()
+                .translation
+                .truncate()
+        };
+        let start = RoomLayout::tile_center(1, 1);
+        // Blocked by the wall on the right, free to keep going up
+        assert!(at(walker).x + Hitbox::default().radius <= 2.0 * TILE_SIZE);
+        assert!(at(walker).y > start.y);
+        assert_eq!(at(broken), start);
+    }
+
+    #[test]
+    fn dead_players_neither_walk_nor_attack() {
+        #[derive(Resource, Default)]
+        struct Shots(Vec<Entity>);
+
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            100,
+        )));
+        app.add_systems(FixedUpdate, (move_players, player_attacks));
+        app.init_resource::<Shots>();
+        app.add_observer(|trigger: On<FireWeapon>, mut shots: ResMut<Shots>| {
+            shots.0.push(trigger.shooter)
+        });
+        app.update();
+
+        let owner = app.world_mut().spawn_empty().id();
+        let mut spawn = |health| {
+            let controlled = ControlledBy {
+                owner,
+                lifetime: Lifetime::default(),
+            };
+            let input = ActionState(PlayerInput {
+                movement: Vec2::X,
+                attack: true,
+                aim: Vec2::X,
+            });
+            app.world_mut()
+                .spawn((controlled, input, Transform::default(), Health(health)))
+                .id()
+        };
+        let alive = spawn(10);
+        let dead = spawn(0);
+        app.update();
+
+        let at = |entity| app.world().get::<Transform>(entity).unwrap().translation;
+        assert!(at(alive).x > 0.0);
+        assert_eq!(at(dead), Vec3::ZERO);
+        let shots = &app.world().resource::<Shots>().0;
+        assert!(shots.contains(&alive));
+        assert!(!shots.contains(&dead));
+    }
+
+    #[test]
+    fn identities_last_only_as_long_as_their_token() {
+        let mut identities = PlayerIdentities::default();
+        let expiry = Duration::from_secs(TOKEN_EXPIRY_SECS as u64);
+        let (alice, bob) = (Uuid::new_v4(), Uuid::new_v4());
+        identities.authorize(PeerId::Netcode(1), alice, Duration::ZERO);
+        identities.authorize(PeerId::Netcode(2), bob, Duration::from_secs(1));
+        assert_eq!(identities.take(&PeerId::Netcode(2)), Some(bob));
+        assert_eq!(identities.take(&PeerId::Netcode(2)), None);
+
+        // Alice never connected; her entry goes once her token has run out
+        identities.authorize(PeerId::Netcode(3), bob, expiry);
+        assert_eq!(identities.take(&PeerId::Netcode(1)), None);
+        assert_eq!(identities.take(&PeerId::Netcode(3)), Some(bob));
+        assert!(identities.is_empty());
+    }
+
+    #[test]
+    fn clients_get_a_player_built_from_the_template() {
+        let knight = EntityDef::new("Knight", 250);
+        let mut app = player_app(PlayerSettings {
+            template: Some(knight.id),
+            lifetime: Lifetime::SessionBased,
+        });
+        app.world_mut()
+            .resource_mut::<EntityTemplates>()
+            .0
+            .insert(knight.id, knight);
+        let mut room = RoomDef::new("Hall", 6, 4);
+        room.spawn_points.push(SpawnPoint {
+            x: 1,
+            y: 2,
+            tag: PLAYER_SPAWN_TAG.to_string(),
+        });
+        app.insert_resource(ActiveRoom(room));
+
+        let peer = PeerId::Netcode(7);
+        let account = Uuid::new_v4();
+        app.world_mut()
+            .resource_mut::<PlayerIdentities>()
+            .authorize(peer, account, Duration::ZERO);
+        let link = connect(&mut app, peer);
+        let player = player_of(&app, peer).unwrap();
+        // The identity is used up by connecting
+        assert!(app.world().resource::<PlayerIdentities>().is_empty());
+        let world = app.world();
+        assert_eq!(world.get::<PlayerId>(player), Some(&PlayerId(account)));
+        assert_eq!(world.get::<EntityName>(player).unwrap().0, "Knight");
+        assert_eq!(world.get::<Health>(player).unwrap().0, 250);
+        assert_eq!(world.get::<ControlledBy>(player).unwrap().owner, link);
+        assert_eq!(
+            world.get::<Transform>(player).unwrap().translation,
+            RoomLayout::tile_center(1, 2).extend(0.0)
+        );
+        assert!(world.get::<Inventory>(player).is_some());
+
+        // Another client gets a player of their own
+        connect(&mut app, PeerId::Netcode(8));
+        assert_ne!(player_of(&app, PeerId::Netcode(8)), Some(player));
+
+        // Session players are forgotten when their client leaves
+        app.world_mut()
+            .entity_mut(link)
+            .insert(Disconnected::default());
+        assert_eq!(player_of(&app, peer), None);
+    }
+
+    #[test]
+    fn default_players_are_attacked_by_enemies() {
+        let mut app = player_app(PlayerSettings::default());
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            100,
+        )));
+        app.insert_resource(RunRng::new(1));
+        app.init_resource::<ContentRegistry<FactionDef>>();
+        app.add_plugins(AiPlugin);
+        app.add_observer(apply_damage);
+
+        let peer = PeerId::Netcode(7);
+        connect(&mut app, peer);
+        let player = player_of(&app, peer).unwrap();
+
+        // Neither side belongs to a faction
+        let mut grunt = EntityDef::new("Grunt", 10);
+        grunt.ai = Some(AiProfile::grunt());
+        let mut commands = app.world_mut().commands();
+        spawn_from_template(&mut commands, &grunt).insert(Transform::from_xyz(60.0, 0.0, 0.0));
+        app.world_mut().flush();
+        for _ in 0..30 {
+            app.update();
+        }
+
+        assert!(app.world().get::<Health>(player).unwrap().0 < PLAYER_HEALTH);
+    }
+
+    #[test]
+    fn persistent_players_are_taken_back_on_reconnect() {
+        let mut app = player_app(PlayerSettings {
+            template: None,
+            lifetime: Lifetime::Persistent,
+        });
+
+        let peer = PeerId::Netcode(7);
+        let link = connect(&mut app, peer);
+        let player = player_of(&app, peer).unwrap();
+        app.world_mut()
+            .entity_mut(link)
+            .insert(Disconnected::default());
+        app.world_mut().despawn(link);
+        assert_eq!(player_of(&app, peer), Some(player));
+
+        let link = connect(&mut app, peer);
+        assert_eq!(player_of(&app, peer), Some(player));
+        assert_eq!(app.world().get::<ControlledBy>(player).unwrap().owner, link);
+        let mut players = app.world_mut().query::<&PlayerId>();
+        assert_eq!(players.iter(app.world()).count(), 1);
+    }
+}
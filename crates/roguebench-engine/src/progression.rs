@@ -0,0 +1,375 @@
+//! Meta-progression: lifetime stats, banked currencies and unlocks that
+//! survive between runs.
+
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use roguebench_core::{
+    CurrencyDef, EncounterDef, PlayerProfile, RunOutcome, RunSummary, UnlockDef,
+};
+use roguebench_protocol::{Health, Wallet};
+use roguebench_storage::ProfileStore;
+use uuid::Uuid;
+
+use crate::combat::DamageDealt;
+use crate::content::{ContentAppExt, ContentRegistry};
+use crate::encounter::{Encounter, EncounterCleared};
+use crate::events::EntityKilled;
+use crate::resources::Storage;
+use crate::rng::StartRun;
+
+/// Stable ID of the player controlling an entity.
+///
+/// Unlike the netcode client ID it stays the same across connections, so it
+/// keys the player's profile. Adding it to a player loads their profile.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PlayerId(pub Uuid);
+
+/// A player's profile as loaded from storage, written back when a run ends.
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct Profile(pub PlayerProfile);
+
+/// What a player has achieved in the current run.
+#[derive(Component, Debug, Clone, Default, PartialEq)]
+pub struct RunStats {
+    pub kills: u32,
+}
+
+/// Event requesting that the current run ends for every player.
+#[derive(Event, Debug, Clone)]
+pub struct EndRun {
+    pub outcome: RunOutcome,
+}
+
+/// Event fired when a player earns an unlock.
+#[derive(Event, Debug, Clone)]
+pub struct ContentUnlocked {
+    pub player: Entity,
+    /// ID of the [`UnlockDef`].
+    pub unlock: Uuid,
+}
+
+/// Plugin for unlock content and player profiles.
+pub struct ProgressionPlugin;
+
+impl Plugin for ProgressionPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_content::<UnlockDef>();
+
+        app.add_observer(load_profile);
+        app.add_observer(count_kills);
+        app.add_observer(reset_run_stats);
+        app.add_observer(end_run);
+        app.add_observer(win_run_on_clear);
+        app.add_observer(lose_run_on_wipe);
+    }
+}
+
+/// Load a player's profile when their stable ID is assigned.
+pub fn load_profile(
+    trigger: On<Add, PlayerId>,
+    mut commands: Commands,
+    storage: Res<Storage>,
+    ids: Query<&PlayerId>,
+) {
+    let Ok(PlayerId(id)) = ids.get(trigger.entity) else {
+        return;
+    };
+
+    let profile = storage.0.load_profile(*id).unwrap_or_else(|e| {
+        tracing::error!("Failed to load profile {}: {}", id, e);
+        PlayerProfile::new(*id)
+    });
+    commands
+        .entity(trigger.entity)
+        .insert((Profile(profile), RunStats::default()));
+}
+
+/// Credit kills to the player who made them.
+pub fn count_kills(trigger: On<EntityKilled>, mut players: Query<&mut RunStats>) {
+    if let Ok(mut stats) = players.get_mut(trigger.killer) {
+        stats.kills = stats.kills.saturating_add(1);
+    }
+}
+
+/// End the run as won once an encounter that ends it has been cleared.
+///
+/// Other encounters are just rooms along the way.
+pub fn win_run_on_clear(
+    trigger: On<EncounterCleared>,
+    mut commands: Commands,
+    definitions: Res<ContentRegistry<EncounterDef>>,
+    encounters: Query<&Encounter>,
+) {
+    let ends_run = encounters
+        .get(trigger.encounter)
+        .ok()
+        .and_then(|encounter| definitions.get(encounter.def))
+        .is_some_and(|def| def.ends_run);
+    if ends_run {
+        commands.trigger(EndRun {
+            outcome: RunOutcome::Won,
+        });
+    }
+}
+
+/// End the run as lost once the last living player dies.
+///
+/// Dead entities take no further damage, so this fires once per death.
+pub fn lose_run_on_wipe(
+    trigger: On<DamageDealt>,
+    mut commands: Commands,
+    players: Query<&Health, With<PlayerId>>,
+) {
+    if trigger.health > 0 || !players.contains(trigger.target) {
+        return;
+    }
+    if players.iter().all(|health| health.0 <= 0) {
+        commands.trigger(EndRun {
+            outcome: RunOutcome::Died,
+        });
+    }
+}
+
+/// Start every player's run stats over for a new run.
+pub fn reset_run_stats(_trigger: On<StartRun>, mut players: Query<&mut RunStats>) {
+    for mut stats in players.iter_mut() {
+        *stats = RunStats::default();
+    }
+}
+
+/// Record the run in every player's profile, award unlocks and persist the result.
+///
+/// Persistent currencies move from the player's wallet into their profile.
+pub fn end_run(
+    trigger: On<EndRun>,
+    mut commands: Commands,
+    storage: Res<Storage>,
+    currencies: Res<ContentRegistry<CurrencyDef>>,
+    unlocks: Res<ContentRegistry<UnlockDef>>,
+    mut players: Query<(Entity, &mut Profile, &mut RunStats, Option<&mut Wallet>)>,
+) {
+    for (player, mut profile, mut stats, wallet) in players.iter_mut() {
+        let mut banked = BTreeMap::new();
+        if let Some(mut wallet) = wallet {
+            for currency in currencies.iter().filter(|currency| currency.persistent) {
+                let amount = wallet.balance(currency.id);
+                if amount > 0 && wallet.withdraw(currency.id, amount) {
+                    banked.insert(currency.id, amount);
+                }
+            }
+        }
+        let summary = RunSummary {
+            outcome: trigger.outcome,
+            kills: stats.kills,
+            banked,
+        };
+        let profile = &mut profile.0;
+        profile.record_run(&summary);
+
+        // Repeat until nothing new is earned, so unlocks gated on other unlocks
+        // can be earned in the same run
+        loop {
+            let mut earned: Vec<Uuid> = unlocks
+                .iter()
+                .filter(|unlock| !profile.unlocked.contains(&unlock.id))
+                .filter(|unlock| unlock.is_met(profile, &summary))
+                .map(|unlock| unlock.id)
+                .collect();
+            if earned.is_empty() {
+                break;
+            }
+            earned.sort();
+            for unlock in earned {
+                profile.unlocked.insert(unlock);
+                commands.trigger(ContentUnlocked { player, unlock });
+            }
+        }
+
+        if let Err(e) = storage.0.save_profile(profile) {
+            tracing::error!("Failed to write profile {}: {}", profile.id, e);
+        }
+        *stats = RunStats::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::combat::{ApplyDamage, apply_damage};
+    use crate::encounter::{StartEncounter, start_encounter};
+    use roguebench_core::{
+        ContentDef, FactionDef, LifetimeStats, UnlockCondition, WaveDef, WaveSpawn,
+    };
+    use roguebench_storage::MemoryStore;
+    use std::sync::Arc;
+
+    #[derive(Resource, Default)]
+    struct Log(Vec<String>);
+
+    fn registry<T: ContentDef>(defs: &[T]) -> ContentRegistry<T> {
+        let mut registry = ContentRegistry::default();
+        registry.replace_all(defs.iter().cloned());
+        registry
+    }
+
+    #[test]
+    fn run_end_records_stats_banks_currency_and_chains_unlocks() {
+        let mut souls = CurrencyDef::new("Souls");
+        souls.persistent = true;
+        let gold = CurrencyDef::new("Gold");
+        let first_blood = UnlockDef::new("First Blood", vec![UnlockCondition::Kills { count: 1 }]);
+        let survivor = UnlockDef::new(
+            "Survivor",
+            vec![
+                UnlockCondition::Unlocked {
+                    unlock: first_blood.id,
+                },
+                UnlockCondition::WinRun,
+            ],
+        );
+        let champion = UnlockDef::new(
+            "Champion",
+            vec![
+                UnlockCondition::Unlocked {
+                    unlock: survivor.id,
+                },
+                UnlockCondition::Currency {
+                    currency: souls.id,
+                    amount: 60,
+                },
+            ],
+        );
+        let names: BTreeMap<Uuid, String> = [&first_blood, &survivor, &champion]
+            .into_iter()
+            .map(|unlock| (unlock.id, unlock.name.clone()))
+            .collect();
+
+        let storage = Arc::new(MemoryStore::new());
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.insert_resource(Storage(storage.clone()));
+        app.insert_resource(registry(&[souls.clone(), gold.clone()]));
+        app.insert_resource(registry(&[first_blood, survivor, champion]));
+        app.add_observer(load_profile);
+        app.add_observer(count_kills);
+        app.add_observer(end_run);
+        app.init_resource::<Log>();
+        app.add_observer(move |event: On<ContentUnlocked>, mut log: ResMut<Log>| {
+            log.0.push(names[&event.unlock].clone());
+        });
+
+        let player_id = Uuid::new_v4();
+        let mut wallet = Wallet::default();
+        wallet.deposit(souls.id, 40);
+        wallet.deposit(gold.id, 10);
+        let player = app.world_mut().spawn((PlayerId(player_id), wallet)).id();
+        for _ in 0..3 {
+            app.world_mut().commands().trigger(EntityKilled {
+                killer: player,
+                template: Uuid::new_v4(),
+            });
+        }
+        app.world_mut().commands().trigger(EndRun {
+            outcome: RunOutcome::Died,
+        });
+        app.world_mut().flush();
+
+        let profile = storage.load_profile(player_id).unwrap();
+        assert_eq!((profile.stats.runs, profile.stats.deaths), (1, 1));
+        assert_eq!(profile.stats.kills, 3);
+        assert_eq!(profile.currencies[&souls.id], 40);
+        let wallet = app.world().get::<Wallet>(player).unwrap();
+        assert_eq!((wallet.balance(souls.id), wallet.balance(gold.id)), (0, 10));
+        assert_eq!(app.world().resource::<Log>().0, ["First Blood"]);
+
+        // Reconnecting as a new entity picks the profile back up
+        app.world_mut().despawn(player);
+        let mut wallet = Wallet::default();
+        wallet.deposit(souls.id, 25);
+        app.world_mut().spawn((PlayerId(player_id), wallet));
+        app.world_mut().commands().trigger(EndRun {
+            outcome: RunOutcome::Won,
+        });
+        app.world_mut().flush();
+
+        let profile = storage.load_profile(player_id).unwrap();
+        assert_eq!((profile.stats.runs, profile.stats.wins), (2, 1));
+        assert_eq!(profile.currencies[&souls.id], 65);
+        assert_eq!(profile.unlocked.len(), 3);
+        assert_eq!(
+            app.world().resource::<Log>().0,
+            ["First Blood", "Survivor", "Champion"]
+        );
+    }
+
+    #[test]
+    fn runs_end_when_the_final_encounter_is_cleared_or_every_player_dies() {
+        let storage = Arc::new(MemoryStore::new());
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.insert_resource(Storage(storage.clone()));
+        app.init_resource::<ContentRegistry<CurrencyDef>>();
+        app.init_resource::<ContentRegistry<UnlockDef>>();
+        app.init_resource::<ContentRegistry<FactionDef>>();
+        app.add_observer(load_profile);
+        app.add_observer(apply_damage);
+        app.add_observer(end_run);
+        app.add_observer(win_run_on_clear);
+        app.add_observer(lose_run_on_wipe);
+        let wave = WaveDef::new(vec![WaveSpawn {
+            entity: Uuid::new_v4(),
+            count: 1,
+        }]);
+        let hall = EncounterDef::new("Hall", vec![wave.clone()]);
+        let mut boss = EncounterDef::new("Boss", vec![wave]);
+        boss.ends_run = true;
+        app.insert_resource(registry(&[hall.clone(), boss.clone()]));
+        app.add_observer(start_encounter);
+
+        let ids = [Uuid::new_v4(), Uuid::new_v4()];
+        let players = ids.map(|id| app.world_mut().spawn((PlayerId(id), Health(10))).id());
+        let kill = |app: &mut App, target| {
+            app.world_mut().commands().trigger(ApplyDamage {
+                target,
+                source: target,
+                amount: 20,
+            });
+            app.world_mut().flush();
+        };
+        let stats = |stat: fn(&LifetimeStats) -> u32| {
+            ids.map(|id| stat(&storage.load_profile(id).unwrap().stats))
+        };
+
+        // The run goes on while anyone is still standing
+        kill(&mut app, players[0]);
+        assert_eq!(stats(|stats| stats.runs), [0, 0]);
+        kill(&mut app, players[1]);
+        assert_eq!(stats(|stats| stats.deaths), [1, 1]);
+        // Hitting the dead doesn't end the run again
+        kill(&mut app, players[1]);
+        assert_eq!(stats(|stats| stats.runs), [1, 1]);
+
+        // A run through two encounters is one run, won by the last
+        let clear = |app: &mut App, def: &EncounterDef| {
+            app.world_mut()
+                .commands()
+                .trigger(StartEncounter { encounter: def.id });
+            app.world_mut().flush();
+            let mut encounters = app.world_mut().query::<(Entity, &Encounter)>();
+            let (encounter, _) = encounters
+                .iter(app.world())
+                .find(|(_, encounter)| encounter.def == def.id)
+                .unwrap();
+            app.world_mut()
+                .commands()
+                .trigger(EncounterCleared { encounter });
+            app.world_mut().flush();
+        };
+        clear(&mut app, &hall);
+        assert_eq!(stats(|stats| stats.runs), [1, 1]);
+        clear(&mut app, &boss);
+        assert_eq!(stats(|stats| stats.runs), [2, 2]);
+        assert_eq!(stats(|stats| stats.wins), [1, 1]);
+    }
+}
@@ -0,0 +1,269 @@
+//! Server-authoritative projectile simulation.
+
+use bevy::prelude::*;
+use lightyear::prelude::{NetworkTarget, Replicate};
+use roguebench_core::{FactionDef, Relation};
+use roguebench_protocol::{Health, Projectile};
+
+use crate::combat::{ApplyDamage, Hitbox};
+use crate::content::ContentRegistry;
+use crate::faction::Team;
+use crate::room::CollisionMap;
+
+/// Server-side state of a projectile in flight.
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct ProjectileFlight {
+    /// The entity that fired the projectile; it can't be hit by it.
+    pub shooter: Entity,
+    pub damage: i32,
+    pub radius: f32,
+    /// Distance left before the projectile expires.
+    pub remaining: f32,
+}
+
+/// Event fired when a projectile hits a wall or an entity.
+#[derive(Event, Debug, Clone)]
+pub struct ProjectileHit {
+    pub shooter: Entity,
+    /// The entity hit, or `None` for a wall.
+    pub target: Option<Entity>,
+    pub position: Vec2,
+}
+
+/// Plugin for moving projectiles and resolving their hits.
+pub struct ProjectilePlugin;
+
+impl Plugin for ProjectilePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, move_projectiles);
+    }
+}
+
+/// Spawn a projectile, replicated to every client.
+pub fn spawn_projectile(
+    commands: &mut Commands,
+    projectile: Projectile,
+    flight: ProjectileFlight,
+) -> Entity {
+    commands
+        .spawn((
+            Transform::from_translation(projectile.origin.extend(0.0)),
+            projectile,
+            flight,
+            Replicate::to_clients(NetworkTarget::All),
+        ))
+        .id()
+}
+
+/// Move every projectile by one fixed tick, sweeping its path for hits.
+///
+/// Projectiles pass through entities friendly to their shooter.
+#[allow(clippy::type_complexity)]
+pub fn move_projectiles(
+    mut commands: Commands,
+    time: Res<Time>,
+    collision: Option<Res<CollisionMap>>,
+    factions: Res<ContentRegistry<FactionDef>>,
+    mut projectiles: Query<(Entity, &Projectile, &mut ProjectileFlight, &mut Transform)>,
+    targets: Query<
+        (Entity, &Transform, &Hitbox, &Health, Option<&Team>),
+        Without<ProjectileFlight>,
+    >,
+    teams: Query<&Team>,
+) {
+    let dt = time.delta_secs();
+    for (entity, projectile, mut flight, mut transform) in projectiles.iter_mut() {
+        let from = transform.translation.truncate();
+        let step = (projectile.velocity.length() * dt).min(flight.remaining);
+        let to = from + projectile.velocity.normalize_or_zero() * step;
+
+        let wall = collision.as_ref().and_then(|map| map.cast_ray(from, to));
+        let shooter_team = teams.get(flight.shooter).ok();
+        let target = targets
+            .iter()
+            .filter(|(target, _, _, health, _)| *target != flight.shooter && health.0 > 0)
+            .filter(|(.., team)| factions.relation(shooter_team, *team) != Relation::Friendly)
+            .filter_map(|(target, position, hitbox, ..)| {
+                let center = position.translation.truncate();
+                sweep_circle(from, to, center, hitbox.radius + flight.radius).map(|t| (target, t))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+
+        let hit = match (target, wall) {
+            (Some((target, t)), wall) if wall.is_none_or(|wall| t <= wall) => {
+                Some((Some(target), t))
+            }
+            (_, Some(wall)) => Some((None, wall)),
+            _ => None,
+        };
+
+        if let Some((target, t)) = hit {
+            if let Some(target) = target {
+                commands.trigger(ApplyDamage {
+                    target,
+                    source: flight.shooter,
+                    amount: flight.damage,
+                });
+            }
+            commands.trigger(ProjectileHit {
+                shooter: flight.shooter,
+                target,
+                position: from.lerp(to, t),
+            });
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        transform.translation = to.extend(transform.translation.z);
+        flight.remaining -= step;
+        if flight.remaining <= 0.0 {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// How far along the segment from `from` to `to` it first touches a circle,
+/// as a fraction between 0 and 1.
+fn sweep_circle(from: Vec2, to: Vec2, center: Vec2, radius: f32) -> Option<f32> {
+    let offset = from - center;
+    let c = offset.length_squared() - radius * radius;
+    if c <= 0.0 {
+        return Some(0.0);
+    }
+    let d = to - from;
+    let a = d.length_squared();
+    if a == 0.0 {
+        return None;
+    }
+    let b = offset.dot(d);
+    let discriminant = b * b - a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let t = (-b - discriminant.sqrt()) / a;
+    (0.0..=1.0).contains(&t).then_some(t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::time::TimeUpdateStrategy;
+    use roguebench_core::{RoomDef, TileKind};
+    use roguebench_protocol::TILE_SIZE;
+    use std::time::Duration;
+
+    use crate::combat::apply_damage;
+
+    #[derive(Resource, Default)]
+    struct Hits(Vec<(Option<Entity>, Vec2)>);
+
+    fn test_app(room: &RoomDef) -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            100,
+        )));
+        app.insert_resource(CollisionMap::new(room));
+        app.init_resource::<ContentRegistry<FactionDef>>();
+
+        app.init_resource::<Hits>();
+        app.add_systems(FixedUpdate, move_projectiles);
+        app.add_observer(apply_damage);
+        app.add_observer(|hit: On<ProjectileHit>, mut hits: ResMut<Hits>| {
+            hits.0.push((hit.target, hit.position));
+        });
+        // The first update only starts the clock
+        app.update();
+        app
+    }
+
+    fn fire(app: &mut App, shooter: Entity, origin: Vec2, velocity: Vec2, range: f32) {
+        let mut commands = app.world_mut().commands();
+        spawn_projectile(
+            &mut commands,
+            Projectile { origin, velocity },
+            ProjectileFlight {
+                shooter,
+                damage: 7,
+                radius: 2.0,
+                remaining: range,
+            },
+        );
+        app.world_mut().flush();
+    }
+
+    fn target(app: &mut App, position: Vec2) -> Entity {
+        app.world_mut()
+            .spawn((
+                Transform::from_translation(position.extend(0.0)),
+                Hitbox { radius: 8.0 },
+                Health(10),
+            ))
+            .id()
+    }
+
+    fn projectile_count(app: &mut App) -> usize {
+        let mut projectiles = app.world_mut().query::<&Projectile>();
+        projectiles.iter(app.world()).count()
+    }
+
+    #[test]
+    fn fast_projectiles_hit_walls_instead_of_tunnelling() {
+        let mut room = RoomDef::new("Range", 20, 3);
+        room.set_tile(6, 1, TileKind::Wall);
+        let mut app = test_app(&room);
+        let behind_wall = target(&mut app, Vec2::new(8.5, 1.5) * TILE_SIZE);
+
+        // Several tiles per tick, far more than the wall is thick
+        let shooter = app.world_mut().spawn_empty().id();
+        fire(
+            &mut app,
+            shooter,
+            Vec2::new(1.5, 1.5) * TILE_SIZE,
+            Vec2::new(40_000.0, 0.0),
+            1_000.0,
+        );
+        app.update();
+
+        let hits = &app.world().resource::<Hits>().0;
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].0, None);
+        assert!((hits[0].1.x - 6.0 * TILE_SIZE).abs() < 1e-2);
+        assert_eq!(app.world().get::<Health>(behind_wall).unwrap().0, 10);
+        assert_eq!(projectile_count(&mut app), 0);
+    }
+
+    #[test]
+    fn projectiles_damage_entities_but_not_their_shooter() {
+        let room = RoomDef::new("Arena", 20, 20);
+        let mut app = test_app(&room);
+        let shooter = target(&mut app, Vec2::new(100.0, 100.0));
+        let victim = target(&mut app, Vec2::new(200.0, 100.0));
+
+        fire(
+            &mut app,
+            shooter,
+            Vec2::new(100.0, 100.0),
+            Vec2::new(3_000.0, 0.0),
+            500.0,
+        );
+        // Misses everything and expires at the end of its range
+        fire(
+            &mut app,
+            shooter,
+            Vec2::new(100.0, 100.0),
+            Vec2::new(0.0, 3_000.0),
+            150.0,
+        );
+        app.update();
+
+        assert_eq!(app.world().get::<Health>(shooter).unwrap().0, 10);
+        assert_eq!(app.world().get::<Health>(victim).unwrap().0, 3);
+        let hits = &app.world().resource::<Hits>().0;
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].0, Some(victim));
+        // Touches the victim's hitbox grown by the projectile radius
+        assert!((hits[0].1.x - 190.0).abs() < 1e-2);
+        assert_eq!(projectile_count(&mut app), 0);
+    }
+}
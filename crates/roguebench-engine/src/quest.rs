@@ -0,0 +1,515 @@
+//! Per-player quest tracking driven by gameplay events.
+
+use bevy::prelude::*;
+use roguebench_core::{ItemDef, QuestDef, QuestProgress, QuestReward, QuestTrigger};
+use roguebench_protocol::{Inventory, QuestJournal};
+use uuid::Uuid;
+
+use crate::content::{ContentAppExt, ContentRegistry};
+use crate::dialogue::{Conversation, StoryFlags};
+use crate::events::{EntityKilled, RoomEntered};
+use crate::inventory::ItemPickedUp;
+use crate::replication::replicate_to_owner_only;
+use crate::save::SaveGame;
+use crate::systems::EntityTemplate;
+
+/// Event requesting that `player` starts a quest.
+#[derive(Event, Debug, Clone)]
+pub struct StartQuest {
+    pub player: Entity,
+    pub quest: Uuid,
+}
+
+/// Event fired when a player starts a quest.
+#[derive(Event, Debug, Clone)]
+pub struct QuestStarted {
+    pub player: Entity,
+    pub quest: Uuid,
+}
+
+/// Event fired when a player completes one of a quest's objectives.
+#[derive(Event, Debug, Clone)]
+pub struct QuestObjectiveCompleted {
+    pub player: Entity,
+    pub quest: Uuid,
+    /// Index into [`QuestDef::objectives`].
+    pub objective: usize,
+}
+
+/// Event fired when a player completes a quest, after which rewards are granted.
+#[derive(Event, Debug, Clone)]
+pub struct QuestCompleted {
+    pub player: Entity,
+    pub quest: Uuid,
+}
+
+/// Plugin for quest content and per-player quest progress.
+pub struct QuestPlugin;
+
+impl Plugin for QuestPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_content::<QuestDef>();
+
+        app.add_observer(start_quest);
+        app.add_observer(track_kills);
+        app.add_observer(track_pickups);
+        app.add_observer(track_rooms);
+        app.add_observer(track_conversations);
+        app.add_observer(grant_quest_rewards);
+        app.add_observer(replicate_to_owner_only::<QuestJournal>);
+    }
+}
+
+/// Start a quest if the player meets its prerequisites.
+pub fn start_quest(
+    trigger: On<StartQuest>,
+    mut commands: Commands,
+    quests: Res<ContentRegistry<QuestDef>>,
+    mut players: Query<&mut QuestJournal>,
+) {
+    let StartQuest { player, quest } = *trigger;
+
+    let Ok(mut journal) = players.get_mut(player) else {
+        return;
+    };
+    let Some(quest) = quests.get(quest) else {
+        tracing::warn!("Cannot start unknown quest {quest}");
+        return;
+    };
+    if !journal.0.can_start(quest) {
+        return;
+    }
+
+    journal.0.active.push(QuestProgress::new(quest));
+    commands.trigger(QuestStarted {
+        player,
+        quest: quest.id,
+    });
+    commands.trigger(SaveGame { player });
+}
+
+/// Apply a gameplay happening to all of a player's active quests.
+fn advance_quests(
+    commands: &mut Commands,
+    quests: &ContentRegistry<QuestDef>,
+    player: Entity,
+    journal: &mut QuestJournal,
+    trigger: QuestTrigger,
+) {
+    // Work on a copy so untouched journals aren't marked changed and re-replicated
+    let mut log = journal.0.clone();
+    let mut finished = Vec::new();
+
+    for progress in log.active.iter_mut() {
+        let Some(quest) = quests.get(progress.quest) else {
+            continue;
+        };
+        for objective in progress.advance(quest, &trigger) {
+            commands.trigger(QuestObjectiveCompleted {
+                player,
+                quest: quest.id,
+                objective,
+            });
+        }
+        if progress.is_complete(quest) {
+            finished.push(quest.id);
+        }
+    }
+
+    log.active
+        .retain(|progress| !finished.contains(&progress.quest));
+    for quest in finished {
+        log.completed.push(quest);
+        commands.trigger(QuestCompleted { player, quest });
+    }
+
+    if log != journal.0 {
+        journal.0 = log;
+        commands.trigger(SaveGame { player });
+    }
+}
+
+/// Credit kills to the killer's quests.
+pub fn track_kills(
+    trigger: On<EntityKilled>,
+    mut commands: Commands,
+    quests: Res<ContentRegistry<QuestDef>>,
+    mut players: Query<&mut QuestJournal>,
+) {
+    let Ok(mut journal) = players.get_mut(trigger.killer) else {
+        return;
+    };
+    advance_quests(
+        &mut commands,
+        &quests,
+        trigger.killer,
+        &mut journal,
+        QuestTrigger::Killed {
+            template: trigger.template,
+        },
+    );
+}
+
+/// Credit picked up items to collect objectives.
+pub fn track_pickups(
+    trigger: On<ItemPickedUp>,
+    mut commands: Commands,
+    quests: Res<ContentRegistry<QuestDef>>,
+    mut players: Query<&mut QuestJournal>,
+) {
+    let Ok(mut journal) = players.get_mut(trigger.picker) else {
+        return;
+    };
+    advance_quests(
+        &mut commands,
+        &quests,
+        trigger.picker,
+        &mut journal,
+        QuestTrigger::Collected {
+            item: trigger.item,
+            count: trigger.count,
+        },
+    );
+}
+
+/// Credit entered rooms to reach objectives.
+pub fn track_rooms(
+    trigger: On<RoomEntered>,
+    mut commands: Commands,
+    quests: Res<ContentRegistry<QuestDef>>,
+    mut players: Query<&mut QuestJournal>,
+) {
+    let Ok(mut journal) = players.get_mut(trigger.player) else {
+        return;
+    };
+    advance_quests(
+        &mut commands,
+        &quests,
+        trigger.player,
+        &mut journal,
+        QuestTrigger::EnteredRoom { room: trigger.room },
+    );
+}
+
+/// Credit new conversations to talk objectives.
+pub fn track_conversations(
+    trigger: On<Add, Conversation>,
+    mut commands: Commands,
+    quests: Res<ContentRegistry<QuestDef>>,
+    mut players: Query<(&Conversation, &mut QuestJournal)>,
+    templates: Query<&EntityTemplate>,
+) {
+    let Ok((conversation, mut journal)) = players.get_mut(trigger.entity) else {
+        return;
+    };
+    let Ok(EntityTemplate(npc)) = templates.get(conversation.npc) else {
+        return;
+    };
+    advance_quests(
+        &mut commands,
+        &quests,
+        trigger.entity,
+        &mut journal,
+        QuestTrigger::TalkedTo { npc: *npc },
+    );
+}
+
+/// Grant a completed quest's rewards.
+pub fn grant_quest_rewards(
+    trigger: On<QuestCompleted>,
+    quests: Res<ContentRegistry<QuestDef>>,
+    items: Res<ContentRegistry<ItemDef>>,
+    mut players: Query<(Option<&mut Inventory>, Option<&mut StoryFlags>)>,
+) {
+    let Some(quest) = quests.get(trigger.quest) else {
+        return;
+    };
+    let Ok((mut inventory, mut flags)) = players.get_mut(trigger.player) else {
+        return;
+    };
+
+    for reward in &quest.rewards {
+        match reward {
+            QuestReward::Item { item, count } => {
+                let (Some(inventory), Some(item)) = (inventory.as_mut(), items.get(*item)) else {
+                    tracing::warn!("Cannot grant item reward of quest '{}'", quest.name);
+                    continue;
+                };
+                let leftover = inventory.add(item, *count);
+                if leftover > 0 {
+                    tracing::warn!("{} {} did not fit in the inventory", leftover, item.name);
+                }
+            }
+            QuestReward::SetFlag { flag } => {
+                if let Some(flags) = flags.as_mut() {
+                    flags.set(flag, true);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progression::PlayerId;
+    use crate::resources::Storage;
+    use crate::rng::RunRng;
+    use crate::room::{LoadRoom, enter_loaded_room, load_room};
+    use crate::save::{SavePlugin, SaveSlot};
+    use roguebench_core::{Objective, RoomDef, SaveData};
+    use roguebench_storage::{ContentStore, ContentStoreExt, MemoryStore};
+    use std::sync::Arc;
+
+    #[derive(Resource, Default)]
+    struct Log(Vec<String>);
+
+    fn test_app(storage: Arc<dyn ContentStore>, quests: &[QuestDef], items: &[ItemDef]) -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.insert_resource(Storage(storage));
+        app.add_plugins(SavePlugin);
+
+        let mut registry = ContentRegistry::<QuestDef>::default();
+        registry.replace_all(quests.iter().cloned());
+        app.insert_resource(registry);
+        let mut registry = ContentRegistry::<ItemDef>::default();
+        registry.replace_all(items.iter().cloned());
+        app.insert_resource(registry);
+
+        app.add_observer(start_quest);
+        app.add_observer(track_kills);
+        app.add_observer(track_pickups);
+        app.add_observer(grant_quest_rewards);
+
+        app.init_resource::<Log>();
+        app.add_observer(|t: On<QuestStarted>, mut log: ResMut<Log>| {
+            log.0.push(format!("started {}", t.quest));
+        });
+        app.add_observer(|t: On<QuestObjectiveCompleted>, mut log: ResMut<Log>| {
+            log.0.push(format!("objective {}", t.objective));
+        });
+        app.add_observer(|t: On<QuestCompleted>, mut log: ResMut<Log>| {
+            log.0.push(format!("completed {}", t.quest));
+        });
+        app
+    }
+
+    #[test]
+    fn quest_progresses_from_events_and_grants_rewards() {
+        let goblin = Uuid::new_v4();
+        let coin = ItemDef::new("Coin", 99);
+        let mut quest = QuestDef::new(
+            "Goblin trouble",
+            vec![
+                Objective::Kill {
+                    template: goblin,
+                    count: 2,
+                },
+                Objective::Collect {
+                    item: coin.id,
+                    count: 3,
+                },
+            ],
+        );
+        quest.rewards = vec![
+            QuestReward::Item {
+                item: coin.id,
+                count: 10,
+            },
+            QuestReward::SetFlag {
+                flag: "goblins_cleared".to_string(),
+            },
+        ];
+        let mut app = test_app(
+            Arc::new(MemoryStore::new()),
+            std::slice::from_ref(&quest),
+            std::slice::from_ref(&coin),
+        );
+
+        let player = app
+            .world_mut()
+            .spawn((
+                QuestJournal::default(),
+                Inventory::new(4),
+                StoryFlags::default(),
+            ))
+            .id();
+        let mut commands = app.world_mut().commands();
+        commands.trigger(StartQuest {
+            player,
+            quest: quest.id,
+        });
+        // Ordered: the pickup doesn't count before both kills
+        commands.trigger(ItemPickedUp {
+            picker: player,
+            item: coin.id,
+            count: 3,
+        });
+        for _ in 0..2 {
+            commands.trigger(EntityKilled {
+                killer: player,
+                template: goblin,
+            });
+        }
+        commands.trigger(ItemPickedUp {
+            picker: player,
+            item: coin.id,
+            count: 3,
+        });
+        app.update();
+
+        assert_eq!(
+            app.world().resource::<Log>().0,
+            vec![
+                format!("started {}", quest.id),
+                "objective 0".to_string(),
+                "objective 1".to_string(),
+                format!("completed {}", quest.id),
+            ]
+        );
+        let journal = app.world().get::<QuestJournal>(player).unwrap();
+        assert!(journal.0.active.is_empty());
+        assert!(journal.0.is_completed(quest.id));
+        assert_eq!(
+            app.world().get::<Inventory>(player).unwrap().count(coin.id),
+            10
+        );
+        assert!(
+            app.world()
+                .get::<StoryFlags>(player)
+                .unwrap()
+                .is_set("goblins_cleared")
+        );
+
+        // Completed quests can't be started again
+        app.world_mut().commands().trigger(StartQuest {
+            player,
+            quest: quest.id,
+        });
+        app.update();
+        assert_eq!(app.world().resource::<Log>().0.len(), 4);
+    }
+
+    #[test]
+    fn loading_a_room_reaches_it_for_every_player() {
+        let cave = RoomDef::new("Cave", 8, 8);
+        let quest = QuestDef::new(
+            "Into the cave",
+            vec![Objective::ReachRoom { room: cave.id }],
+        );
+        let mut app = test_app(
+            Arc::new(MemoryStore::new()),
+            std::slice::from_ref(&quest),
+            &[],
+        );
+        let mut rooms = ContentRegistry::<RoomDef>::default();
+        rooms.insert(cave.clone());
+        app.insert_resource(rooms);
+        app.add_observer(load_room);
+        app.add_observer(enter_loaded_room);
+        app.add_observer(track_rooms);
+
+        let player = app
+            .world_mut()
+            .spawn((PlayerId(Uuid::new_v4()), QuestJournal::default()))
+            .id();
+        let mut commands = app.world_mut().commands();
+        commands.trigger(StartQuest {
+            player,
+            quest: quest.id,
+        });
+        commands.trigger(LoadRoom { room: cave.id });
+        app.update();
+
+        let journal = app.world().get::<QuestJournal>(player).unwrap();
+        assert!(journal.0.is_completed(quest.id));
+    }
+
+    #[test]
+    fn quest_requires_prerequisites() {
+        let npc = Uuid::new_v4();
+        let first = QuestDef::new("First", vec![Objective::TalkTo { npc }]);
+        let mut second = QuestDef::new("Second", vec![Objective::TalkTo { npc }]);
+        second.prerequisites.push(first.id);
+        let mut app = test_app(
+            Arc::new(MemoryStore::new()),
+            &[first.clone(), second.clone()],
+            &[],
+        );
+
+        let player = app.world_mut().spawn(QuestJournal::default()).id();
+        app.world_mut().commands().trigger(StartQuest {
+            player,
+            quest: second.id,
+        });
+        app.update();
+        assert!(
+            app.world()
+                .get::<QuestJournal>(player)
+                .unwrap()
+                .0
+                .active
+                .is_empty()
+        );
+
+        app.world_mut()
+            .get_mut::<QuestJournal>(player)
+            .unwrap()
+            .0
+            .completed
+            .push(first.id);
+        app.world_mut().commands().trigger(StartQuest {
+            player,
+            quest: second.id,
+        });
+        app.update();
+        assert!(
+            app.world()
+                .get::<QuestJournal>(player)
+                .unwrap()
+                .0
+                .is_active(second.id)
+        );
+    }
+
+    #[test]
+    fn quest_progress_persists_in_save() {
+        let goblin = Uuid::new_v4();
+        let quest = QuestDef::new(
+            "Cull",
+            vec![Objective::Kill {
+                template: goblin,
+                count: 3,
+            }],
+        );
+        let storage = Arc::new(MemoryStore::new());
+        let slot = Uuid::new_v4();
+
+        let mut app = test_app(storage.clone(), std::slice::from_ref(&quest), &[]);
+        app.insert_resource(RunRng::new(99));
+        let player = app.world_mut().spawn(SaveSlot(slot)).id();
+        app.update();
+        app.world_mut().commands().trigger(StartQuest {
+            player,
+            quest: quest.id,
+        });
+        app.world_mut().commands().trigger(EntityKilled {
+            killer: player,
+            template: goblin,
+        });
+        app.update();
+
+        let save = storage.load::<SaveData>(slot).unwrap().unwrap();
+        assert_eq!(save.quests.active[0].counts, vec![1]);
+        assert_eq!(save.seed, Some(99));
+
+        // A fresh session picks up where the last one left off
+        let mut app = test_app(storage, std::slice::from_ref(&quest), &[]);
+        let player = app.world_mut().spawn(SaveSlot(slot)).id();
+        app.update();
+        let journal = app.world().get::<QuestJournal>(player).unwrap();
+        assert_eq!(journal.0.active[0].quest, quest.id);
+        assert_eq!(journal.0.active[0].counts, vec![1]);
+    }
+}
@@ -0,0 +1,23 @@
+//! Shared replication helpers.
+
+use bevy::prelude::*;
+use lightyear::prelude::{ComponentReplicationOverrides, ControlledBy};
+
+/// Only replicate component `C` to the client controlling its entity.
+///
+/// Register as an observer once per component that is private to its owner.
+pub fn replicate_to_owner_only<C: Component>(
+    trigger: On<Add, (C, ControlledBy)>,
+    mut commands: Commands,
+    owners: Query<Option<&ControlledBy>, With<C>>,
+) {
+    let Ok(controlled_by) = owners.get(trigger.entity) else {
+        return;
+    };
+
+    let mut overrides = ComponentReplicationOverrides::<C>::default().disable_all();
+    if let Some(controlled_by) = controlled_by {
+        overrides = overrides.enable_for(controlled_by.owner);
+    }
+    commands.entity(trigger.entity).insert(overrides);
+}
@@ -1,12 +1,17 @@
 //! Bevy resources for the engine.
 
+use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::{Arc, Mutex};
 
 use bevy::prelude::*;
+use lightyear::netcode::Key;
+use lightyear::prelude::Lifetime;
+use roguebench_core::EntityDef;
 use roguebench_protocol::EditorMessage;
 use roguebench_storage::ContentStore;
 use tokio::sync::mpsc;
+use uuid::Uuid;
 
 /// Configuration for the engine plugin.
 ///
@@ -20,6 +25,14 @@ pub struct EngineConfig {
     pub(crate) editor_receiver: Mutex<Option<mpsc::UnboundedReceiver<EditorMessage>>>,
     /// Address for the Lightyear server.
     pub server_addr: SocketAddr,
+    /// Private key connect tokens for the server are signed with.
+    pub private_key: Key,
+    /// Seed for the first run; random when `None`.
+    pub seed: Option<u64>,
+    /// ID of the entity definition players are built from.
+    pub player_template: Option<Uuid>,
+    /// Whether players outlive their client's connection.
+    pub player_lifetime: Lifetime,
 }
 
 /// Resource holding the content store.
@@ -30,6 +43,14 @@ pub struct Storage(pub Arc<dyn ContentStore>);
 #[derive(Resource)]
 pub struct ServerAddr(pub SocketAddr);
 
+/// Resource holding the server's private key.
+#[derive(Resource)]
+pub struct ServerKey(pub Key);
+
 /// Resource for receiving messages from the web editor.
 #[derive(Resource)]
 pub struct EditorReceiver(pub mpsc::UnboundedReceiver<EditorMessage>);
+
+/// Resource holding every entity definition, refreshed on each entity reload.
+#[derive(Resource, Default)]
+pub struct EntityTemplates(pub HashMap<Uuid, EntityDef>);
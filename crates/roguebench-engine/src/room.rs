@@ -0,0 +1,324 @@
+//! Room loading and tile collision.
+
+use bevy::prelude::*;
+use lightyear::prelude::{NetworkTarget, Replicate};
+use roguebench_core::{ContentDef, RoomDef, TileKind, grid_index};
+use roguebench_protocol::{RoomLayout, TILE_SIZE};
+use uuid::Uuid;
+
+use crate::content::{ContentAppExt, ContentRegistry, ReloadContent};
+use crate::events::RoomEntered;
+use crate::progression::PlayerId;
+
+/// Event requesting that the given room becomes the active room.
+#[derive(Event, Debug, Clone)]
+pub struct LoadRoom {
+    pub room: Uuid,
+}
+
+/// Event fired once a room has been loaded and its collision map built.
+#[derive(Event, Debug, Clone)]
+pub struct RoomLoaded {
+    pub room: Uuid,
+}
+
+/// The room currently being played, authored or generated.
+#[derive(Resource, Debug, Clone)]
+pub struct ActiveRoom(pub RoomDef);
+
+/// Tile collision for the active room.
+///
+/// Cell `(0, 0)` covers the world square from the origin to `(TILE_SIZE, TILE_SIZE)`.
+/// Everything outside the grid is treated as solid.
+#[derive(Resource, Debug, Clone)]
+pub struct CollisionMap {
+    /// ID of the room the map was built from.
+    pub room: Uuid,
+    width: u32,
+    height: u32,
+    tiles: Vec<TileKind>,
+}
+
+impl CollisionMap {
+    pub fn new(room: &RoomDef) -> Self {
+        Self {
+            room: room.id,
+            width: room.width,
+            height: room.height,
+            tiles: room.tiles.clone(),
+        }
+    }
+
+    /// The cell containing a world position, or `None` outside the grid.
+    pub fn cell_at(&self, position: Vec2) -> Option<(u32, u32)> {
+        let cell = (position / TILE_SIZE).floor();
+        if cell.x < 0.0 || cell.y < 0.0 {
+            return None;
+        }
+        let (x, y) = (cell.x as u32, cell.y as u32);
+        (x < self.width && y < self.height).then_some((x, y))
+    }
+
+    /// The tile at a world position, or `None` outside the grid.
+    pub fn tile_at(&self, position: Vec2) -> Option<TileKind> {
+        let (x, y) = self.cell_at(position)?;
+        self.tiles.get(grid_index(self.width, x, y)).copied()
+    }
+
+    /// Whether an entity can stand at the world position.
+    pub fn is_walkable(&self, position: Vec2) -> bool {
+        self.tile_at(position).is_some_and(TileKind::is_walkable)
+    }
+
+    /// Whether a projectile at the world position is stopped.
+    pub fn blocks_projectile(&self, position: Vec2) -> bool {
+        self.tile_at(position)
+            .is_none_or(TileKind::blocks_projectiles)
+    }
+
+    /// Whether a box centred on `center` overlaps any tile entities can't stand on.
+    pub fn blocks_box(&self, center: Vec2, half_extents: Vec2) -> bool {
+        let min = ((center - half_extents) / TILE_SIZE).floor();
+        // Nudge the max corner inwards so a box flush against a tile edge doesn't touch it
+        let max = ((center + half_extents) / TILE_SIZE - Vec2::splat(1e-4)).floor();
+        for y in min.y as i64..=max.y as i64 {
+            for x in min.x as i64..=max.x as i64 {
+                let cell = (Vec2::new(x as f32, y as f32) + 0.5) * TILE_SIZE;
+                if !self.is_walkable(cell) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// How far along the segment from `from` to `to` it first enters a tile
+    /// that stops projectiles, as a fraction between 0 and 1.
+    ///
+    /// Walks every cell the segment crosses, so fast movers can't skip a wall.
+    pub fn cast_ray(&self, from: Vec2, to: Vec2) -> Option<f32> {
+        let start = from / TILE_SIZE;
+        let delta = to / TILE_SIZE - start;
+        let mut cell = start.floor();
+        let blocks = |cell: Vec2| self.blocks_projectile((cell + 0.5) * TILE_SIZE);
+        if blocks(cell) {
+            return Some(0.0);
+        }
+
+        // Fraction of the segment needed to cross one cell, and to reach the next cell edge
+        let step = delta.signum();
+        let t_delta = delta.abs().recip();
+        let mut t_max = Vec2::ZERO;
+        for axis in 0..2 {
+            t_max[axis] = if delta[axis] > 0.0 {
+                (cell[axis] + 1.0 - start[axis]) * t_delta[axis]
+            } else if delta[axis] < 0.0 {
+                (start[axis] - cell[axis]) * t_delta[axis]
+            } else {
+                f32::INFINITY
+            };
+        }
+
+        loop {
+            let axis = if t_max.x < t_max.y { 0 } else { 1 };
+            let t = t_max[axis];
+            if t > 1.0 {
+                return None;
+            }
+            cell[axis] += step[axis];
+            t_max[axis] += t_delta[axis];
+            if blocks(cell) {
+                return Some(t);
+            }
+        }
+    }
+}
+
+/// Plugin for room content, the active room and its collision map.
+pub struct RoomPlugin;
+
+impl Plugin for RoomPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_content::<RoomDef>();
+
+        app.add_observer(load_room);
+        app.add_observer(reload_active_room);
+        app.add_observer(enter_loaded_room);
+    }
+}
+
+/// Move up to `distance` towards a point, sliding along tiles that block the way.
+pub(crate) fn step(
+    from: Vec2,
+    towards: Vec2,
+    distance: f32,
+    collision: Option<&CollisionMap>,
+    half_extents: Vec2,
+) -> Vec2 {
+    let offset = towards - from;
+    let motion = offset.clamp_length_max(distance);
+    let mut position = from;
+    for axis in [Vec2::X, Vec2::Y] {
+        let next = position + axis * motion.dot(axis);
+        if collision.is_none_or(|map| !map.blocks_box(next, half_extents)) {
+            position = next;
+        }
+    }
+    position
+}
+
+/// Load an authored room from the registry.
+pub fn load_room(
+    trigger: On<LoadRoom>,
+    mut commands: Commands,
+    rooms: Res<ContentRegistry<RoomDef>>,
+    existing: Query<Entity, With<RoomLayout>>,
+) {
+    let Some(room) = rooms.get(trigger.room) else {
+        tracing::warn!("Cannot load unknown room {}", trigger.room);
+        return;
+    };
+    activate_room(&mut commands, room.clone(), &existing);
+}
+
+/// Replace the active room: respawn its replicated layout and rebuild collision.
+pub fn activate_room(
+    commands: &mut Commands,
+    room: RoomDef,
+    existing: &Query<Entity, With<RoomLayout>>,
+) {
+    tracing::info!(
+        "Loading room: {} ({}x{})",
+        room.name,
+        room.width,
+        room.height
+    );
+
+    for entity in existing.iter() {
+        commands.entity(entity).despawn();
+    }
+    commands.spawn((
+        RoomLayout::from(&room),
+        Replicate::to_clients(NetworkTarget::All),
+    ));
+    commands.insert_resource(CollisionMap::new(&room));
+    commands.trigger(RoomLoaded { room: room.id });
+    commands.insert_resource(ActiveRoom(room));
+}
+
+/// Count every player as having entered a room once it is loaded.
+pub fn enter_loaded_room(
+    trigger: On<RoomLoaded>,
+    mut commands: Commands,
+    players: Query<Entity, With<PlayerId>>,
+) {
+    for player in players.iter() {
+        commands.trigger(RoomEntered {
+            player,
+            room: trigger.room,
+        });
+    }
+}
+
+/// Reload the active room when room content changes.
+pub fn reload_active_room(
+    trigger: On<ReloadContent>,
+    mut commands: Commands,
+    active: Option<Res<ActiveRoom>>,
+    rooms: Res<ContentRegistry<RoomDef>>,
+) {
+    if trigger.kind != RoomDef::KIND {
+        return;
+    }
+    // Generated rooms aren't in the registry and stay as they are
+    if let Some(active) = active.filter(|active| rooms.get(active.0.id).is_some()) {
+        // Triggered from here so it runs after the registry has been reloaded
+        commands.trigger(LoadRoom { room: active.0.id });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_app(rooms: &[RoomDef]) -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+
+        let mut registry = ContentRegistry::<RoomDef>::default();
+        registry.replace_all(rooms.iter().cloned());
+        app.insert_resource(registry);
+
+        app.add_observer(load_room);
+        app
+    }
+
+    #[test]
+    fn collision_map_queries_tiles() {
+        let mut room = RoomDef::new("Pit room", 3, 2);
+        room.set_tile(1, 0, TileKind::Pit);
+        room.set_tile(2, 1, TileKind::Wall);
+        let map = CollisionMap::new(&room);
+
+        assert_eq!(map.cell_at(Vec2::new(40.0, 10.0)), Some((1, 0)));
+        assert_eq!(map.cell_at(Vec2::new(-1.0, 10.0)), None);
+        assert!(map.is_walkable(Vec2::new(10.0, 10.0)));
+        assert!(!map.is_walkable(Vec2::new(40.0, 10.0)));
+        assert!(!map.is_walkable(Vec2::new(200.0, 10.0)));
+
+        // Pits stop walkers but not projectiles; walls stop both
+        assert!(!map.blocks_projectile(Vec2::new(40.0, 10.0)));
+        assert!(map.blocks_projectile(Vec2::new(80.0, 40.0)));
+
+        // A box flush against the pit doesn't collide, one overlapping it does
+        assert!(!map.blocks_box(Vec2::new(16.0, 48.0), Vec2::splat(16.0)));
+        assert!(map.blocks_box(Vec2::new(30.0, 16.0), Vec2::splat(8.0)));
+    }
+
+    #[test]
+    fn rays_stop_at_walls_but_cross_pits() {
+        let mut room = RoomDef::new("Corridor", 8, 3);
+        room.set_tile(3, 1, TileKind::Pit);
+        room.set_tile(6, 1, TileKind::Wall);
+        let map = CollisionMap::new(&room);
+
+        // One long step still hits the wall instead of tunnelling through it
+        let from = Vec2::new(16.0, 48.0);
+        let t = map.cast_ray(from, Vec2::new(250.0, 48.0)).unwrap();
+        assert!((from.x + t * 234.0 - 192.0).abs() < 1e-3);
+        assert_eq!(map.cast_ray(from, Vec2::new(150.0, 48.0)), None);
+
+        // Leaving the grid counts as a hit, starting inside a wall too
+        assert!(map.cast_ray(from, Vec2::new(16.0, 200.0)).is_some());
+        assert_eq!(map.cast_ray(Vec2::new(200.0, 48.0), from), Some(0.0));
+    }
+
+    #[test]
+    fn load_room_replaces_layout_and_collision() {
+        let first = RoomDef::new("First", 4, 4);
+        let mut second = RoomDef::new("Second", 2, 2);
+        second.set_tile(0, 0, TileKind::Wall);
+        let mut app = test_app(&[first.clone(), second.clone()]);
+
+        app.world_mut()
+            .commands()
+            .trigger(LoadRoom { room: first.id });
+        app.update();
+        assert_eq!(app.world().resource::<CollisionMap>().room, first.id);
+
+        app.world_mut()
+            .commands()
+            .trigger(LoadRoom { room: second.id });
+        app.update();
+
+        let mut layouts = app.world_mut().query::<&RoomLayout>();
+        let layouts: Vec<_> = layouts.iter(app.world()).collect();
+        assert_eq!(layouts.len(), 1);
+        assert_eq!(layouts[0].room, second.id);
+        assert_eq!(layouts[0].tile(0, 0), Some(TileKind::Wall));
+
+        let map = app.world().resource::<CollisionMap>();
+        assert_eq!(map.room, second.id);
+        assert!(!map.is_walkable(Vec2::new(1.0, 1.0)));
+    }
+}
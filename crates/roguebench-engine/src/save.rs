@@ -0,0 +1,76 @@
+//! Loading and writing per-player save data.
+
+use bevy::prelude::*;
+use roguebench_core::SaveData;
+use roguebench_protocol::QuestJournal;
+use roguebench_storage::ContentStoreExt;
+use uuid::Uuid;
+
+use crate::resources::Storage;
+use crate::rng::RunRng;
+
+/// ID of the save a player's progress is loaded from and written to.
+///
+/// Adding it to a player loads the save; players without one are never saved.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct SaveSlot(pub Uuid);
+
+/// Event requesting that a player's progress is written to storage.
+#[derive(Event, Debug, Clone)]
+pub struct SaveGame {
+    pub player: Entity,
+}
+
+/// Plugin for persisting player progress.
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_observer(load_save);
+        app.add_observer(write_save);
+    }
+}
+
+/// Restore a player's progress when a save slot is assigned.
+pub fn load_save(
+    trigger: On<Add, SaveSlot>,
+    mut commands: Commands,
+    storage: Res<Storage>,
+    slots: Query<&SaveSlot>,
+) {
+    let Ok(SaveSlot(id)) = slots.get(trigger.entity) else {
+        return;
+    };
+
+    let save = match storage.0.load::<SaveData>(*id) {
+        Ok(save) => save.unwrap_or_else(|| SaveData::new(*id)),
+        Err(e) => {
+            tracing::error!("Failed to load save {}: {}", id, e);
+            SaveData::new(*id)
+        }
+    };
+    commands
+        .entity(trigger.entity)
+        .insert(QuestJournal(save.quests));
+}
+
+/// Write a player's progress to storage.
+pub fn write_save(
+    trigger: On<SaveGame>,
+    storage: Res<Storage>,
+    rng: Option<Res<RunRng>>,
+    players: Query<(&SaveSlot, Option<&QuestJournal>)>,
+) {
+    let Ok((SaveSlot(id), journal)) = players.get(trigger.player) else {
+        return;
+    };
+
+    let save = SaveData {
+        id: *id,
+        seed: rng.map(|rng| rng.seed()),
+        quests: journal.map(|journal| journal.0.clone()).unwrap_or_default(),
+    };
+    if let Err(e) = storage.0.save(&save) {
+        tracing::error!("Failed to write save {}: {}", id, e);
+    }
+}
@@ -0,0 +1,712 @@
+//! Currencies, wallets and server-validated shop trades.
+
+use bevy::prelude::*;
+use lightyear::prelude::{ControlledBy, ControlledByRemote, MessageReceiver, MessageSender};
+use rand::seq::SliceRandom;
+use roguebench_core::{CurrencyDef, ItemDef, Restock, ShopDef, StockEntry};
+use roguebench_protocol::{
+    BuyItem, Inventory, Listing, ReliableChannel, SellItem, ShopStock, TRADE_RANGE, TradeError,
+    TradeRejected, Wallet,
+};
+use uuid::Uuid;
+
+use crate::content::{ContentAppExt, ContentRegistry};
+use crate::replication::replicate_to_owner_only;
+use crate::rng::{RngStream, RunRng};
+use crate::room::RoomLoaded;
+
+/// Shop an entity runs.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct ShopRef(pub Uuid);
+
+/// Seconds until a shop with an interval restock rolls its stock again.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct RestockTimer(pub f32);
+
+/// Event requesting that `player` buys units of a shop listing.
+#[derive(Event, Debug, Clone)]
+pub struct BuyFromShop {
+    pub player: Entity,
+    pub shop: Entity,
+    /// Index into the shop's [`ShopStock::listings`].
+    pub listing: usize,
+    /// Item the player expects the listing to offer.
+    pub item: Uuid,
+    /// Unit price the player expects to pay.
+    pub price: u32,
+    pub count: u32,
+}
+
+/// Event requesting that `player` sells items from their inventory to a shop.
+#[derive(Event, Debug, Clone)]
+pub struct SellToShop {
+    pub player: Entity,
+    pub shop: Entity,
+    pub item: Uuid,
+    pub count: u32,
+}
+
+/// Event fired after a player bought items.
+#[derive(Event, Debug, Clone)]
+pub struct ItemBought {
+    pub player: Entity,
+    pub shop: Entity,
+    pub item: Uuid,
+    pub count: u32,
+    /// Total paid.
+    pub cost: u32,
+}
+
+/// Event fired after a player sold items.
+#[derive(Event, Debug, Clone)]
+pub struct ItemSold {
+    pub player: Entity,
+    pub shop: Entity,
+    pub item: Uuid,
+    pub count: u32,
+    /// Total received.
+    pub payment: u32,
+}
+
+/// Event fired when a trade is turned down.
+#[derive(Event, Debug, Clone)]
+pub struct TradeFailed {
+    pub player: Entity,
+    pub reason: TradeError,
+}
+
+/// Plugin for currency and shop content, wallets and trading.
+pub struct ShopPlugin;
+
+impl Plugin for ShopPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_content::<CurrencyDef>();
+        app.register_content::<ShopDef>();
+
+        app.add_systems(Update, receive_shop_messages);
+        app.add_systems(FixedUpdate, restock_on_interval);
+
+        app.add_observer(stock_new_shop);
+        app.add_observer(restock_on_room_loaded);
+        app.add_observer(buy_from_shop);
+        app.add_observer(sell_to_shop);
+        app.add_observer(send_trade_rejected);
+        app.add_observer(fill_starting_wallet);
+        app.add_observer(replicate_to_owner_only::<Wallet>);
+    }
+}
+
+/// Roll a shop's stock from its definition.
+///
+/// Random entries draw distinct items from the catalogue using the loot stream.
+pub fn roll_stock(def: &ShopDef, items: &ContentRegistry<ItemDef>, rng: &mut RunRng) -> ShopStock {
+    let mut listings = Vec::new();
+    for entry in &def.stock {
+        match entry {
+            StockEntry::Item {
+                item,
+                quantity,
+                price,
+            } => {
+                let Some(item) = items.get(*item) else {
+                    tracing::warn!("Shop {} stocks unknown item {}", def.name, item);
+                    continue;
+                };
+                listings.push(Listing {
+                    item: item.id,
+                    price: price.unwrap_or_else(|| def.pricing.buy_price(item)),
+                    remaining: *quantity,
+                });
+            }
+            StockEntry::Random {
+                picks, quantity, ..
+            } => {
+                // Registry order isn't stable; sort so a seed always draws the same items
+                let mut candidates: Vec<&ItemDef> =
+                    items.iter().filter(|item| entry.matches(item)).collect();
+                candidates.sort_by_key(|item| item.id);
+                let drawn =
+                    candidates.choose_multiple(rng.stream(RngStream::Loot), *picks as usize);
+                listings.extend(drawn.map(|item| Listing {
+                    item: item.id,
+                    price: def.pricing.buy_price(item),
+                    remaining: *quantity,
+                }));
+            }
+        }
+    }
+    ShopStock {
+        shop: def.id,
+        currency: def.currency,
+        listings,
+    }
+}
+
+fn restock(
+    commands: &mut Commands,
+    entity: Entity,
+    def: &ShopDef,
+    items: &ContentRegistry<ItemDef>,
+    rng: &mut RunRng,
+) {
+    let mut shop = commands.entity(entity);
+    shop.insert(roll_stock(def, items, rng));
+    if let Restock::Interval { seconds } = def.restock {
+        shop.insert(RestockTimer(seconds));
+    }
+}
+
+/// Give shops their first stock when they appear.
+pub fn stock_new_shop(
+    trigger: On<Add, ShopRef>,
+    mut commands: Commands,
+    shops: Res<ContentRegistry<ShopDef>>,
+    items: Res<ContentRegistry<ItemDef>>,
+    mut rng: ResMut<RunRng>,
+    refs: Query<&ShopRef>,
+) {
+    let Ok(shop) = refs.get(trigger.entity) else {
+        return;
+    };
+    match shops.get(shop.0) {
+        Some(def) => restock(&mut commands, trigger.entity, def, &items, &mut rng),
+        None => tracing::warn!("Entity runs unknown shop {}", shop.0),
+    }
+}
+
+/// Roll stock again for shops whose restock interval has run out.
+pub fn restock_on_interval(
+    mut commands: Commands,
+    time: Res<Time>,
+    shops: Res<ContentRegistry<ShopDef>>,
+    items: Res<ContentRegistry<ItemDef>>,
+    mut rng: ResMut<RunRng>,
+    mut timers: Query<(Entity, &ShopRef, &mut RestockTimer)>,
+) {
+    for (entity, shop, mut timer) in timers.iter_mut() {
+        timer.0 -= time.delta_secs();
+        if timer.0 > 0.0 {
+            continue;
+        }
+        match shops.get(shop.0) {
+            Some(def) => restock(&mut commands, entity, def, &items, &mut rng),
+            None => {
+                commands.entity(entity).remove::<RestockTimer>();
+            }
+        }
+    }
+}
+
+/// Roll stock again for shops that restock every room.
+pub fn restock_on_room_loaded(
+    _trigger: On<RoomLoaded>,
+    mut commands: Commands,
+    shops: Res<ContentRegistry<ShopDef>>,
+    items: Res<ContentRegistry<ItemDef>>,
+    mut rng: ResMut<RunRng>,
+    refs: Query<(Entity, &ShopRef)>,
+) {
+    for (entity, shop) in refs.iter() {
+        let Some(def) = shops.get(shop.0) else {
+            continue;
+        };
+        if def.restock == Restock::EveryRoom {
+            restock(&mut commands, entity, def, &items, &mut rng);
+        }
+    }
+}
+
+/// Whether a player at `player` is close enough to trade with a shop at `shop`.
+fn within_trade_range(player: &Transform, shop: &Transform) -> bool {
+    player
+        .translation
+        .truncate()
+        .distance(shop.translation.truncate())
+        <= TRADE_RANGE
+}
+
+/// Buy from a shop if it has the stock, the player can pay and the items fit.
+///
+/// The listing must still offer the item at the price the player saw, and
+/// the player must be within [`TRADE_RANGE`] of the shop. Stock and wallets
+/// change before the next trade is handled, so two players buying the last
+/// unit at once can't both get it.
+pub fn buy_from_shop(
+    trigger: On<BuyFromShop>,
+    mut commands: Commands,
+    items: Res<ContentRegistry<ItemDef>>,
+    mut shops: Query<(&mut ShopStock, &Transform)>,
+    mut players: Query<(&mut Wallet, &mut Inventory, &Transform)>,
+) {
+    let BuyFromShop {
+        player,
+        shop,
+        listing,
+        item,
+        price,
+        count,
+    } = *trigger;
+    let Ok((mut wallet, mut inventory, at)) = players.get_mut(player) else {
+        return;
+    };
+    let mut fail = |reason| commands.trigger(TradeFailed { player, reason });
+
+    let Ok((mut stock, shop_at)) = shops.get_mut(shop) else {
+        return fail(TradeError::NotForSale);
+    };
+    if !within_trade_range(at, shop_at) {
+        return fail(TradeError::OutOfRange);
+    }
+    let currency = stock.currency;
+    let Some(on_sale) = stock.listings.get_mut(listing) else {
+        return fail(TradeError::NotForSale);
+    };
+    if on_sale.item != item || on_sale.price != price {
+        return fail(TradeError::ListingChanged);
+    }
+    let Some(item) = items.get(on_sale.item) else {
+        return fail(TradeError::NotForSale);
+    };
+    if count == 0 || on_sale.remaining.is_some_and(|remaining| remaining < count) {
+        return fail(TradeError::OutOfStock);
+    }
+    let Some(cost) = on_sale.price.checked_mul(count) else {
+        return fail(TradeError::InsufficientFunds);
+    };
+    if wallet.balance(currency) < cost {
+        return fail(TradeError::InsufficientFunds);
+    }
+    let mut after = inventory.clone();
+    if after.add(item, count) > 0 {
+        return fail(TradeError::InventoryFull);
+    }
+
+    wallet.withdraw(currency, cost);
+    *inventory = after;
+    if let Some(remaining) = on_sale.remaining.as_mut() {
+        *remaining -= count;
+    }
+    commands.trigger(ItemBought {
+        player,
+        shop,
+        item: item.id,
+        count,
+        cost,
+    });
+}
+
+/// Sell items from the player's inventory to a shop at the shop's sell price.
+///
+/// The player must be within [`TRADE_RANGE`] of the shop.
+pub fn sell_to_shop(
+    trigger: On<SellToShop>,
+    mut commands: Commands,
+    items: Res<ContentRegistry<ItemDef>>,
+    defs: Res<ContentRegistry<ShopDef>>,
+    shops: Query<(&ShopRef, &Transform)>,
+    mut players: Query<(&mut Wallet, &mut Inventory, &Transform)>,
+) {
+    let SellToShop {
+        player,
+        shop,
+        item,
+        count,
+    } = *trigger;
+    let Ok((mut wallet, mut inventory, at)) = players.get_mut(player) else {
+        return;
+    };
+    let mut fail = |reason| commands.trigger(TradeFailed { player, reason });
+
+    let Ok((shop_ref, shop_at)) = shops.get(shop) else {
+        return fail(TradeError::NotForSale);
+    };
+    let Some(def) = defs.get(shop_ref.0) else {
+        return fail(TradeError::NotForSale);
+    };
+    if !within_trade_range(at, shop_at) {
+        return fail(TradeError::OutOfRange);
+    }
+    let Some(item) = items.get(item) else {
+        return fail(TradeError::NotForSale);
+    };
+    if def.pricing.sell_ratio <= 0.0 {
+        return fail(TradeError::NotBuying);
+    }
+    if count == 0 || inventory.count(item.id) < count {
+        return fail(TradeError::NotOwned);
+    }
+
+    let payment = def.pricing.sell_price(item).saturating_mul(count);
+    inventory.remove(item.id, count);
+    wallet.deposit(def.currency, payment);
+    commands.trigger(ItemSold {
+        player,
+        shop,
+        item: item.id,
+        count,
+        payment,
+    });
+}
+
+/// Top new wallets up with every currency's starting amount.
+pub fn fill_starting_wallet(
+    trigger: On<Add, Wallet>,
+    currencies: Res<ContentRegistry<CurrencyDef>>,
+    mut wallets: Query<&mut Wallet>,
+) {
+    let Ok(mut wallet) = wallets.get_mut(trigger.entity) else {
+        return;
+    };
+    for currency in currencies.iter() {
+        if !wallet.0.contains_key(&currency.id) {
+            wallet.deposit(currency.id, currency.starting_amount);
+        }
+    }
+}
+
+/// Turn trade messages from clients into events for the players they control.
+pub fn receive_shop_messages(
+    mut commands: Commands,
+    mut links: Query<(
+        &ControlledByRemote,
+        &mut MessageReceiver<BuyItem>,
+        &mut MessageReceiver<SellItem>,
+    )>,
+    players: Query<(), With<Wallet>>,
+) {
+    for (controlled, mut buy_receiver, mut sell_receiver) in links.iter_mut() {
+        let Some(player) = controlled
+            .collection()
+            .iter()
+            .copied()
+            .find(|entity| players.contains(*entity))
+        else {
+            buy_receiver.receive().for_each(drop);
+            sell_receiver.receive().for_each(drop);
+            continue;
+        };
+
+        for BuyItem {
+            shop,
+            listing,
+            item,
+            price,
+            count,
+        } in buy_receiver.receive()
+        {
+            commands.trigger(BuyFromShop {
+                player,
+                shop,
+                listing,
+                item,
+                price,
+                count,
+            });
+        }
+        for SellItem { shop, item, count } in sell_receiver.receive() {
+            commands.trigger(SellToShop {
+                player,
+                shop,
+                item,
+                count,
+            });
+        }
+    }
+}
+
+/// Tell the client controlling the player why their trade was turned down.
+pub fn send_trade_rejected(
+    trigger: On<TradeFailed>,
+    players: Query<&ControlledBy>,
+    mut senders: Query<&mut MessageSender<TradeRejected>>,
+) {
+    let Ok(controlled_by) = players.get(trigger.player) else {
+        return;
+    };
+    if let Ok(mut sender) = senders.get_mut(controlled_by.owner) {
+        sender.send::<ReliableChannel>(TradeRejected {
+            reason: trigger.reason,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::time::TimeUpdateStrategy;
+    use roguebench_core::{Pricing, Rarity};
+    use std::time::Duration;
+
+    #[derive(Resource, Default)]
+    struct Log(Vec<String>);
+
+    struct Content {
+        gold: CurrencyDef,
+        potion: ItemDef,
+        shop: ShopDef,
+    }
+
+    fn content() -> Content {
+        let mut gold = CurrencyDef::new("Gold");
+        gold.starting_amount = 25;
+        let potion = ItemDef::new("Potion", 5);
+        let mut shop = ShopDef::new("Apothecary", gold.id, Pricing::new(10));
+        shop.stock.push(StockEntry::Item {
+            item: potion.id,
+            quantity: Some(3),
+            price: None,
+        });
+        Content { gold, potion, shop }
+    }
+
+    fn test_app(content: &Content, extra_items: &[ItemDef]) -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            100,
+        )));
+        let mut currencies = ContentRegistry::<CurrencyDef>::default();
+        currencies.insert(content.gold.clone());
+        app.insert_resource(currencies);
+        let mut items = ContentRegistry::<ItemDef>::default();
+        items.replace_all(extra_items.iter().cloned());
+        items.insert(content.potion.clone());
+        app.insert_resource(items);
+        let mut shops = ContentRegistry::<ShopDef>::default();
+        shops.insert(content.shop.clone());
+        app.insert_resource(shops);
+        app.insert_resource(RunRng::new(9));
+
+        app.add_systems(FixedUpdate, restock_on_interval);
+        app.add_observer(stock_new_shop);
+        app.add_observer(buy_from_shop);
+        app.add_observer(sell_to_shop);
+        app.add_observer(fill_starting_wallet);
+
+        app.init_resource::<Log>();
+        app.add_observer(|event: On<TradeFailed>, mut log: ResMut<Log>| {
+            log.0.push(format!("{:?}", event.reason));
+        });
+        app.add_observer(|event: On<ItemBought>, mut log: ResMut<Log>| {
+            log.0
+                .push(format!("bought {} for {}", event.count, event.cost));
+        });
+        app
+    }
+
+    fn spawn_player(app: &mut App, slots: usize) -> Entity {
+        app.world_mut()
+            .spawn((
+                Wallet::default(),
+                Inventory::new(slots),
+                Transform::default(),
+            ))
+            .id()
+    }
+
+    /// Buy from the first listing, as the player currently sees it.
+    fn buy(app: &mut App, player: Entity, shop: Entity, count: u32) {
+        app.world_mut().flush();
+        let listing = app.world().get::<ShopStock>(shop).unwrap().listings[0];
+        app.world_mut().commands().trigger(BuyFromShop {
+            player,
+            shop,
+            listing: 0,
+            item: listing.item,
+            price: listing.price,
+            count,
+        });
+    }
+
+    fn log(app: &mut App) -> Vec<String> {
+        std::mem::take(&mut app.world_mut().resource_mut::<Log>().0)
+    }
+
+    #[test]
+    fn trades_check_funds_stock_space_and_ownership() {
+        let content = content();
+        let mut app = test_app(&content, &[]);
+        let shop = app
+            .world_mut()
+            .spawn((ShopRef(content.shop.id), Transform::default()))
+            .id();
+        let player = spawn_player(&mut app, 1);
+        let gold = content.gold.id;
+
+        // Starts with 25 gold: two potions at 10 are fine, a third isn't
+        buy(&mut app, player, shop, 3);
+        buy(&mut app, player, shop, 2);
+        buy(&mut app, player, shop, 1);
+        app.world_mut().flush();
+        assert_eq!(
+            log(&mut app),
+            ["InsufficientFunds", "bought 2 for 20", "InsufficientFunds"]
+        );
+        assert_eq!(app.world().get::<Wallet>(player).unwrap().balance(gold), 5);
+
+        // Selling pays half price; selling what isn't owned is refused
+        app.world_mut().commands().trigger(SellToShop {
+            player,
+            shop,
+            item: content.potion.id,
+            count: 3,
+        });
+        app.world_mut().commands().trigger(SellToShop {
+            player,
+            shop,
+            item: content.potion.id,
+            count: 1,
+        });
+        app.world_mut().flush();
+        assert_eq!(log(&mut app), ["NotOwned"]);
+        assert_eq!(app.world().get::<Wallet>(player).unwrap().balance(gold), 10);
+
+        // One potion left in stock, and nowhere to put it without a free slot
+        app.world_mut()
+            .get_mut::<Wallet>(player)
+            .unwrap()
+            .deposit(gold, 100);
+        let no_room = spawn_player(&mut app, 0);
+        buy(&mut app, player, shop, 2);
+        buy(&mut app, no_room, shop, 1);
+        app.world_mut().flush();
+        let stock = app.world().get::<ShopStock>(shop).unwrap();
+        assert_eq!(stock.listings[0].remaining, Some(1));
+        assert_eq!(log(&mut app), ["OutOfStock", "InventoryFull"]);
+    }
+
+    #[test]
+    fn concurrent_purchases_only_sell_stock_once() {
+        let mut content = content();
+        content.shop.stock = vec![StockEntry::Item {
+            item: content.potion.id,
+            quantity: Some(1),
+            price: Some(5),
+        }];
+        let mut app = test_app(&content, &[]);
+        let shop = app
+            .world_mut()
+            .spawn((ShopRef(content.shop.id), Transform::default()))
+            .id();
+        let first = spawn_player(&mut app, 4);
+        let second = spawn_player(&mut app, 4);
+
+        // Both requests arrive in the same frame
+        buy(&mut app, first, shop, 1);
+        buy(&mut app, second, shop, 1);
+        app.update();
+
+        assert_eq!(log(&mut app), ["bought 1 for 5", "OutOfStock"]);
+        let gold = content.gold.id;
+        let wallet = |player| app.world().get::<Wallet>(player).unwrap().balance(gold);
+        assert_eq!((wallet(first), wallet(second)), (20, 25));
+        let potions = |player| {
+            let inventory = app.world().get::<Inventory>(player).unwrap();
+            inventory.count(content.potion.id)
+        };
+        assert_eq!((potions(first), potions(second)), (1, 0));
+    }
+
+    #[test]
+    fn random_stock_is_drawn_from_matching_items_and_restocks() {
+        let mut content = content();
+        let gems: Vec<ItemDef> = (0..4)
+            .map(|index| {
+                let mut gem = ItemDef::new(format!("Gem {index}"), 1);
+                gem.rarity = Rarity::Uncommon;
+                gem.tags.push("gem".to_string());
+                gem
+            })
+            .collect();
+        content.shop.stock = vec![StockEntry::Random {
+            tag: Some("gem".to_string()),
+            rarity: None,
+            picks: 2,
+            quantity: Some(1),
+        }];
+        content.shop.restock = Restock::Interval { seconds: 1.0 };
+        let mut app = test_app(&content, &gems);
+        let shop = app
+            .world_mut()
+            .spawn((ShopRef(content.shop.id), Transform::default()))
+            .id();
+        let player = spawn_player(&mut app, 4);
+        app.world_mut().flush();
+
+        let stock = app.world().get::<ShopStock>(shop).unwrap().clone();
+        assert_eq!(stock.listings.len(), 2);
+        assert_ne!(stock.listings[0].item, stock.listings[1].item);
+        assert!(stock.listings.iter().all(|listing| listing.price == 20));
+
+        app.world_mut()
+            .get_mut::<Wallet>(player)
+            .unwrap()
+            .deposit(content.gold.id, 100);
+        buy(&mut app, player, shop, 1);
+        app.world_mut().flush();
+        let sold_out = app.world().get::<ShopStock>(shop).unwrap();
+        assert_eq!(sold_out.listings[0].remaining, Some(0));
+
+        for _ in 0..12 {
+            app.update();
+        }
+        let restocked = app.world().get::<ShopStock>(shop).unwrap();
+        assert!(restocked.listings.iter().all(|l| l.remaining == Some(1)));
+    }
+
+    #[test]
+    fn trades_need_the_listing_seen_and_a_nearby_shop() {
+        let mut content = content();
+        let elixir = ItemDef::new("Elixir", 5);
+        content.shop.stock.push(StockEntry::Item {
+            item: elixir.id,
+            quantity: None,
+            price: Some(1),
+        });
+        let mut app = test_app(&content, std::slice::from_ref(&elixir));
+        let shop = app
+            .world_mut()
+            .spawn((ShopRef(content.shop.id), Transform::default()))
+            .id();
+        let player = spawn_player(&mut app, 4);
+        app.world_mut().flush();
+
+        // A listing that moved or was repriced since the player looked is refused
+        let stale = |item, price| BuyFromShop {
+            player,
+            shop,
+            listing: 0,
+            item,
+            price,
+            count: 1,
+        };
+        app.world_mut().commands().trigger(stale(elixir.id, 1));
+        app.world_mut()
+            .commands()
+            .trigger(stale(content.potion.id, 1));
+        app.world_mut().flush();
+        assert_eq!(log(&mut app), ["ListingChanged", "ListingChanged"]);
+        assert_eq!(
+            app.world()
+                .get::<Wallet>(player)
+                .unwrap()
+                .balance(content.gold.id),
+            25
+        );
+
+        // Shops can't be traded with from across the room
+        let far = Vec3::X * (TRADE_RANGE + 1.0);
+        app.world_mut()
+            .entity_mut(player)
+            .insert(Transform::from_translation(far));
+        buy(&mut app, player, shop, 1);
+        app.world_mut().commands().trigger(SellToShop {
+            player,
+            shop,
+            item: content.potion.id,
+            count: 1,
+        });
+        app.world_mut().flush();
+        assert_eq!(log(&mut app), ["OutOfRange", "OutOfRange"]);
+    }
+}
@@ -0,0 +1,110 @@
+//! Publishing the spatial state of replicated entities to clients.
+
+use bevy::prelude::*;
+use lightyear::prelude::Replicate;
+use roguebench_protocol::{Facing, Position, Projectile, Velocity};
+
+/// Plugin keeping replicated spatial components in step with the simulation.
+pub struct SpatialPlugin;
+
+impl Plugin for SpatialPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedPostUpdate, sync_spatial);
+    }
+}
+
+/// Copy the simulated transform of every replicated entity into its
+/// position, velocity and facing.
+///
+/// Gameplay moves entities through their `Transform`; these components are
+/// what clients see. Facing follows the direction of travel and is kept while
+/// standing still. Components are only written when their value changes, so
+/// entities at rest send no updates. Projectiles are left out, as clients
+/// extrapolate them from their launch state instead.
+#[allow(clippy::type_complexity)]
+pub fn sync_spatial(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut entities: Query<
+        (
+            Entity,
+            &Transform,
+            Option<&mut Position>,
+            Option<&mut Velocity>,
+            Option<&mut Facing>,
+        ),
+        (With<Replicate>, Without<Projectile>),
+    >,
+) {
+    let dt = time.delta_secs();
+    for (entity, transform, position, velocity, facing) in entities.iter_mut() {
+        let at = transform.translation.truncate();
+        let Some(mut position) = position else {
+            commands
+                .entity(entity)
+                .insert((Position(at), Velocity::default(), Facing::default()));
+            continue;
+        };
+
+        let moved = at - position.0;
+        let speed = if dt > 0.0 { moved / dt } else { Vec2::ZERO };
+        position.set_if_neq(Position(at));
+        if let Some(mut velocity) = velocity {
+            velocity.set_if_neq(Velocity(speed));
+        }
+        if let (Some(mut facing), Some(towards)) = (facing, Facing::towards(moved)) {
+            facing.set_if_neq(towards);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::time::TimeUpdateStrategy;
+    use lightyear::prelude::NetworkTarget;
+    use std::f32::consts::FRAC_PI_2;
+    use std::time::Duration;
+
+    #[test]
+    fn spatial_state_follows_the_transform() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        // One fixed tick per update
+        let tick = Duration::from_micros(15_625);
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(tick));
+        app.add_plugins(SpatialPlugin);
+        app.update();
+
+        let mover = app
+            .world_mut()
+            .spawn((
+                Transform::from_xyz(10.0, 20.0, 0.0),
+                Replicate::to_clients(NetworkTarget::All),
+            ))
+            .id();
+        app.update();
+        app.update();
+        let position = |app: &App| app.world().get::<Position>(mover).unwrap().0;
+        let velocity = |app: &App| app.world().get::<Velocity>(mover).unwrap().0;
+        let facing = |app: &App| app.world().get::<Facing>(mover).unwrap().0;
+        assert_eq!(position(&app), Vec2::new(10.0, 20.0));
+        assert_eq!(velocity(&app), Vec2::ZERO);
+
+        // Walking up for a tick
+        app.world_mut()
+            .get_mut::<Transform>(mover)
+            .unwrap()
+            .translation
+            .y += 2.0;
+        app.update();
+        assert_eq!(position(&app), Vec2::new(10.0, 22.0));
+        assert_eq!(velocity(&app), Vec2::new(0.0, 2.0 / tick.as_secs_f32()));
+        assert!((facing(&app) - FRAC_PI_2).abs() < 1e-6);
+
+        // Stopping keeps the facing but zeroes the velocity
+        app.update();
+        assert_eq!(velocity(&app), Vec2::ZERO);
+        assert!((facing(&app) - FRAC_PI_2).abs() < 1e-6);
+    }
+}
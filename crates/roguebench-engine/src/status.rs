@@ -0,0 +1,425 @@
+//! Status effects: applying, stacking, ticking and expiring.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use roguebench_core::{Stacking, Stat, StatusEffectDef};
+use roguebench_protocol::{ActiveStatus, Health, StatusEffects};
+use uuid::Uuid;
+
+use crate::combat::{ApplyDamage, MaxHealth};
+use crate::content::{ContentAppExt, ContentRegistry};
+
+/// Slack for timers landing exactly on a tick or expiry.
+const EPSILON: f32 = 1e-4;
+
+/// Most periodic ticks one effect runs per update; any backlog beyond is dropped.
+const MAX_TICKS_PER_UPDATE: u32 = 8;
+
+/// One copy of a status effect on an entity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActiveEffect {
+    /// ID of the [`StatusEffectDef`].
+    pub effect: Uuid,
+    /// The entity that applied the effect, credited with its damage.
+    pub source: Entity,
+    pub stacks: u32,
+    /// Seconds until the effect expires.
+    pub remaining: f32,
+    /// Seconds until the next periodic tick.
+    pub next_tick: f32,
+}
+
+/// Server-side state of the status effects on an entity.
+#[derive(Component, Debug, Clone, Default, PartialEq)]
+pub struct ActiveEffects(pub Vec<ActiveEffect>);
+
+/// Stat multipliers from an entity's status effects, recomputed whenever they change.
+#[derive(Component, Debug, Clone, Default, PartialEq)]
+pub struct StatMultipliers(HashMap<Stat, f32>);
+
+impl StatMultipliers {
+    /// Multiplier for a stat; 1 when no effect touches it.
+    pub fn get(&self, stat: Stat) -> f32 {
+        self.0.get(&stat).copied().unwrap_or(1.0)
+    }
+}
+
+/// Event requesting that a status effect is applied to an entity.
+#[derive(Event, Debug, Clone)]
+pub struct ApplyStatus {
+    pub target: Entity,
+    pub source: Entity,
+    /// ID of the [`StatusEffectDef`] to apply.
+    pub effect: Uuid,
+}
+
+/// Event fired when a status effect is applied, refreshed or stacked.
+#[derive(Event, Debug, Clone)]
+pub struct StatusApplied {
+    pub target: Entity,
+    pub effect: Uuid,
+    /// Stacks, or copies for independent effects, after applying.
+    pub stacks: u32,
+}
+
+/// Event fired when a copy of a status effect runs out.
+#[derive(Event, Debug, Clone)]
+pub struct StatusExpired {
+    pub target: Entity,
+    pub effect: Uuid,
+}
+
+/// Plugin for status effect content and simulation.
+pub struct StatusPlugin;
+
+impl Plugin for StatusPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_content::<StatusEffectDef>();
+
+        app.add_systems(FixedUpdate, tick_status_effects);
+        app.add_observer(apply_status);
+    }
+}
+
+/// Add a status effect to the target according to its stacking policy.
+pub fn apply_status(
+    trigger: On<ApplyStatus>,
+    mut commands: Commands,
+    defs: Res<ContentRegistry<StatusEffectDef>>,
+    mut targets: Query<Option<&mut ActiveEffects>, With<Health>>,
+) {
+    let Ok(existing) = targets.get_mut(trigger.target) else {
+        return;
+    };
+    let Some(def) = defs.get(trigger.effect) else {
+        tracing::warn!("Cannot apply unknown status effect {}", trigger.effect);
+        return;
+    };
+
+    let mut fresh = ActiveEffects::default();
+    let effects = match existing {
+        Some(effects) => effects.into_inner(),
+        None => &mut fresh,
+    };
+    let new_copy = ActiveEffect {
+        effect: def.id,
+        source: trigger.source,
+        stacks: 1,
+        remaining: def.duration,
+        next_tick: def.tick_interval,
+    };
+    let copies = effects
+        .0
+        .iter()
+        .filter(|active| active.effect == def.id)
+        .count();
+    let current = effects.0.iter_mut().find(|active| active.effect == def.id);
+
+    let stacks = match (def.stacking, current) {
+        (_, None) => {
+            effects.0.push(new_copy);
+            1
+        }
+        (Stacking::Refresh, Some(active)) => {
+            active.remaining = def.duration;
+            active.source = trigger.source;
+            active.stacks
+        }
+        (Stacking::Intensity, Some(active)) => {
+            active.stacks = (active.stacks + 1).min(def.max_stacks);
+            active.remaining = def.duration;
+            active.source = trigger.source;
+            active.stacks
+        }
+        (Stacking::Independent, Some(_)) if copies < def.max_stacks as usize => {
+            effects.0.push(new_copy);
+            copies as u32 + 1
+        }
+        (Stacking::Independent, Some(_)) => {
+            // At the limit: the copy closest to expiring starts over
+            if let Some(oldest) = effects
+                .0
+                .iter_mut()
+                .filter(|active| active.effect == def.id)
+                .min_by(|a, b| a.remaining.total_cmp(&b.remaining))
+            {
+                *oldest = new_copy;
+            }
+            copies as u32
+        }
+    };
+
+    sync_effects(&mut commands, trigger.target, effects, &defs);
+    if !fresh.0.is_empty() {
+        commands.entity(trigger.target).insert(fresh);
+    }
+    commands.trigger(StatusApplied {
+        target: trigger.target,
+        effect: def.id,
+        stacks,
+    });
+}
+
+/// Count down every status effect, running periodic ticks and removing expired copies.
+pub fn tick_status_effects(
+    mut commands: Commands,
+    time: Res<Time>,
+    defs: Res<ContentRegistry<StatusEffectDef>>,
+    mut targets: Query<(Entity, &mut ActiveEffects)>,
+    mut healths: Query<(&mut Health, Option<&MaxHealth>)>,
+) {
+    let dt = time.delta_secs();
+    for (entity, mut effects) in targets.iter_mut() {
+        let mut expired = Vec::new();
+        let before = effects.0.len();
+        effects.0.retain_mut(|active| {
+            // Definitions removed by a reload end their effects
+            let Some(def) = defs.get(active.effect) else {
+                return false;
+            };
+            active.remaining -= dt;
+            if def.tick_interval > 0.0 {
+                active.next_tick -= dt;
+                let mut ticks = 0;
+                while active.next_tick <= EPSILON {
+                    // Unvalidated intervals can be too small to ever catch up
+                    if ticks == MAX_TICKS_PER_UPDATE {
+                        active.next_tick = def.tick_interval;
+                        break;
+                    }
+                    ticks += 1;
+                    active.next_tick += def.tick_interval;
+                    let stacks = i32::try_from(active.stacks).unwrap_or(i32::MAX);
+                    let amount = def.tick_damage.saturating_mul(stacks);
+                    if amount > 0 {
+                        commands.trigger(ApplyDamage {
+                            target: entity,
+                            source: active.source,
+                            amount,
+                        });
+                    } else if amount < 0 {
+                        heal(&mut healths, entity, amount.saturating_neg());
+                    }
+                }
+            }
+            if active.remaining <= EPSILON {
+                expired.push(active.effect);
+                return false;
+            }
+            true
+        });
+
+        if effects.0.len() == before {
+            continue;
+        }
+        sync_effects(&mut commands, entity, &effects, &defs);
+        for effect in expired {
+            commands.trigger(StatusExpired {
+                target: entity,
+                effect,
+            });
+        }
+    }
+}
+
+fn heal(healths: &mut Query<(&mut Health, Option<&MaxHealth>)>, entity: Entity, amount: i32) {
+    let Ok((mut health, max)) = healths.get_mut(entity) else {
+        return;
+    };
+    // The dead stay dead
+    if health.0 <= 0 {
+        return;
+    }
+    let cap = max.map_or(i32::MAX, |max| max.0);
+    health.0 = health.0.saturating_add(amount).min(cap.max(health.0));
+}
+
+/// Refresh the replicated view and stat multipliers after the effects changed.
+fn sync_effects(
+    commands: &mut Commands,
+    entity: Entity,
+    effects: &ActiveEffects,
+    defs: &ContentRegistry<StatusEffectDef>,
+) {
+    let mut multipliers = HashMap::new();
+    for active in &effects.0 {
+        let Some(def) = defs.get(active.effect) else {
+            continue;
+        };
+        for modifier in &def.modifiers {
+            *multipliers.entry(modifier.stat).or_insert(1.0) *=
+                modifier.multiplier.powi(active.stacks as i32);
+        }
+    }
+    let statuses = effects
+        .0
+        .iter()
+        .map(|active| ActiveStatus {
+            effect: active.effect,
+            stacks: active.stacks,
+            remaining: active.remaining,
+        })
+        .collect();
+    commands
+        .entity(entity)
+        .insert((StatusEffects(statuses), StatMultipliers(multipliers)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::time::TimeUpdateStrategy;
+    use roguebench_core::{FactionDef, StatModifier};
+    use std::time::Duration;
+
+    use crate::combat::apply_damage;
+
+    #[derive(Resource, Default)]
+    struct Log(Vec<String>);
+
+    fn test_app(effects: &[StatusEffectDef]) -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            100,
+        )));
+        let mut registry = ContentRegistry::<StatusEffectDef>::default();
+        registry.replace_all(effects.iter().cloned());
+        app.insert_resource(registry);
+        app.init_resource::<ContentRegistry<FactionDef>>();
+
+        app.add_systems(FixedUpdate, tick_status_effects);
+        app.add_observer(apply_status);
+        app.add_observer(apply_damage);
+
+        app.init_resource::<Log>();
+        app.add_observer(|event: On<StatusApplied>, mut log: ResMut<Log>| {
+            log.0.push(format!("applied x{}", event.stacks));
+        });
+        app.add_observer(|_: On<StatusExpired>, mut log: ResMut<Log>| {
+            log.0.push("expired".to_string());
+        });
+        app.update();
+        app
+    }
+
+    fn apply(app: &mut App, target: Entity, effect: &StatusEffectDef) {
+        app.world_mut().commands().trigger(ApplyStatus {
+            target,
+            source: target,
+            effect: effect.id,
+        });
+        app.world_mut().flush();
+    }
+
+    fn statuses(app: &App, target: Entity) -> Vec<ActiveStatus> {
+        app.world().get::<StatusEffects>(target).unwrap().0.clone()
+    }
+
+    #[test]
+    fn tiny_tick_intervals_and_huge_damage_stay_bounded() {
+        // Neither passes validation, but stale or hand-made content can still get here
+        let mut frenzy = StatusEffectDef::new("Frenzy", 0.5);
+        frenzy.tick_interval = 1e-10;
+        frenzy.tick_damage = 1;
+        let mut overkill = StatusEffectDef::new("Overkill", 1.0);
+        overkill.tick_interval = 0.5;
+        overkill.tick_damage = i32::MIN;
+        overkill.stacking = Stacking::Intensity;
+        overkill.max_stacks = 2;
+        let mut app = test_app(&[frenzy.clone(), overkill.clone()]);
+        let target = app.world_mut().spawn(Health(1000)).id();
+
+        apply(&mut app, target, &frenzy);
+        app.update();
+        let health = app.world().get::<Health>(target).unwrap().0;
+        // 100ms covers at most seven fixed ticks
+        assert!(health < 1000 && health >= 1000 - 7 * MAX_TICKS_PER_UPDATE as i32);
+
+        // Two stacks of the most negative damage heal without overflowing
+        apply(&mut app, target, &overkill);
+        apply(&mut app, target, &overkill);
+        for _ in 0..10 {
+            app.update();
+        }
+        assert!(app.world().get::<Health>(target).unwrap().0 >= health);
+    }
+
+    #[test]
+    fn intensity_stacks_tick_damage_and_expire() {
+        let mut poison = StatusEffectDef::new("Poison", 3.0);
+        poison.tick_interval = 1.0;
+        poison.tick_damage = 2;
+        poison.stacking = Stacking::Intensity;
+        poison.max_stacks = 2;
+        let mut app = test_app(std::slice::from_ref(&poison));
+        let target = app.world_mut().spawn(Health(50)).id();
+
+        for _ in 0..3 {
+            apply(&mut app, target, &poison);
+        }
+        let replicated = statuses(&app, target);
+        assert_eq!(replicated.len(), 1);
+        assert_eq!(replicated[0].stacks, 2);
+        assert_eq!(replicated[0].remaining, 3.0);
+
+        for _ in 0..40 {
+            app.update();
+        }
+        // Three ticks of two damage per stack
+        assert_eq!(app.world().get::<Health>(target).unwrap().0, 50 - 12);
+        assert!(statuses(&app, target).is_empty());
+        assert_eq!(
+            app.world().resource::<Log>().0,
+            ["applied x1", "applied x2", "applied x2", "expired"]
+        );
+    }
+
+    #[test]
+    fn refresh_independent_and_stat_modifiers() {
+        let mut vulnerable = StatusEffectDef::new("Vulnerable", 2.0);
+        vulnerable.modifiers = vec![StatModifier {
+            stat: Stat::DamageTaken,
+            multiplier: 2.0,
+        }];
+        let mut regen = StatusEffectDef::new("Regen", 1.0);
+        regen.tick_interval = 0.25;
+        regen.tick_damage = -1;
+        regen.stacking = Stacking::Independent;
+        regen.max_stacks = 2;
+        let mut app = test_app(&[vulnerable.clone(), regen.clone()]);
+        let target = app.world_mut().spawn((Health(20), MaxHealth(30))).id();
+
+        apply(&mut app, target, &vulnerable);
+        app.update();
+        apply(&mut app, target, &vulnerable);
+        let refreshed = statuses(&app, target);
+        assert_eq!(refreshed.len(), 1);
+        assert_eq!(refreshed[0].remaining, 2.0);
+
+        let multipliers = app.world().get::<StatMultipliers>(target).unwrap();
+        assert_eq!(multipliers.get(Stat::DamageTaken), 2.0);
+        assert_eq!(multipliers.get(Stat::MoveSpeed), 1.0);
+        let source = app.world_mut().spawn_empty().id();
+        app.world_mut().commands().trigger(ApplyDamage {
+            target,
+            source,
+            amount: 5,
+        });
+        app.world_mut().flush();
+        assert_eq!(app.world().get::<Health>(target).unwrap().0, 10);
+
+        // Two copies run side by side; a third restarts the older one
+        for _ in 0..3 {
+            apply(&mut app, target, &regen);
+        }
+        assert_eq!(statuses(&app, target).len(), 3);
+        for _ in 0..25 {
+            app.update();
+        }
+        // Four ticks per copy, healing from 10
+        assert_eq!(app.world().get::<Health>(target).unwrap().0, 18);
+        assert!(statuses(&app, target).is_empty());
+    }
+}
@@ -1,29 +1,82 @@
 //! Bevy systems for the engine.
 
 use bevy::prelude::*;
+use std::time::Duration;
+
 use lightyear::prelude::server::{
-    NetcodeConfig, NetcodeServer, ServerUdpIo, Start as LightyearStart,
+    ClientOf, NetcodeConfig, NetcodeServer, ServerUdpIo, Start as LightyearStart,
+};
+use lightyear::prelude::{
+    Connected, InterpolationTarget, Link, LinkOf, LocalAddr, NetworkTarget, PeerAddr, PeerId,
+    Replicate, ReplicationSender, SendUpdatesMode,
 };
-use lightyear::prelude::{Link, LocalAddr, Replicate};
-use roguebench_protocol::{EditorMessage, EntityName, Health};
+use roguebench_core::{AiState, EntityDef, StateMachine};
+use roguebench_protocol::{
+    ContentChange, Definitions, ENTITY_KIND, EditorMessage, EntityName, Health, ProtocolHash,
+    TemplateId, VersionMismatch,
+};
+
+use crate::ai::AiBrain;
+use crate::combat::{Hitbox, MaxHealth};
+use crate::content::ReloadContent;
+use crate::definitions::DefinitionsPublished;
+use crate::dialogue::DialogueRef;
+use crate::faction::Team;
+use crate::fsm::Fsm;
+use crate::player::{PlayerIdentities, PlayerSettings};
+use crate::progression::PlayerId;
+use crate::resources::{EditorReceiver, EntityTemplates, ServerAddr, ServerKey, Storage};
+use crate::shop::ShopRef;
+use crate::weapon::Weapon;
 
-use crate::resources::{EditorReceiver, ServerAddr, Storage};
+/// How often replication updates are sent to each client.
+const REPLICATION_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Most clients turned away per second, across all addresses.
+///
+/// Requests are easily sent from spoofed addresses, so replies and their log
+/// lines are capped rather than sent to whoever asks.
+const TURN_AWAYS_PER_SECOND: u32 = 8;
 
 /// Event triggered when entities should be reloaded from storage.
 #[derive(Event, Message)]
 pub struct ReloadEntities;
 
+/// Event triggered when the editor changed one entity definition.
+#[derive(Event, Debug, Clone)]
+pub struct TemplateChanged {
+    pub id: uuid::Uuid,
+    pub change: ContentChange,
+}
+
 /// Marker component for entities spawned from definitions.
 #[derive(Component)]
 pub struct SpawnedEntity;
 
+/// ID of the [`EntityDef`](roguebench_core::EntityDef) an entity was spawned from.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct EntityTemplate(pub uuid::Uuid);
+
 /// Spawn the Lightyear server.
-pub fn spawn_server(mut commands: Commands, server_addr: Res<ServerAddr>) {
-    tracing::info!("Spawning Lightyear server on {}", server_addr.0);
+pub fn spawn_server(
+    mut commands: Commands,
+    server_addr: Res<ServerAddr>,
+    key: Res<ServerKey>,
+    protocol: Res<ProtocolHash>,
+) {
+    tracing::info!(
+        "Spawning Lightyear server on {} (protocol {})",
+        server_addr.0,
+        *protocol
+    );
 
     let server = commands
         .spawn((
-            NetcodeServer::new(NetcodeConfig::default()),
+            NetcodeServer::new(
+                NetcodeConfig::default()
+                    .with_protocol_id(protocol.0)
+                    .with_key(key.0),
+            ),
             LocalAddr(server_addr.0),
             ServerUdpIo::default(),
         ))
@@ -32,27 +85,162 @@ pub fn spawn_server(mut commands: Commands, server_addr: Res<ServerAddr>) {
     commands.trigger(LightyearStart { entity: server });
 }
 
+/// Reply owed to a client that asked to connect with another protocol.
+#[derive(Component, Debug)]
+pub struct TurnAway(VersionMismatch);
+
+/// Clients turned away during the current second.
+#[derive(Debug, Default)]
+pub struct TurnAwayBudget {
+    second: u64,
+    used: u32,
+}
+
+/// Catch connection requests made with another protocol, which netcode
+/// would silently drop.
+///
+/// Runs on raw packets, so the client can be told why instead of timing out.
+/// Past [`TURN_AWAYS_PER_SECOND`] such requests are dropped without a reply.
+#[allow(clippy::type_complexity)]
+pub fn turn_away_mismatched_clients(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    protocol: Res<ProtocolHash>,
+    mut budget: Local<TurnAwayBudget>,
+    mut links: Query<(Entity, &mut Link, Option<&PeerAddr>), (With<LinkOf>, Without<ClientOf>)>,
+) {
+    let second = time.elapsed().as_secs();
+    if budget.second != second {
+        *budget = TurnAwayBudget { second, used: 0 };
+    }
+    for (entity, mut link, addr) in links.iter_mut() {
+        let packets: Vec<_> = link.recv.drain().collect();
+        for packet in packets {
+            match ProtocolHash::requested_by(&packet) {
+                Some(requested) if requested != *protocol => {
+                    if budget.used >= TURN_AWAYS_PER_SECOND {
+                        continue;
+                    }
+                    budget.used += 1;
+                    let mismatch = VersionMismatch {
+                        client: requested,
+                        server: *protocol,
+                    };
+                    tracing::warn!("Turning away client {:?}: {}", addr.map(|a| a.0), mismatch);
+                    commands.entity(entity).insert(TurnAway(mismatch));
+                }
+                _ => link.recv.push_raw(packet),
+            }
+        }
+    }
+}
+
+/// Tell turned away clients about the mismatch.
+///
+/// Netcode clears whatever else is queued for clients that aren't connected,
+/// so this runs after it.
+pub fn send_version_mismatches(
+    mut commands: Commands,
+    mut links: Query<(Entity, &mut Link, &TurnAway)>,
+) {
+    for (entity, mut link, TurnAway(mismatch)) in links.iter_mut() {
+        link.send(mismatch.to_packet().into());
+        commands.entity(entity).remove::<TurnAway>();
+    }
+}
+
 /// Trigger initial entity load at startup.
 pub fn initial_load(mut commands: Commands) {
     commands.trigger(ReloadEntities);
 }
 
 /// Check for messages from the editor and dispatch events.
-pub fn check_editor_messages(mut editor_rx: ResMut<EditorReceiver>, mut commands: Commands) {
+pub fn check_editor_messages(
+    mut editor_rx: ResMut<EditorReceiver>,
+    mut identities: ResMut<PlayerIdentities>,
+    time: Res<Time<Real>>,
+    mut commands: Commands,
+) {
     while let Ok(message) = editor_rx.0.try_recv() {
         match message {
-            EditorMessage::ReloadEntities => {
-                commands.trigger(ReloadEntities);
+            EditorMessage::ContentChanged { kind, id, change } if kind == ENTITY_KIND => {
+                commands.trigger(TemplateChanged { id, change });
+            }
+            EditorMessage::ContentChanged { kind, .. } => {
+                commands.trigger(ReloadContent { kind });
+            }
+            EditorMessage::PlayerAuthorized { client_id, player } => {
+                identities.authorize(PeerId::Netcode(client_id), player, time.elapsed());
             }
         }
     }
 }
 
+/// Spawn a replicated entity from its definition.
+pub fn spawn_from_template<'a>(
+    commands: &'a mut Commands,
+    entity_def: &EntityDef,
+) -> EntityCommands<'a> {
+    let mut entity = commands.spawn((
+        EntityTemplate(entity_def.id),
+        Health(entity_def.health),
+        Hitbox::default(),
+        Replicate::to_clients(NetworkTarget::All),
+        InterpolationTarget::to_clients(NetworkTarget::All),
+    ));
+    apply_template(&mut entity, entity_def, None);
+    entity
+}
+
+/// Bring the components an entity takes from its definition in line with it.
+///
+/// `previous` is the definition the entity was built from, if any; an AI
+/// whose profile didn't change keeps its state but follows the new health.
+fn apply_template(
+    entity: &mut EntityCommands,
+    entity_def: &EntityDef,
+    previous: Option<&EntityDef>,
+) {
+    entity.insert((
+        EntityName(entity_def.name.clone()),
+        MaxHealth(entity_def.health),
+    ));
+    match entity_def.dialogue {
+        Some(dialogue) => entity.insert(DialogueRef(dialogue)),
+        None => entity.remove::<DialogueRef>(),
+    };
+    match entity_def.shop {
+        Some(shop) => entity.insert(ShopRef(shop)),
+        None => entity.remove::<ShopRef>(),
+    };
+    match entity_def.faction {
+        Some(faction) => entity.insert(Team(faction)),
+        None => entity.remove::<Team>(),
+    };
+    if previous.is_some_and(|previous| previous.ai == entity_def.ai) {
+        let max_health = entity_def.health;
+        entity
+            .entry::<AiBrain>()
+            .and_modify(move |mut brain| brain.max_health = max_health);
+        return;
+    }
+    match &entity_def.ai {
+        Some(profile) => entity.insert((
+            AiBrain::new(profile.clone(), entity_def.health),
+            Fsm(StateMachine::new(AiState::Idle)),
+        )),
+        None => entity.remove::<(AiBrain, Fsm<AiState>)>(),
+    };
+}
+
 /// Reload entities from storage when triggered.
 pub fn reload_entities(
     _trigger: On<ReloadEntities>,
     mut commands: Commands,
     storage: Res<Storage>,
+    mut templates: ResMut<EntityTemplates>,
+    mut definitions: ResMut<Definitions>,
+    settings: Res<PlayerSettings>,
     existing: Query<Entity, With<SpawnedEntity>>,
 ) {
     tracing::info!("Reloading entities from storage");
@@ -65,18 +253,26 @@ pub fn reload_entities(
     // Load and spawn new entities
     match storage.0.load_entities() {
         Ok(entities) => {
-            for entity_def in entities {
+            // Players are built from their template when clients connect
+            let world = entities
+                .iter()
+                .filter(|entity_def| settings.template != Some(entity_def.id));
+            for entity_def in world {
                 tracing::info!(
                     "Spawning entity: {} (health: {})",
                     entity_def.name,
                     entity_def.health
                 );
-                commands.spawn((
-                    SpawnedEntity,
-                    EntityName(entity_def.name),
-                    Health(entity_def.health),
-                    Replicate::default(),
-                ));
+                spawn_from_template(&mut commands, entity_def)
+                    .insert((SpawnedEntity, TemplateId::of(entity_def)));
+            }
+            templates.0 = entities
+                .into_iter()
+                .map(|entity_def| (entity_def.id, entity_def))
+                .collect();
+            let defs = templates.0.iter().map(|(id, def)| (*id, def));
+            if let Some(delta) = definitions.publish(ENTITY_KIND, defs) {
+                commands.trigger(DefinitionsPublished(delta));
             }
         }
         Err(e) => {
@@ -85,6 +281,113 @@ pub fn reload_entities(
     }
 }
 
+/// Patch the entities spawned from a changed definition, keeping their
+/// network identity and live state.
+///
+/// New definitions spawn their entity, edited ones update it in place and
+/// deleted ones despawn it. Players built from the definition are updated
+/// too, but never spawned or despawned here.
+#[allow(clippy::type_complexity)]
+pub fn patch_entities(
+    trigger: On<TemplateChanged>,
+    mut commands: Commands,
+    storage: Res<Storage>,
+    mut templates: ResMut<EntityTemplates>,
+    mut definitions: ResMut<Definitions>,
+    settings: Res<PlayerSettings>,
+    spawned: Query<
+        (
+            Entity,
+            &EntityTemplate,
+            &Health,
+            &MaxHealth,
+            Has<SpawnedEntity>,
+        ),
+        Or<(With<SpawnedEntity>, With<PlayerId>)>,
+    >,
+) {
+    let id = trigger.id;
+    let entity_def = match trigger.change {
+        ContentChange::Deleted => None,
+        ContentChange::Created | ContentChange::Updated => match storage.0.load_entity(id) {
+            Ok(entity_def) => entity_def,
+            Err(e) => {
+                tracing::error!("Failed to load entity {}: {}", id, e);
+                return;
+            }
+        },
+    };
+    let previous = templates.0.remove(&id);
+    let existing: Vec<_> = spawned
+        .iter()
+        .filter(|(_, template, ..)| template.0 == id)
+        .map(|(entity, _, health, max, world)| (entity, health.0, max.0, world))
+        .collect();
+
+    match &entity_def {
+        None => {
+            tracing::info!("Despawning entities of deleted definition {}", id);
+            for (entity, .., world) in existing {
+                if world {
+                    commands.entity(entity).despawn();
+                }
+            }
+        }
+        // Players are built from their template when clients connect
+        Some(_) if existing.is_empty() && settings.template == Some(id) => {}
+        Some(entity_def) if existing.is_empty() => {
+            tracing::info!("Spawning entity: {}", entity_def.name);
+            spawn_from_template(&mut commands, entity_def)
+                .insert((SpawnedEntity, TemplateId::of(entity_def)));
+        }
+        Some(entity_def) => {
+            tracing::info!("Updating entities of {} in place", entity_def.name);
+            let weapon = previous.as_ref().and_then(|previous| previous.weapon);
+            for (entity, health, max, _) in existing {
+                // Unhurt entities stay at full health; damage taken carries over
+                let health = if health >= max {
+                    entity_def.health
+                } else {
+                    health.min(entity_def.health)
+                };
+                let mut entity = commands.entity(entity);
+                apply_template(&mut entity, entity_def, previous.as_ref());
+                entity.insert((Health(health), TemplateId::of(entity_def)));
+                if entity_def.weapon != weapon {
+                    // Inserting the template again equips the new weapon
+                    entity.remove::<Weapon>().insert(EntityTemplate(id));
+                }
+            }
+        }
+    }
+
+    if let Some(entity_def) = entity_def {
+        templates.0.insert(id, entity_def);
+    }
+    let defs = templates.0.iter().map(|(id, def)| (*id, def));
+    if let Some(delta) = definitions.publish(ENTITY_KIND, defs) {
+        commands.trigger(DefinitionsPublished(delta));
+    }
+}
+
+/// Start replicating the world to clients once they have connected.
+pub fn start_replication(
+    trigger: On<Add, Connected>,
+    mut commands: Commands,
+    clients: Query<(), With<ClientOf>>,
+) {
+    if !clients.contains(trigger.entity) {
+        return;
+    }
+    commands
+        .entity(trigger.entity)
+        .insert(ReplicationSender::new(
+            REPLICATION_INTERVAL,
+            SendUpdatesMode::SinceLastAck,
+            false,
+        ));
+}
+
 /// Log new connections.
 pub fn log_connections(trigger: On<Add, Link>) {
     tracing::info!("New link added: {:?}", trigger.entity);
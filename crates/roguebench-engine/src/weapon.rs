@@ -0,0 +1,278 @@
+//! Weapons: firing, cooldowns and reloading.
+
+use bevy::prelude::*;
+use rand::Rng;
+use roguebench_core::{Stat, WeaponDef};
+use roguebench_protocol::Projectile;
+use uuid::Uuid;
+
+use crate::content::{ContentAppExt, ContentRegistry};
+use crate::projectile::{ProjectileFlight, spawn_projectile};
+use crate::resources::EntityTemplates;
+use crate::rng::{RngStream, RunRng};
+use crate::status::StatMultipliers;
+use crate::systems::EntityTemplate;
+
+/// A weapon held by an entity.
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct Weapon {
+    /// ID of the [`WeaponDef`] the weapon fires as.
+    pub def: Uuid,
+    /// Shots left in the magazine; `None` for unlimited ammo.
+    pub ammo: Option<u32>,
+    /// Seconds until the next shot is allowed.
+    pub cooldown: f32,
+    /// Seconds until an empty magazine is refilled.
+    pub reload: f32,
+}
+
+impl Weapon {
+    pub fn new(def: &WeaponDef) -> Self {
+        Self {
+            def: def.id,
+            ammo: def.ammo,
+            cooldown: 0.0,
+            reload: 0.0,
+        }
+    }
+
+    /// Whether the weapon can fire right now.
+    pub fn is_ready(&self) -> bool {
+        self.cooldown <= 0.0 && self.ammo != Some(0)
+    }
+}
+
+/// Event requesting that an entity fires its weapon.
+#[derive(Event, Debug, Clone)]
+pub struct FireWeapon {
+    pub shooter: Entity,
+    /// Direction to aim in; doesn't need to be normalized.
+    pub direction: Vec2,
+}
+
+/// Event fired when a weapon shoots.
+#[derive(Event, Debug, Clone)]
+pub struct WeaponFired {
+    pub shooter: Entity,
+    /// ID of the [`WeaponDef`] that was fired.
+    pub weapon: Uuid,
+}
+
+/// Plugin for weapon content and firing.
+pub struct WeaponPlugin;
+
+impl Plugin for WeaponPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_content::<WeaponDef>();
+
+        app.add_systems(FixedUpdate, tick_weapons);
+        app.add_observer(fire_weapon);
+        app.add_observer(equip_template_weapon);
+    }
+}
+
+/// Give entities spawned from a template the weapon their definition names.
+///
+/// Runs again whenever the template is inserted, re-equipping after edits.
+pub fn equip_template_weapon(
+    trigger: On<Insert, EntityTemplate>,
+    mut commands: Commands,
+    templates: Res<EntityTemplates>,
+    weapons: Res<ContentRegistry<WeaponDef>>,
+    spawned: Query<&EntityTemplate>,
+) {
+    let Ok(template) = spawned.get(trigger.entity) else {
+        return;
+    };
+    let Some(weapon) = templates.0.get(&template.0).and_then(|def| def.weapon) else {
+        return;
+    };
+    match weapons.get(weapon) {
+        Some(def) => {
+            commands.entity(trigger.entity).insert(Weapon::new(def));
+        }
+        None => tracing::warn!("Cannot equip unknown weapon {}", weapon),
+    }
+}
+
+/// Fire the shooter's weapon if it is ready, spawning its projectiles.
+pub fn fire_weapon(
+    trigger: On<FireWeapon>,
+    mut commands: Commands,
+    weapons: Res<ContentRegistry<WeaponDef>>,
+    mut rng: ResMut<RunRng>,
+    mut shooters: Query<(&mut Weapon, &Transform, Option<&StatMultipliers>)>,
+) {
+    let Ok((mut weapon, transform, multipliers)) = shooters.get_mut(trigger.shooter) else {
+        return;
+    };
+    // Stunned
+    if multipliers.is_some_and(|m| m.get(Stat::AttackSpeed) <= 0.0) {
+        return;
+    }
+    let Some(def) = weapons.get(weapon.def) else {
+        tracing::warn!("Cannot fire unknown weapon {}", weapon.def);
+        return;
+    };
+    let Some(aim) = trigger.direction.try_normalize() else {
+        return;
+    };
+    if !weapon.is_ready() {
+        return;
+    }
+
+    weapon.cooldown = def.cooldown();
+    if let Some(ammo) = weapon.ammo.as_mut() {
+        *ammo -= 1;
+        if *ammo == 0 {
+            weapon.reload = def.reload_time;
+        }
+    }
+
+    // Several projectiles fan out evenly; a single one strays randomly within the cone
+    let spread = def.spread.to_radians();
+    let count = def.projectile_count.max(1);
+    let origin = transform.translation.truncate();
+    for index in 0..count {
+        let angle = if count > 1 {
+            spread * (index as f32 / (count - 1) as f32 - 0.5)
+        } else if spread > 0.0 {
+            rng.stream(RngStream::Combat)
+                .gen_range(-spread / 2.0..=spread / 2.0)
+        } else {
+            0.0
+        };
+        spawn_projectile(
+            &mut commands,
+            Projectile {
+                origin,
+                velocity: Vec2::from_angle(angle).rotate(aim) * def.speed,
+            },
+            ProjectileFlight {
+                shooter: trigger.shooter,
+                damage: def.damage,
+                radius: def.projectile_radius,
+                remaining: def.range,
+            },
+        );
+    }
+
+    commands.trigger(WeaponFired {
+        shooter: trigger.shooter,
+        weapon: def.id,
+    });
+}
+
+/// Count down cooldowns and refill empty magazines once reloaded.
+///
+/// Cooldowns run faster or slower with the holder's attack speed.
+pub fn tick_weapons(
+    time: Res<Time>,
+    defs: Res<ContentRegistry<WeaponDef>>,
+    mut weapons: Query<(&mut Weapon, Option<&StatMultipliers>)>,
+) {
+    let dt = time.delta_secs();
+    for (mut weapon, multipliers) in weapons.iter_mut() {
+        let attack_speed = multipliers.map_or(1.0, |m| m.get(Stat::AttackSpeed));
+        weapon.cooldown = (weapon.cooldown - dt * attack_speed).max(0.0);
+        if weapon.ammo != Some(0) {
+            continue;
+        }
+        weapon.reload -= dt;
+        if weapon.reload <= 0.0 {
+            weapon.reload = 0.0;
+            weapon.ammo = defs.get(weapon.def).and_then(|def| def.ammo);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::time::TimeUpdateStrategy;
+    use std::time::Duration;
+
+    fn test_app(weapon: &WeaponDef) -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            100,
+        )));
+
+        let mut registry = ContentRegistry::<WeaponDef>::default();
+        registry.insert(weapon.clone());
+        app.insert_resource(registry);
+        app.insert_resource(RunRng::new(1));
+
+        app.add_systems(FixedUpdate, tick_weapons);
+        app.add_observer(fire_weapon);
+        app
+    }
+
+    fn fire(app: &mut App, shooter: Entity) -> usize {
+        app.world_mut().commands().trigger(FireWeapon {
+            shooter,
+            direction: Vec2::X,
+        });
+        app.world_mut().flush();
+        let mut projectiles = app.world_mut().query::<&Projectile>();
+        projectiles.iter(app.world()).count()
+    }
+
+    #[test]
+    fn weapons_respect_fire_rate_ammo_and_reload() {
+        let mut pistol = WeaponDef::new("Pistol", 4.0, 300.0, 200.0, 4);
+        pistol.ammo = Some(2);
+        pistol.reload_time = 0.5;
+        let mut app = test_app(&pistol);
+        app.update();
+        let shooter = app
+            .world_mut()
+            .spawn((Weapon::new(&pistol), Transform::default()))
+            .id();
+
+        assert_eq!(fire(&mut app, shooter), 1);
+        // Still cooling down
+        assert_eq!(fire(&mut app, shooter), 1);
+
+        for _ in 0..3 {
+            app.update();
+        }
+        assert_eq!(fire(&mut app, shooter), 2);
+        let weapon = app.world().get::<Weapon>(shooter).unwrap();
+        assert_eq!(weapon.ammo, Some(0));
+
+        // Empty until the reload finishes
+        app.update();
+        app.update();
+        assert_eq!(fire(&mut app, shooter), 2);
+        for _ in 0..5 {
+            app.update();
+        }
+        assert_eq!(app.world().get::<Weapon>(shooter).unwrap().ammo, Some(2));
+        assert_eq!(fire(&mut app, shooter), 3);
+    }
+
+    #[test]
+    fn projectiles_fan_out_across_the_spread() {
+        let mut shotgun = WeaponDef::new("Shotgun", 1.0, 100.0, 100.0, 2);
+        shotgun.projectile_count = 5;
+        shotgun.spread = 90.0;
+        let mut app = test_app(&shotgun);
+        let shooter = app
+            .world_mut()
+            .spawn((Weapon::new(&shotgun), Transform::default()))
+            .id();
+        assert_eq!(fire(&mut app, shooter), 5);
+
+        let mut projectiles = app.world_mut().query::<&Projectile>();
+        let mut angles: Vec<f32> = projectiles
+            .iter(app.world())
+            .map(|p| p.velocity.to_angle().to_degrees())
+            .collect();
+        angles.sort_by(f32::total_cmp);
+        for (angle, expected) in angles.into_iter().zip([-45.0, -22.5, 0.0, 22.5, 45.0]) {
+            assert!((angle - expected).abs() < 1e-3);
+        }
+    }
+}
@@ -0,0 +1,109 @@
+//! Generating rooms on demand during a run.
+
+use bevy::prelude::*;
+use rand::Rng;
+use roguebench_core::TilesetDef;
+use roguebench_protocol::RoomLayout;
+use roguebench_worldgen::{RoomRequest, generate_room};
+use uuid::Uuid;
+
+use crate::content::{ContentAppExt, ContentRegistry};
+use crate::rng::{RngStream, RunRng};
+use crate::room::activate_room;
+
+/// Event requesting that a new room is generated and made the active room.
+#[derive(Event, Debug, Clone)]
+pub struct GenerateRoom {
+    pub tileset: Uuid,
+    pub request: RoomRequest,
+}
+
+/// Plugin for tileset content and procedural rooms.
+pub struct WorldgenPlugin;
+
+impl Plugin for WorldgenPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_content::<TilesetDef>();
+
+        app.add_observer(generate_next_room);
+    }
+}
+
+/// Generate a room from the run's worldgen stream and activate it.
+pub fn generate_next_room(
+    trigger: On<GenerateRoom>,
+    mut commands: Commands,
+    tilesets: Res<ContentRegistry<TilesetDef>>,
+    mut rng: ResMut<RunRng>,
+    existing: Query<Entity, With<RoomLayout>>,
+) {
+    let Some(tileset) = tilesets.get(trigger.tileset) else {
+        tracing::warn!(
+            "Cannot generate room from unknown tileset {}",
+            trigger.tileset
+        );
+        return;
+    };
+    let seed: u64 = rng.stream(RngStream::Worldgen).r#gen();
+    match generate_room(tileset, &trigger.request, seed) {
+        Ok(room) => activate_room(&mut commands, room, &existing),
+        Err(e) => tracing::error!("Failed to generate room: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use roguebench_core::{Direction, Sockets, TileKind, TileVariant};
+    use roguebench_worldgen::playability_issues;
+
+    use crate::room::{ActiveRoom, CollisionMap};
+
+    fn test_app(tileset: &TilesetDef, seed: u64) -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+
+        let mut registry = ContentRegistry::<TilesetDef>::default();
+        registry.insert(tileset.clone());
+        app.insert_resource(registry);
+        app.insert_resource(RunRng::new(seed));
+
+        app.add_observer(generate_next_room);
+        app
+    }
+
+    fn generate(app: &mut App, tileset: &TilesetDef) -> ActiveRoom {
+        app.world_mut().commands().trigger(GenerateRoom {
+            tileset: tileset.id,
+            request: RoomRequest::new(9, 9, vec![Direction::South, Direction::North]),
+        });
+        app.update();
+        app.world().resource::<ActiveRoom>().clone()
+    }
+
+    #[test]
+    fn generated_rooms_follow_the_run_seed() {
+        let tileset = TilesetDef::new(
+            "Plain",
+            vec![
+                TileVariant::new("floor", TileKind::Floor, Sockets::uniform("open")),
+                TileVariant::new("wall", TileKind::Wall, Sockets::uniform("open")),
+            ],
+        );
+        let mut first = test_app(&tileset, 3);
+        let mut second = test_app(&tileset, 3);
+
+        let room = generate(&mut first, &tileset);
+        assert_eq!(playability_issues(&room.0), Vec::<String>::new());
+        assert_eq!(first.world().resource::<CollisionMap>().room, room.0.id);
+        assert_eq!(generate(&mut second, &tileset).0, room.0);
+
+        // The next room of the run is a different one
+        let next = generate(&mut first, &tileset);
+        assert_ne!(next.0.id, room.0.id);
+        let mut layouts = first.world_mut().query::<&RoomLayout>();
+        let layouts: Vec<_> = layouts.iter(first.world()).collect();
+        assert_eq!(layouts.len(), 1);
+        assert_eq!(layouts[0].room, next.0.id);
+    }
+}
@@ -0,0 +1,71 @@
+//! Player identity and connect token requests.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::ProtocolHash;
+
+/// Path of the HTTP endpoint issuing connect tokens.
+pub const CONNECT_PATH: &str = "/connect";
+
+/// Seconds a connect token stays valid for after being issued.
+pub const TOKEN_EXPIRY_SECS: i32 = 30;
+
+/// Secret a player proves who they are with when asking for a connect token.
+///
+/// The player's ID is derived from it, so nobody can play as a player
+/// without holding their secret; keep it private.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct PlayerSecret(pub String);
+
+impl PlayerSecret {
+    /// Shortest secret accepted, in characters.
+    pub const MIN_LEN: usize = 32;
+
+    /// A new random secret, for a new player.
+    pub fn generate() -> Self {
+        Self(format!(
+            "{}{}",
+            Uuid::new_v4().simple(),
+            Uuid::new_v4().simple()
+        ))
+    }
+
+    /// Whether the secret is long enough to be hard to guess.
+    pub fn is_valid(&self) -> bool {
+        self.0.chars().count() >= Self::MIN_LEN
+    }
+
+    /// ID of the player holding the secret.
+    pub fn player(&self) -> Uuid {
+        let digest = Sha256::new()
+            .chain_update(b"roguebench player\0")
+            .chain_update(self.0.as_bytes())
+            .finalize();
+        let bytes = digest[..16]
+            .try_into()
+            .expect("SHA-256 digests are 32 bytes");
+        uuid::Builder::from_random_bytes(bytes).into_uuid()
+    }
+}
+
+// Never print the secret itself
+impl fmt::Debug for PlayerSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PlayerSecret(..)")
+    }
+}
+
+/// Body of a request for a connect token.
+///
+/// Tokens are only issued to clients speaking the server's protocol, and
+/// only for the player their secret belongs to.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TokenRequest {
+    /// Secret of the player connecting.
+    pub secret: PlayerSecret,
+    pub protocol: ProtocolHash,
+}
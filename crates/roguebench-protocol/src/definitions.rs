@@ -0,0 +1,216 @@
+//! Authored definitions mirrored from the server to clients.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use bevy::prelude::*;
+use roguebench_core::{ContentDef, EntityDef};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Kind entity templates are synced under; they aren't a [`ContentDef`].
+pub const ENTITY_KIND: &str = "entity";
+
+/// One synced definition: its kind, ID and JSON document.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DefinitionDoc {
+    pub kind: String,
+    pub id: Uuid,
+    pub json: String,
+}
+
+/// Server → client: the published definitions, or changes to them.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum DefinitionSync {
+    /// Every published definition, replacing whatever the client held.
+    Snapshot {
+        revision: u64,
+        docs: Vec<DefinitionDoc>,
+    },
+    /// What changed going from `revision - 1` to `revision`.
+    Delta {
+        revision: u64,
+        changed: Vec<DefinitionDoc>,
+        /// Kind and ID of each definition that was deleted.
+        removed: Vec<(String, Uuid)>,
+    },
+}
+
+/// Client → server: send a fresh [`DefinitionSync::Snapshot`], the client's
+/// copy having fallen behind.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RequestDefinitions;
+
+/// A delta that doesn't follow on from the revision the client holds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StaleDefinitions {
+    /// Revision the client holds.
+    pub have: u64,
+    /// Revision the delta leads to.
+    pub got: u64,
+}
+
+impl fmt::Display for StaleDefinitions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "definitions at revision {} can't take the delta to revision {}",
+            self.have, self.got
+        )
+    }
+}
+
+/// Published definitions of every kind, at a content revision.
+///
+/// The server bumps the revision on every change it publishes; clients
+/// keep a copy up to date from [`DefinitionSync`] messages.
+#[derive(Resource, Default, Clone, Debug)]
+pub struct Definitions {
+    revision: u64,
+    docs: HashMap<(String, Uuid), String>,
+}
+
+impl Definitions {
+    /// Revision of the content held; 0 before anything was published.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Number of definitions held.
+    pub fn len(&self) -> usize {
+        self.docs.len()
+    }
+
+    /// Whether no definitions are held.
+    pub fn is_empty(&self) -> bool {
+        self.docs.is_empty()
+    }
+
+    /// JSON document of a definition.
+    pub fn document(&self, kind: &str, id: Uuid) -> Option<&str> {
+        self.docs.get(&(kind.to_string(), id)).map(String::as_str)
+    }
+
+    /// Look up a content definition by ID.
+    pub fn get<T: ContentDef>(&self, id: Uuid) -> Option<T> {
+        self.decode(T::KIND, id)
+    }
+
+    /// Look up an entity template by ID.
+    pub fn entity(&self, id: Uuid) -> Option<EntityDef> {
+        self.decode(ENTITY_KIND, id)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, kind: &str, id: Uuid) -> Option<T> {
+        serde_json::from_str(self.document(kind, id)?).ok()
+    }
+
+    /// Replace every definition of one kind, bumping the revision.
+    ///
+    /// Returns the delta to send clients, or `None` if nothing changed.
+    pub fn publish<'a, T: Serialize + 'a>(
+        &mut self,
+        kind: &str,
+        defs: impl IntoIterator<Item = (Uuid, &'a T)>,
+    ) -> Option<DefinitionSync> {
+        let mut published: HashMap<Uuid, String> = defs
+            .into_iter()
+            .filter_map(|(id, def)| Some((id, serde_json::to_string(def).ok()?)))
+            .collect();
+
+        let mut removed = Vec::new();
+        self.docs.retain(|(doc_kind, id), json| {
+            if doc_kind != kind {
+                return true;
+            }
+            match published.remove(id) {
+                // Unchanged; nothing to send
+                Some(new) if new == *json => true,
+                // Changed; put back so it goes out below
+                Some(new) => {
+                    published.insert(*id, new);
+                    false
+                }
+                None => {
+                    removed.push((kind.to_string(), *id));
+                    false
+                }
+            }
+        });
+        if published.is_empty() && removed.is_empty() {
+            return None;
+        }
+
+        let changed: Vec<_> = published
+            .into_iter()
+            .map(|(id, json)| DefinitionDoc {
+                kind: kind.to_string(),
+                id,
+                json,
+            })
+            .collect();
+        for doc in &changed {
+            self.docs
+                .insert((doc.kind.clone(), doc.id), doc.json.clone());
+        }
+        self.revision += 1;
+        Some(DefinitionSync::Delta {
+            revision: self.revision,
+            changed,
+            removed,
+        })
+    }
+
+    /// Everything held, for a client that has nothing yet.
+    pub fn snapshot(&self) -> DefinitionSync {
+        DefinitionSync::Snapshot {
+            revision: self.revision,
+            docs: self
+                .docs
+                .iter()
+                .map(|((kind, id), json)| DefinitionDoc {
+                    kind: kind.clone(),
+                    id: *id,
+                    json: json.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Bring this copy up to date with a message from the server.
+    ///
+    /// Deltas must follow on from the revision held; otherwise nothing is
+    /// applied and a new snapshot is needed.
+    pub fn apply(&mut self, sync: DefinitionSync) -> Result<(), StaleDefinitions> {
+        match sync {
+            DefinitionSync::Snapshot { revision, docs } => {
+                self.revision = revision;
+                self.docs = docs
+                    .into_iter()
+                    .map(|doc| ((doc.kind, doc.id), doc.json))
+                    .collect();
+            }
+            DefinitionSync::Delta {
+                revision,
+                changed,
+                removed,
+            } => {
+                if revision != self.revision + 1 {
+                    return Err(StaleDefinitions {
+                        have: self.revision,
+                        got: revision,
+                    });
+                }
+                self.revision = revision;
+                for key in removed {
+                    self.docs.remove(&key);
+                }
+                for doc in changed {
+                    self.docs.insert((doc.kind, doc.id), doc.json);
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,42 @@
+//! Dialogue messages exchanged between client and server.
+
+use bevy::ecs::entity::{EntityMapper, MapEntities};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::TILE_SIZE;
+
+/// Furthest a player can stand from an NPC and still talk to it, in world units.
+pub const TALK_RANGE: f32 = 3.0 * TILE_SIZE;
+
+/// Client → server: start talking to an NPC.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TalkToNpc {
+    pub npc: Entity,
+}
+
+impl MapEntities for TalkToNpc {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.npc = entity_mapper.get_mapped(self.npc);
+    }
+}
+
+/// Client → server: pick one of the choices offered by the last [`DialogueLine`].
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PickDialogueChoice {
+    /// Index into [`DialogueLine::choices`].
+    pub choice: usize,
+}
+
+/// Server → client: the line the player's conversation is currently at.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DialogueLine {
+    pub speaker: String,
+    pub text: String,
+    /// Text of the choices available to this player, in order.
+    pub choices: Vec<String>,
+}
+
+/// Server → client: the player's conversation has ended.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DialogueClosed;
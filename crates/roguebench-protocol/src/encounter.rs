@@ -0,0 +1,31 @@
+//! Replicated encounter progress.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Where an encounter is in its lifecycle.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EncounterPhase {
+    /// Counting down before the current wave starts spawning.
+    #[default]
+    Waiting,
+    /// Spawning the current wave.
+    Spawning,
+    /// Everything in the current wave has spawned; waiting for it to be cleared.
+    Active,
+    /// Every wave has been cleared.
+    Cleared,
+}
+
+/// Replicated component with the progress of a running encounter.
+///
+/// Sent to every client so they can show a wave counter.
+#[derive(Component, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct WaveProgress {
+    /// Index of the current wave, starting at zero.
+    pub wave: u32,
+    pub total_waves: u32,
+    pub phase: EncounterPhase,
+    /// Entities of the current wave that are still alive or yet to spawn.
+    pub remaining: u32,
+}
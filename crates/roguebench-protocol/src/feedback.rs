@@ -0,0 +1,58 @@
+//! Gameplay events the server tells nearby clients about, so they can play
+//! effects for them.
+
+use bevy::ecs::entity::{EntityMapper, MapEntities};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Server → client: an entity took damage.
+#[derive(Event, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HitLanded {
+    pub target: Entity,
+    pub source: Entity,
+    pub amount: i32,
+}
+
+impl MapEntities for HitLanded {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.target = entity_mapper.get_mapped(self.target);
+        self.source = entity_mapper.get_mapped(self.source);
+    }
+}
+
+/// Server → client: an entity's health ran out.
+#[derive(Event, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct EntityDied {
+    pub entity: Entity,
+}
+
+impl MapEntities for EntityDied {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.entity = entity_mapper.get_mapped(self.entity);
+    }
+}
+
+/// Server → client: a player picked up an item.
+#[derive(Event, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ItemCollected {
+    pub picker: Entity,
+    /// ID of the [`ItemDef`](roguebench_core::ItemDef) picked up.
+    pub item: Uuid,
+    pub count: u32,
+}
+
+impl MapEntities for ItemCollected {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.picker = entity_mapper.get_mapped(self.picker);
+    }
+}
+
+/// Server → client: a wave of the room's encounter was cleared.
+#[derive(Event, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct WaveComplete {
+    /// Index of the wave, as in [`WaveProgress::wave`](crate::WaveProgress::wave).
+    pub wave: u32,
+    /// Whether it was the encounter's last wave.
+    pub last: bool,
+}
@@ -0,0 +1,42 @@
+//! Player input sent from clients to the server every tick.
+
+use bevy::ecs::entity::{EntityMapper, MapEntities};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Walking speed of players, in world units per second.
+pub const PLAYER_SPEED: f32 = 160.0;
+
+/// What a player is doing on one tick.
+///
+/// The default value means no buttons are held, which Lightyear tells apart
+/// from input that never arrived.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Reflect)]
+pub struct PlayerInput {
+    /// Direction to walk in; longer than one is treated as one.
+    pub movement: Vec2,
+    /// Direction to aim in, relative to the player.
+    pub aim: Vec2,
+    /// Whether the attack button is held.
+    pub attack: bool,
+}
+
+impl PlayerInput {
+    /// Where a player at `from` ends up after walking for `dt` seconds.
+    ///
+    /// The server and client prediction both move players with this, so they
+    /// agree whenever the speed does. Movement that isn't a finite vector is
+    /// treated as standing still.
+    pub fn walk(&self, from: Vec2, speed: f32, dt: f32) -> Vec2 {
+        let movement = if self.movement.is_finite() {
+            self.movement
+        } else {
+            Vec2::ZERO
+        };
+        from + movement.clamp_length_max(1.0) * speed * dt
+    }
+}
+
+impl MapEntities for PlayerInput {
+    fn map_entities<M: EntityMapper>(&mut self, _entity_mapper: &mut M) {}
+}
@@ -0,0 +1,154 @@
+//! Replicated inventory and pickup components.
+
+use bevy::prelude::*;
+use roguebench_core::ItemDef;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A number of identical items occupying one inventory slot.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ItemStack {
+    /// ID of the [`ItemDef`] this stack holds.
+    pub item: Uuid,
+    pub count: u32,
+}
+
+/// Replicated component holding a player's items.
+///
+/// The server is authoritative; clients only ever receive their own inventory.
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Inventory {
+    pub slots: Vec<Option<ItemStack>>,
+}
+
+impl Inventory {
+    /// Create an empty inventory with a fixed number of slots.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            slots: vec![None; capacity],
+        }
+    }
+
+    /// Total number of the given item across all slots.
+    pub fn count(&self, item: Uuid) -> u32 {
+        self.slots
+            .iter()
+            .flatten()
+            .filter(|stack| stack.item == item)
+            .map(|stack| stack.count)
+            .sum()
+    }
+
+    /// Add items, topping up existing stacks before using empty slots.
+    ///
+    /// Returns the number of items that did not fit.
+    pub fn add(&mut self, item: &ItemDef, mut count: u32) -> u32 {
+        let max_stack = item.max_stack.max(1);
+
+        for stack in self.slots.iter_mut().flatten() {
+            if count == 0 {
+                break;
+            }
+            if stack.item == item.id && stack.count < max_stack {
+                let moved = count.min(max_stack - stack.count);
+                stack.count += moved;
+                count -= moved;
+            }
+        }
+
+        for slot in self.slots.iter_mut().filter(|slot| slot.is_none()) {
+            if count == 0 {
+                break;
+            }
+            let moved = count.min(max_stack);
+            *slot = Some(ItemStack {
+                item: item.id,
+                count: moved,
+            });
+            count -= moved;
+        }
+
+        count
+    }
+
+    /// Remove up to `count` of an item, emptying slots that run out.
+    ///
+    /// Returns the number of items actually removed.
+    pub fn remove(&mut self, item: Uuid, count: u32) -> u32 {
+        let mut removed = 0;
+        for slot in self.slots.iter_mut().rev() {
+            let Some(stack) = slot.as_mut().filter(|stack| stack.item == item) else {
+                continue;
+            };
+            let taken = (count - removed).min(stack.count);
+            stack.count -= taken;
+            removed += taken;
+            if stack.count == 0 {
+                *slot = None;
+            }
+            if removed == count {
+                break;
+            }
+        }
+        removed
+    }
+
+    /// Move `count` items from the stack in `slot` into the first empty slot.
+    ///
+    /// Returns the index of the new stack, or `None` if the split is invalid
+    /// or there is no empty slot.
+    pub fn split(&mut self, slot: usize, count: u32) -> Option<usize> {
+        let stack = (*self.slots.get(slot)?)?;
+        if count == 0 || count >= stack.count {
+            return None;
+        }
+        let target = self.slots.iter().position(Option::is_none)?;
+        self.slots[slot] = Some(ItemStack {
+            count: stack.count - count,
+            ..stack
+        });
+        self.slots[target] = Some(ItemStack { count, ..stack });
+        Some(target)
+    }
+
+    /// Move as many items as fit from the stack in `from` onto the stack in `to`.
+    ///
+    /// Both slots must hold the same item. Returns the number of items moved.
+    pub fn stack(&mut self, item: &ItemDef, from: usize, to: usize) -> u32 {
+        if from == to {
+            return 0;
+        }
+        let (Some(Some(source)), Some(Some(target))) =
+            (self.slots.get(from).copied(), self.slots.get(to).copied())
+        else {
+            return 0;
+        };
+        if source.item != item.id || target.item != item.id {
+            return 0;
+        }
+        let moved = source
+            .count
+            .min(item.max_stack.max(1).saturating_sub(target.count));
+        if moved == 0 {
+            return 0;
+        }
+
+        let remaining = source.count - moved;
+        self.slots[from] = (remaining > 0).then_some(ItemStack {
+            count: remaining,
+            ..source
+        });
+        if let Some(target) = self.slots[to].as_mut() {
+            target.count += moved;
+        }
+        moved
+    }
+}
+
+/// Replicated component for an item lying in the world, waiting to be picked up.
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Pickup {
+    /// ID of the [`ItemDef`] this pickup grants.
+    pub item: Uuid,
+    pub count: u32,
+}
@@ -3,14 +3,46 @@
 //! This crate contains replicated components and messages shared between client and server.
 
 use bevy::prelude::*;
+use lightyear::prelude::input::native::InputPlugin;
 use lightyear::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
-pub use roguebench_core::prelude::*;
+mod auth;
+mod definitions;
+mod dialogue;
+mod encounter;
+mod feedback;
+mod input;
+mod inventory;
+mod projectile;
+mod quest;
+mod room;
+mod shop;
+mod spatial;
+mod status;
+mod version;
 
-/// Fixed timestep for network synchronization (60 Hz).
-pub const FIXED_TIMESTEP_HZ: f64 = 60.0;
+pub use auth::{CONNECT_PATH, PlayerSecret, TOKEN_EXPIRY_SECS, TokenRequest};
+pub use definitions::{
+    DefinitionDoc, DefinitionSync, Definitions, ENTITY_KIND, RequestDefinitions, StaleDefinitions,
+};
+pub use dialogue::{DialogueClosed, DialogueLine, PickDialogueChoice, TALK_RANGE, TalkToNpc};
+pub use encounter::{EncounterPhase, WaveProgress};
+pub use feedback::{EntityDied, HitLanded, ItemCollected, WaveComplete};
+pub use input::{PLAYER_SPEED, PlayerInput};
+pub use inventory::{Inventory, ItemStack, Pickup};
+pub use projectile::Projectile;
+pub use quest::QuestJournal;
+pub use roguebench_core::FIXED_TIMESTEP_HZ;
+pub use roguebench_core::prelude::*;
+pub use room::{RoomLayout, TILE_SIZE};
+pub use shop::{
+    BuyItem, Listing, SellItem, ShopStock, TRADE_RANGE, TradeError, TradeRejected, Wallet,
+};
+pub use spatial::{Facing, Position, Velocity};
+pub use status::{ActiveStatus, StatusEffects};
+pub use version::{PROTOCOL_REVISION, ProtocolHash, VersionMismatch};
 
 /// Tick duration derived from the fixed timestep.
 pub fn tick_duration() -> Duration {
@@ -23,6 +55,32 @@ pub fn tick_duration() -> Duration {
 #[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct EntityName(pub String);
 
+/// Replicated component naming the [`EntityDef`] an entity was spawned from.
+///
+/// Names aren't unique; this is what clients and tools use to find the
+/// definition behind a runtime entity.
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TemplateId {
+    pub id: uuid::Uuid,
+    /// The definition's [`slug`](EntityDef::slug), if it has one.
+    pub slug: Option<String>,
+}
+
+impl TemplateId {
+    /// ID of a definition.
+    pub fn of(def: &EntityDef) -> Self {
+        Self {
+            id: def.id,
+            slug: def.slug.clone(),
+        }
+    }
+
+    /// Look the definition up among those synced from the server.
+    pub fn resolve(&self, definitions: &Definitions) -> Option<EntityDef> {
+        definitions.entity(self.id)
+    }
+}
+
 /// Replicated component for entity health.
 ///
 /// Represents the current health of a spawned entity.
@@ -32,14 +90,32 @@ pub struct Health(pub i32);
 /// Channel for reliable ordered messages.
 pub struct ReliableChannel;
 
+/// Channel for cosmetic messages that are fine to lose, such as hit effects.
+pub struct FeedbackChannel;
+
+/// How the editor changed a definition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentChange {
+    Created,
+    Updated,
+    Deleted,
+}
+
 /// Messages from the editor to the engine.
 ///
 /// These are sent via an in-process channel when the editor
 /// modifies content that the engine should reload.
 #[derive(Debug, Clone)]
 pub enum EditorMessage {
-    /// Entities have been modified; reload from storage.
-    ReloadEntities,
+    /// A definition was saved or deleted; entity definitions come under
+    /// [`ENTITY_KIND`], the rest under their [`ContentDef::KIND`].
+    ContentChanged {
+        kind: &'static str,
+        id: uuid::Uuid,
+        change: ContentChange,
+    },
+    /// A connect token was issued to a player under the given netcode client ID.
+    PlayerAuthorized { client_id: u64, player: uuid::Uuid },
 }
 
 /// Plugin that registers the network protocol.
@@ -50,19 +126,95 @@ impl Plugin for ProtocolPlugin {
         // Register replicated components
         app.register_component::<EntityName>();
         app.register_component::<Health>();
+        app.register_component::<Inventory>();
+        app.register_component::<Pickup>();
+        app.register_component::<Projectile>();
+        app.register_component::<QuestJournal>();
+        app.register_component::<RoomLayout>();
+        app.register_component::<ShopStock>();
+        app.register_component::<StatusEffects>();
+        app.register_component::<TemplateId>();
+        app.register_component::<Wallet>();
+        app.register_component::<WaveProgress>();
+
+        // Spatial state is interpolated between server updates on clients; a
+        // client predicts the position of the player it controls instead
+        app.register_component::<Position>()
+            .add_prediction()
+            .add_interpolation_with(spatial::lerp_position);
+        app.register_component::<Velocity>()
+            .add_interpolation_with(spatial::lerp_velocity);
+        app.register_component::<Facing>()
+            .add_interpolation_with(spatial::lerp_facing);
+
+        // Register messages
+        app.register_message::<TalkToNpc>()
+            .add_map_entities()
+            .add_direction(NetworkDirection::ClientToServer);
+        app.register_message::<PickDialogueChoice>()
+            .add_direction(NetworkDirection::ClientToServer);
+        app.register_message::<DialogueLine>()
+            .add_direction(NetworkDirection::ServerToClient);
+        app.register_message::<DialogueClosed>()
+            .add_direction(NetworkDirection::ServerToClient);
+        app.register_message::<BuyItem>()
+            .add_map_entities()
+            .add_direction(NetworkDirection::ClientToServer);
+        app.register_message::<SellItem>()
+            .add_map_entities()
+            .add_direction(NetworkDirection::ClientToServer);
+        app.register_message::<TradeRejected>()
+            .add_direction(NetworkDirection::ServerToClient);
+        app.register_message::<DefinitionSync>()
+            .add_direction(NetworkDirection::ServerToClient);
+        app.register_message::<RequestDefinitions>()
+            .add_direction(NetworkDirection::ClientToServer);
+        app.register_message::<HitLanded>()
+            .add_map_entities()
+            .add_direction(NetworkDirection::ServerToClient);
+        app.register_message::<EntityDied>()
+            .add_map_entities()
+            .add_direction(NetworkDirection::ServerToClient);
+        app.register_message::<ItemCollected>()
+            .add_map_entities()
+            .add_direction(NetworkDirection::ServerToClient);
+        app.register_message::<WaveComplete>()
+            .add_direction(NetworkDirection::ServerToClient);
+
+        // Register inputs
+        app.add_plugins(InputPlugin::<PlayerInput>::default());
 
         // Register channels
         app.add_channel::<ReliableChannel>(ChannelSettings {
             mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
             ..default()
-        });
+        })
+        .add_direction(NetworkDirection::Bidirectional);
+        app.add_channel::<FeedbackChannel>(ChannelSettings {
+            mode: ChannelMode::UnorderedUnreliable,
+            ..default()
+        })
+        .add_direction(NetworkDirection::ServerToClient);
+    }
+
+    fn finish(&self, app: &mut App) {
+        // Every plugin has registered its types by now
+        let hash = ProtocolHash::of(app.world_mut());
+        app.insert_resource(hash);
     }
 }
 
 pub mod prelude {
     pub use crate::{
-        tick_duration, EditorMessage, EntityName, Health, ProtocolPlugin, ReliableChannel,
-        FIXED_TIMESTEP_HZ,
+        ActiveStatus, BuyItem, CONNECT_PATH, ContentChange, DefinitionSync, Definitions,
+        DialogueClosed, DialogueLine, ENTITY_KIND, EditorMessage, EncounterPhase, EntityDied,
+        EntityName, FIXED_TIMESTEP_HZ, Facing, FeedbackChannel, Health, HitLanded, Inventory,
+        ItemCollected, ItemStack, Listing, PLAYER_SPEED, PROTOCOL_REVISION, PickDialogueChoice,
+        Pickup, PlayerInput, PlayerSecret, Position, Projectile, ProtocolHash, ProtocolPlugin,
+        QuestJournal, ReliableChannel, RequestDefinitions, RoomLayout, SellItem, ShopStock,
+        StatusEffects, TALK_RANGE, TILE_SIZE, TOKEN_EXPIRY_SECS, TRADE_RANGE, TalkToNpc,
+        TemplateId, TokenRequest, TradeError, TradeRejected, Velocity, VersionMismatch, Wallet,
+        WaveComplete, WaveProgress, tick_duration,
     };
     pub use roguebench_core::prelude::*;
 }
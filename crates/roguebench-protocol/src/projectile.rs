@@ -0,0 +1,24 @@
+//! Replicated projectiles.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Replicated component for a projectile in flight.
+///
+/// Projectiles fly in a straight line at constant speed, so the server never
+/// changes this component after spawning it. Clients receive it once and
+/// extrapolate the position themselves until the projectile is despawned.
+#[derive(Component, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Projectile {
+    /// World position the projectile was fired from.
+    pub origin: Vec2,
+    /// World units per second.
+    pub velocity: Vec2,
+}
+
+impl Projectile {
+    /// Where the projectile is after `elapsed` seconds of flight.
+    pub fn position_after(&self, elapsed: f32) -> Vec2 {
+        self.origin + self.velocity * elapsed
+    }
+}
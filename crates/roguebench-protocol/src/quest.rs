@@ -0,0 +1,11 @@
+//! Replicated quest progress.
+
+use bevy::prelude::*;
+use roguebench_core::QuestLog;
+use serde::{Deserialize, Serialize};
+
+/// Replicated component holding a player's quest progress.
+///
+/// Only replicated to the client controlling the player.
+#[derive(Component, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct QuestJournal(pub QuestLog);
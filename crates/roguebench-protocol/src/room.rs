@@ -0,0 +1,48 @@
+//! Replicated room layout.
+
+use bevy::prelude::*;
+use roguebench_core::{RoomDef, TileKind, grid_index};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// World units covered by one tile along each axis.
+pub const TILE_SIZE: f32 = 32.0;
+
+/// Replicated component describing the tile grid of the current room.
+///
+/// Sent to every client so they can render the room.
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RoomLayout {
+    /// ID of the [`RoomDef`] the layout was built from.
+    pub room: Uuid,
+    pub width: u32,
+    pub height: u32,
+    /// Tiles row by row, starting from the bottom row.
+    pub tiles: Vec<TileKind>,
+}
+
+impl RoomLayout {
+    /// The tile at the given cell, or `None` outside the grid.
+    pub fn tile(&self, x: u32, y: u32) -> Option<TileKind> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.tiles.get(grid_index(self.width, x, y)).copied()
+    }
+
+    /// World position of the centre of a cell. Cell `(0, 0)` starts at the origin.
+    pub fn tile_center(x: u32, y: u32) -> Vec2 {
+        Vec2::new((x as f32 + 0.5) * TILE_SIZE, (y as f32 + 0.5) * TILE_SIZE)
+    }
+}
+
+impl From<&RoomDef> for RoomLayout {
+    fn from(room: &RoomDef) -> Self {
+        Self {
+            room: room.id,
+            width: room.width,
+            height: room.height,
+            tiles: room.tiles.clone(),
+        }
+    }
+}
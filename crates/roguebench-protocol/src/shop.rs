@@ -0,0 +1,124 @@
+//! Replicated wallets and shops, and the messages for trading with shops.
+
+use std::collections::BTreeMap;
+
+use bevy::ecs::entity::{EntityMapper, MapEntities};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::TILE_SIZE;
+
+/// Furthest a player can stand from a shop and still trade with it, in world units.
+pub const TRADE_RANGE: f32 = 3.0 * TILE_SIZE;
+
+/// Replicated component holding a player's money.
+///
+/// The server is authoritative; clients only ever receive their own wallet.
+#[derive(Component, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Wallet(pub BTreeMap<Uuid, u32>);
+
+impl Wallet {
+    /// Amount held of a currency.
+    pub fn balance(&self, currency: Uuid) -> u32 {
+        self.0.get(&currency).copied().unwrap_or(0)
+    }
+
+    pub fn deposit(&mut self, currency: Uuid, amount: u32) {
+        let balance = self.0.entry(currency).or_insert(0);
+        *balance = balance.saturating_add(amount);
+    }
+
+    /// Take money out of the wallet, or nothing if there isn't enough.
+    ///
+    /// Returns whether the money was taken.
+    pub fn withdraw(&mut self, currency: Uuid, amount: u32) -> bool {
+        let balance = self.balance(currency);
+        if balance < amount {
+            return false;
+        }
+        self.0.insert(currency, balance - amount);
+        true
+    }
+}
+
+/// An item on sale in a shop.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Listing {
+    /// ID of the [`ItemDef`](roguebench_core::ItemDef) on sale.
+    pub item: Uuid,
+    /// Price of one unit.
+    pub price: u32,
+    /// Units left; `None` for unlimited.
+    pub remaining: Option<u32>,
+}
+
+/// Replicated component with what a shop currently sells.
+#[derive(Component, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ShopStock {
+    /// ID of the [`ShopDef`](roguebench_core::ShopDef).
+    pub shop: Uuid,
+    /// Currency prices are paid in.
+    pub currency: Uuid,
+    pub listings: Vec<Listing>,
+}
+
+/// Client → server: buy units of one listing from a shop.
+///
+/// Carries the item and unit price the player saw, so a purchase made just as
+/// the shop restocks is refused rather than buying something else.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BuyItem {
+    pub shop: Entity,
+    /// Index into [`ShopStock::listings`].
+    pub listing: usize,
+    /// ID of the item the listing is expected to offer.
+    pub item: Uuid,
+    /// Unit price the listing is expected to ask.
+    pub price: u32,
+    pub count: u32,
+}
+
+impl MapEntities for BuyItem {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.shop = entity_mapper.get_mapped(self.shop);
+    }
+}
+
+/// Client → server: sell items from the player's inventory to a shop.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SellItem {
+    pub shop: Entity,
+    pub item: Uuid,
+    pub count: u32,
+}
+
+impl MapEntities for SellItem {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.shop = entity_mapper.get_mapped(self.shop);
+    }
+}
+
+/// Why the server turned down a trade.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TradeError {
+    /// The shop, listing or item doesn't exist.
+    NotForSale,
+    OutOfStock,
+    InsufficientFunds,
+    InventoryFull,
+    /// The player doesn't have the items they tried to sell.
+    NotOwned,
+    /// The shop doesn't buy items.
+    NotBuying,
+    /// The listing no longer offers the item at the price the player saw.
+    ListingChanged,
+    /// The player is too far from the shop.
+    OutOfRange,
+}
+
+/// Server → client: a trade the player asked for was turned down.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TradeRejected {
+    pub reason: TradeError,
+}
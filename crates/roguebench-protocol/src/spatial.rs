@@ -0,0 +1,41 @@
+//! Replicated spatial state: where entities are, how they move and where they face.
+
+use std::f32::consts::{PI, TAU};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Replicated world position of an entity.
+///
+/// Interpolated on clients between server updates.
+#[derive(Component, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct Position(pub Vec2);
+
+/// Replicated velocity of an entity, in world units per second.
+#[derive(Component, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct Velocity(pub Vec2);
+
+/// Replicated direction an entity faces, as an angle in radians from +X.
+#[derive(Component, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct Facing(pub f32);
+
+impl Facing {
+    /// Facing along a direction; `None` for a zero vector.
+    pub fn towards(direction: Vec2) -> Option<Self> {
+        (direction != Vec2::ZERO).then(|| Self(direction.to_angle()))
+    }
+}
+
+pub(crate) fn lerp_position(start: Position, end: Position, t: f32) -> Position {
+    Position(start.0.lerp(end.0, t))
+}
+
+pub(crate) fn lerp_velocity(start: Velocity, end: Velocity, t: f32) -> Velocity {
+    Velocity(start.0.lerp(end.0, t))
+}
+
+/// Turn through the shorter arc, so facing never spins the long way round.
+pub(crate) fn lerp_facing(start: Facing, end: Facing, t: f32) -> Facing {
+    let turn = (end.0 - start.0 + PI).rem_euclid(TAU) - PI;
+    Facing(start.0 + turn * t)
+}
@@ -0,0 +1,22 @@
+//! Replicated status effects.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// One status effect on an entity, as clients see it.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct ActiveStatus {
+    /// ID of the [`StatusEffectDef`](roguebench_core::StatusEffectDef).
+    pub effect: Uuid,
+    pub stacks: u32,
+    /// Seconds left when the server last changed the effects.
+    pub remaining: f32,
+}
+
+/// Replicated component listing the status effects on an entity.
+///
+/// The server only updates it when an effect is applied, stacked or expires,
+/// so clients count `remaining` down themselves in between.
+#[derive(Component, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct StatusEffects(pub Vec<ActiveStatus>);
@@ -0,0 +1,106 @@
+//! Protocol versioning: telling apart builds that can't talk to each other.
+
+use std::fmt;
+
+use bevy::prelude::*;
+use lightyear::prelude::{ChannelRegistry, ComponentRegistry, MessageRegistry};
+use serde::{Deserialize, Serialize};
+
+/// Revision of the wire format, mixed into the [`ProtocolHash`].
+///
+/// The hash only sees which types are registered, not how they serialize;
+/// bump this whenever a registered type changes shape.
+pub const PROTOCOL_REVISION: u64 = 1;
+
+/// Start of every netcode connection request: its packet type, then the netcode version.
+const REQUEST_HEADER: &[u8] = b"\0NETCODE 1.02\0";
+
+/// Size of a netcode connection request: the header, protocol ID, expiry,
+/// token nonce and encrypted token.
+///
+/// Only requests of this size are answered, so the much shorter reply can't
+/// be used to amplify traffic.
+const REQUEST_LEN: usize = REQUEST_HEADER.len() + 8 + 8 + 24 + 1024;
+
+/// Start of the reply to a connection request made with another protocol.
+///
+/// Netcode ignores packet types above 6, so clients of any build let it through.
+const MISMATCH_HEADER: &[u8] = b"\xffROGUEBENCH VERSION";
+
+/// Hash of everything registered with the network protocol and the
+/// [`PROTOCOL_REVISION`].
+///
+/// Used as the netcode protocol ID, so builds registering different
+/// components, messages or channels can't connect to each other.
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ProtocolHash(pub u64);
+
+impl ProtocolHash {
+    /// Hash the registered protocol, freezing it against further registration.
+    pub(crate) fn of(world: &mut World) -> Self {
+        let components = world.resource_mut::<ComponentRegistry>().finish();
+        let messages = world.resource_mut::<MessageRegistry>().finish();
+        let channels = world.resource_mut::<ChannelRegistry>().finish();
+        let hash = [components, messages, channels]
+            .into_iter()
+            .fold(PROTOCOL_REVISION, |hash, part| hash.rotate_left(21) ^ part);
+        Self(hash)
+    }
+
+    /// Protocol a raw netcode connection request was made with.
+    ///
+    /// `None` for any other packet, including truncated requests.
+    pub fn requested_by(packet: &[u8]) -> Option<Self> {
+        if packet.len() != REQUEST_LEN {
+            return None;
+        }
+        let id = packet.strip_prefix(REQUEST_HEADER)?.first_chunk()?;
+        Some(Self(u64::from_be_bytes(*id)))
+    }
+}
+
+impl fmt::Display for ProtocolHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// A client tried to connect to a server built with another protocol.
+///
+/// Netcode drops such connection requests without a word, so servers send
+/// this back instead. Also the body of a refused [`TokenRequest`](crate::TokenRequest).
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VersionMismatch {
+    pub client: ProtocolHash,
+    pub server: ProtocolHash,
+}
+
+impl VersionMismatch {
+    /// Raw packet telling the client about the mismatch.
+    pub fn to_packet(&self) -> Vec<u8> {
+        let mut packet = MISMATCH_HEADER.to_vec();
+        packet.extend_from_slice(&self.client.0.to_be_bytes());
+        packet.extend_from_slice(&self.server.0.to_be_bytes());
+        packet
+    }
+
+    /// Read a packet made by [`to_packet`](Self::to_packet); `None` for any
+    /// other packet.
+    pub fn from_packet(packet: &[u8]) -> Option<Self> {
+        let (client, server) = packet.strip_prefix(MISMATCH_HEADER)?.split_first_chunk()?;
+        Some(Self {
+            client: ProtocolHash(u64::from_be_bytes(*client)),
+            server: ProtocolHash(u64::from_be_bytes(*server.first_chunk()?)),
+        })
+    }
+}
+
+impl fmt::Display for VersionMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "version mismatch: client speaks protocol {}, server speaks {}",
+            self.client, self.server
+        )
+    }
+}
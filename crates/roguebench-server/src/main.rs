@@ -7,6 +7,7 @@ use std::sync::Arc;
 
 use anyhow::Result;
 use bevy::prelude::*;
+use lightyear::netcode::{Key, PRIVATE_KEY_BYTES};
 use lightyear::prelude::server::ServerPlugins;
 use roguebench_editor::prelude::*;
 use roguebench_engine::prelude::*;
@@ -20,15 +21,27 @@ const WEB_PORT: u16 = 8080;
 /// Port for the game server (Lightyear).
 const GAME_PORT: u16 = 5000;
 
+/// Environment variable holding a fixed run seed, for reproducing bug reports.
+const SEED_VAR: &str = "ROGUEBENCH_SEED";
+
+/// Environment variable holding the ID of the entity definition players are built from.
+const PLAYER_TEMPLATE_VAR: &str = "ROGUEBENCH_PLAYER_TEMPLATE";
+
+/// Environment variable holding the server's private key as 64 hex digits.
+///
+/// Without it a fresh key is made on every start, invalidating tokens
+/// issued before.
+const PRIVATE_KEY_VAR: &str = "ROGUEBENCH_PRIVATE_KEY";
+
 /// Server address for Lightyear.
-const SERVER_ADDR: SocketAddr = SocketAddr::new(
-    std::net::IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
-    GAME_PORT,
-);
+const SERVER_ADDR: SocketAddr =
+    SocketAddr::new(std::net::IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), GAME_PORT);
 
 fn main() -> Result<()> {
     tracing_subscriber::fmt()
-        .with_env_filter("roguebench_server=info,roguebench_engine=info,roguebench_editor=info,lightyear=warn")
+        .with_env_filter(
+            "roguebench_server=info,roguebench_engine=info,roguebench_editor=info,lightyear=warn",
+        )
         .init();
 
     // Initialize storage
@@ -37,11 +50,58 @@ fn main() -> Result<()> {
     // Channel for editor -> engine messages
     let (message_tx, message_rx) = mpsc::unbounded_channel();
 
-    // Start web editor in background
+    // Use a fixed run seed if one was given
+    let mut engine = EnginePlugin::new(store.clone(), message_rx, SERVER_ADDR);
+    if let Ok(seed) = std::env::var(SEED_VAR) {
+        match seed.trim().parse() {
+            Ok(seed) => engine.config.seed = Some(seed),
+            Err(_) => tracing::warn!("{} is not a whole number, using a random seed", SEED_VAR),
+        }
+    }
+    // Build players from a stored entity definition if one was named
+    if let Ok(id) = std::env::var(PLAYER_TEMPLATE_VAR) {
+        match id.trim().parse() {
+            Ok(id) => engine.config.player_template = Some(id),
+            Err(_) => tracing::warn!(
+                "{} is not an entity ID, using a default player",
+                PLAYER_TEMPLATE_VAR
+            ),
+        }
+    }
+    // Keep the private key across restarts if one was given
+    if let Ok(hex) = std::env::var(PRIVATE_KEY_VAR) {
+        match parse_key(&hex) {
+            Some(key) => engine.config.private_key = key,
+            None => tracing::warn!(
+                "{} is not 64 hex digits, using a fresh key",
+                PRIVATE_KEY_VAR
+            ),
+        }
+    }
+    let private_key = engine.config.private_key;
+
+    // Build the Bevy app with the engine plugin
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(ServerPlugins {
+            tick_duration: tick_duration(),
+        })
+        .add_plugins(ProtocolPlugin)
+        .add_plugins(engine);
+    // The protocol hash is only known once every plugin has finished
+    app.finish();
+    app.cleanup();
+
+    // Start web editor in background, issuing tokens for the game server
     let editor_config = EditorConfig {
-        storage: store.clone(),
+        storage: store,
         message_tx,
         listen_addr: SocketAddr::from((Ipv4Addr::LOCALHOST, WEB_PORT)),
+        tokens: Some(TokenIssuer {
+            server_addr: SERVER_ADDR,
+            protocol: *app.world().resource::<ProtocolHash>(),
+            private_key,
+        }),
     };
     std::thread::spawn(move || {
         let rt = tokio::runtime::Runtime::new().unwrap();
@@ -51,15 +111,20 @@ fn main() -> Result<()> {
     tracing::info!("Web editor at http://localhost:{}", WEB_PORT);
     tracing::info!("Game server on UDP port {}", GAME_PORT);
 
-    // Run Bevy app with engine plugin
-    App::new()
-        .add_plugins(MinimalPlugins)
-        .add_plugins(ServerPlugins {
-            tick_duration: tick_duration(),
-        })
-        .add_plugins(ProtocolPlugin)
-        .add_plugins(EnginePlugin::new(store, message_rx, SERVER_ADDR))
-        .run();
+    app.run();
 
     Ok(())
 }
+
+/// Parse a private key written as 64 hex digits.
+fn parse_key(hex: &str) -> Option<Key> {
+    let hex = hex.trim();
+    if hex.len() != 2 * PRIVATE_KEY_BYTES || !hex.is_ascii() {
+        return None;
+    }
+    let mut key = [0; PRIVATE_KEY_BYTES];
+    for (byte, pair) in key.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+    }
+    Some(key)
+}
@@ -9,7 +9,7 @@ mod sqlite;
 pub use memory::MemoryStore;
 pub use sqlite::SqliteStore;
 
-use roguebench_core::EntityDef;
+use roguebench_core::{ContentDef, EntityDef, PlayerProfile};
 use thiserror::Error;
 
 /// Errors that can occur during storage operations.
@@ -21,8 +21,14 @@ pub enum StorageError {
     #[error("Entity not found: {0}")]
     NotFound(String),
 
+    #[error("Slug already taken: {0}")]
+    SlugTaken(String),
+
     #[error("Data corruption: invalid UUID '{0}'")]
     InvalidUuid(String),
+
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
 }
 
 /// Result type for storage operations.
@@ -36,15 +42,99 @@ pub trait ContentStore: Send + Sync {
     /// Load all entity definitions.
     fn load_entities(&self) -> Result<Vec<EntityDef>>;
 
+    /// Load a single entity definition by ID, if present.
+    fn load_entity(&self, id: uuid::Uuid) -> Result<Option<EntityDef>>;
+
     /// Save an entity definition.
+    ///
+    /// Fails with [`StorageError::SlugTaken`] if another entity has its slug.
     fn save_entity(&self, entity: &EntityDef) -> Result<()>;
 
     /// Delete an entity by ID.
     fn delete_entity(&self, id: uuid::Uuid) -> Result<()>;
+
+    /// Load all serialized documents of the given content kind.
+    fn load_documents(&self, kind: &str) -> Result<Vec<String>>;
+
+    /// Load a single serialized document of the given content kind, if present.
+    fn load_document(&self, kind: &str, id: uuid::Uuid) -> Result<Option<String>>;
+
+    /// Save a serialized document of the given content kind.
+    fn save_document(&self, kind: &str, id: uuid::Uuid, data: &str) -> Result<()>;
+
+    /// Delete a document of the given content kind by ID.
+    fn delete_document(&self, kind: &str, id: uuid::Uuid) -> Result<()>;
+}
+
+/// Typed access to [`ContentDef`] kinds on top of the document methods.
+///
+/// Implemented for every [`ContentStore`], including `dyn ContentStore`.
+pub trait ContentStoreExt {
+    /// Load all definitions of kind `T`.
+    fn load_all<T: ContentDef>(&self) -> Result<Vec<T>>;
+
+    /// Load a single definition of kind `T` by ID, if present.
+    fn load<T: ContentDef>(&self, id: uuid::Uuid) -> Result<Option<T>>;
+
+    /// Save a definition, replacing any existing one with the same ID.
+    fn save<T: ContentDef>(&self, def: &T) -> Result<()>;
+
+    /// Delete a definition of kind `T` by ID.
+    fn delete<T: ContentDef>(&self, id: uuid::Uuid) -> Result<()>;
+}
+
+impl<S: ContentStore + ?Sized> ContentStoreExt for S {
+    fn load_all<T: ContentDef>(&self) -> Result<Vec<T>> {
+        self.load_documents(T::KIND)?
+            .iter()
+            .map(|data| serde_json::from_str(data).map_err(StorageError::from))
+            .collect()
+    }
+
+    fn load<T: ContentDef>(&self, id: uuid::Uuid) -> Result<Option<T>> {
+        self.load_document(T::KIND, id)?
+            .map(|data| serde_json::from_str(&data).map_err(StorageError::from))
+            .transpose()
+    }
+
+    fn save<T: ContentDef>(&self, def: &T) -> Result<()> {
+        let data = serde_json::to_string(def)?;
+        self.save_document(T::KIND, def.id(), &data)
+    }
+
+    fn delete<T: ContentDef>(&self, id: uuid::Uuid) -> Result<()> {
+        self.delete_document(T::KIND, id)
+    }
+}
+
+/// Player profiles that persist between runs.
+///
+/// Profiles are keyed by a stable player ID, never the netcode client ID,
+/// which changes on every connection. Implemented for every [`ContentStore`].
+pub trait ProfileStore {
+    /// Load a player's profile, or a fresh one if they have never played.
+    fn load_profile(&self, player: uuid::Uuid) -> Result<PlayerProfile>;
+
+    /// Save a player's profile, replacing the stored one.
+    fn save_profile(&self, profile: &PlayerProfile) -> Result<()>;
+}
+
+impl<S: ContentStore + ?Sized> ProfileStore for S {
+    fn load_profile(&self, player: uuid::Uuid) -> Result<PlayerProfile> {
+        Ok(self
+            .load::<PlayerProfile>(player)?
+            .unwrap_or_else(|| PlayerProfile::new(player)))
+    }
+
+    fn save_profile(&self, profile: &PlayerProfile) -> Result<()> {
+        self.save(profile)
+    }
 }
 
 pub mod prelude {
-    pub use crate::{ContentStore, MemoryStore, Result, SqliteStore, StorageError};
+    pub use crate::{
+        ContentStore, ContentStoreExt, MemoryStore, ProfileStore, Result, SqliteStore, StorageError,
+    };
 }
 
 #[cfg(test)]
@@ -76,17 +166,34 @@ mod tests {
         let loaded = store.load_entities().unwrap();
         assert_eq!(loaded.len(), 2);
 
-        // Update existing (same ID, different name and health)
+        // Update existing (same ID, different slug, name, health, dialogue, weapon, AI, faction
+        // and shop)
         let mut updated = entity.clone();
+        updated.slug = Some("goblin-king".to_string());
         updated.name = "Goblin King".to_string();
         updated.health = 150;
+        updated.dialogue = Some(uuid::Uuid::new_v4());
+        updated.weapon = Some(uuid::Uuid::new_v4());
+        updated.ai = Some(roguebench_core::AiProfile::brute());
+        updated.faction = Some(uuid::Uuid::new_v4());
+        updated.shop = Some(uuid::Uuid::new_v4());
         store.save_entity(&updated).unwrap();
 
         let loaded = store.load_entities().unwrap();
         assert_eq!(loaded.len(), 2);
         let goblin = loaded.iter().find(|e| e.id == entity.id).unwrap();
+        assert_eq!(goblin.slug.as_deref(), Some("goblin-king"));
         assert_eq!(goblin.name, "Goblin King");
         assert_eq!(goblin.health, 150);
+        assert_eq!(goblin.dialogue, updated.dialogue);
+        assert_eq!(goblin.weapon, updated.weapon);
+        assert_eq!(goblin.ai, updated.ai);
+        assert_eq!(goblin.faction, updated.faction);
+        assert_eq!(goblin.shop, updated.shop);
+        let single = store.load_entity(entity.id).unwrap().unwrap();
+        assert_eq!(single.slug.as_deref(), Some("goblin-king"));
+        assert_eq!(single.ai, updated.ai);
+        assert!(store.load_entity(uuid::Uuid::new_v4()).unwrap().is_none());
 
         // Delete
         store.delete_entity(entity.id).unwrap();
@@ -95,15 +202,116 @@ mod tests {
         assert_eq!(loaded[0].id, entity2.id);
     }
 
+    /// Test that exercises slug uniqueness among entities.
+    fn test_unique_slugs(store: &dyn ContentStore) {
+        let mut archer = EntityDef::new("Goblin", 30);
+        archer.slug = Some("goblin-archer".to_string());
+        store.save_entity(&archer).unwrap();
+        // Saving again under the same slug is fine
+        archer.health = 40;
+        store.save_entity(&archer).unwrap();
+
+        let mut copycat = EntityDef::new("Goblin", 60);
+        copycat.slug = archer.slug.clone();
+        assert!(matches!(
+            store.save_entity(&copycat),
+            Err(StorageError::SlugTaken(slug)) if slug == "goblin-archer"
+        ));
+        let loaded = store.load_entities().unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].health, 40);
+
+        // Any number of entities can go without one
+        copycat.slug = None;
+        store.save_entity(&copycat).unwrap();
+        store.save_entity(&EntityDef::new("Orc", 80)).unwrap();
+        assert_eq!(store.load_entities().unwrap().len(), 3);
+
+        // Slugs are freed by renaming or deleting their entity
+        archer.slug = Some("goblin-sniper".to_string());
+        store.save_entity(&archer).unwrap();
+        copycat.slug = Some("goblin-archer".to_string());
+        store.save_entity(&copycat).unwrap();
+        store.delete_entity(archer.id).unwrap();
+        let mut sniper = EntityDef::new("Goblin", 20);
+        sniper.slug = Some("goblin-sniper".to_string());
+        store.save_entity(&sniper).unwrap();
+    }
+
+    /// Test that exercises the typed content contract for one kind.
+    fn test_content_roundtrip(store: &dyn ContentStore) {
+        use roguebench_core::{ItemDef, Rarity};
+
+        assert!(store.load_all::<ItemDef>().unwrap().is_empty());
+
+        let mut potion = ItemDef::new("Potion", 10);
+        potion.tags.push("consumable".to_string());
+        store.save(&potion).unwrap();
+        store.save(&ItemDef::new("Sword", 1)).unwrap();
+
+        let loaded = store.load_all::<ItemDef>().unwrap();
+        assert_eq!(loaded.len(), 2);
+
+        // Update existing
+        potion.rarity = Rarity::Rare;
+        store.save(&potion).unwrap();
+        let loaded = store.load_all::<ItemDef>().unwrap();
+        assert_eq!(loaded.len(), 2);
+        let stored = loaded.iter().find(|i| i.id == potion.id).unwrap();
+        assert_eq!(stored.rarity, Rarity::Rare);
+        assert_eq!(stored.tags, vec!["consumable".to_string()]);
+        assert_eq!(
+            store.load::<ItemDef>(potion.id).unwrap().unwrap().rarity,
+            Rarity::Rare
+        );
+
+        // Kinds are isolated from each other
+        assert!(store.load_documents("other").unwrap().is_empty());
+
+        // Delete
+        store.delete::<ItemDef>(potion.id).unwrap();
+        assert_eq!(store.load_all::<ItemDef>().unwrap().len(), 1);
+        assert!(store.load::<ItemDef>(potion.id).unwrap().is_none());
+        assert!(matches!(
+            store.delete::<ItemDef>(potion.id),
+            Err(StorageError::NotFound(_))
+        ));
+    }
+
+    /// Test that exercises the profile contract.
+    fn test_profile_roundtrip(store: &dyn ContentStore) {
+        let player = uuid::Uuid::new_v4();
+
+        // Unknown players get a fresh profile
+        let mut profile = store.load_profile(player).unwrap();
+        assert_eq!(profile, PlayerProfile::new(player));
+
+        profile.stats.runs = 3;
+        profile.unlocked.insert(uuid::Uuid::new_v4());
+        profile.currencies.insert(uuid::Uuid::new_v4(), 120);
+        store.save_profile(&profile).unwrap();
+        assert_eq!(store.load_profile(player).unwrap(), profile);
+
+        // Other players are unaffected
+        let other = uuid::Uuid::new_v4();
+        assert_eq!(store.load_profile(other).unwrap().stats.runs, 0);
+    }
+
     #[test]
     fn memory_store_roundtrip() {
         let store = MemoryStore::new();
         test_roundtrip(&store);
+        test_content_roundtrip(&store);
+        test_profile_roundtrip(&store);
+        test_unique_slugs(&MemoryStore::new());
     }
 
     #[test]
     fn sqlite_store_roundtrip() {
         let store = SqliteStore::open_in_memory().unwrap();
         test_roundtrip(&store);
+        test_content_roundtrip(&store);
+        test_profile_roundtrip(&store);
+        test_unique_slugs(&SqliteStore::open_in_memory().unwrap());
     }
 }
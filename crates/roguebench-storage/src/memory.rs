@@ -14,6 +14,7 @@ use roguebench_core::EntityDef;
 /// Not suitable for production - data is lost when dropped.
 pub struct MemoryStore {
     entities: Mutex<HashMap<Uuid, EntityDef>>,
+    documents: Mutex<HashMap<(String, Uuid), String>>,
 }
 
 impl MemoryStore {
@@ -21,6 +22,7 @@ impl MemoryStore {
     pub fn new() -> Self {
         Self {
             entities: Mutex::new(HashMap::new()),
+            documents: Mutex::new(HashMap::new()),
         }
     }
 }
@@ -37,8 +39,20 @@ impl ContentStore for MemoryStore {
         Ok(entities.values().cloned().collect())
     }
 
+    fn load_entity(&self, id: Uuid) -> Result<Option<EntityDef>> {
+        let entities = self.entities.lock().unwrap();
+        Ok(entities.get(&id).cloned())
+    }
+
     fn save_entity(&self, entity: &EntityDef) -> Result<()> {
         let mut entities = self.entities.lock().unwrap();
+        if let Some(slug) = &entity.slug
+            && entities
+                .values()
+                .any(|other| other.id != entity.id && other.slug.as_ref() == Some(slug))
+        {
+            return Err(StorageError::SlugTaken(slug.clone()));
+        }
         entities.insert(entity.id, entity.clone());
         Ok(())
     }
@@ -50,4 +64,32 @@ impl ContentStore for MemoryStore {
         }
         Ok(())
     }
+
+    fn load_documents(&self, kind: &str) -> Result<Vec<String>> {
+        let documents = self.documents.lock().unwrap();
+        Ok(documents
+            .iter()
+            .filter(|((k, _), _)| k == kind)
+            .map(|(_, data)| data.clone())
+            .collect())
+    }
+
+    fn load_document(&self, kind: &str, id: Uuid) -> Result<Option<String>> {
+        let documents = self.documents.lock().unwrap();
+        Ok(documents.get(&(kind.to_string(), id)).cloned())
+    }
+
+    fn save_document(&self, kind: &str, id: Uuid, data: &str) -> Result<()> {
+        let mut documents = self.documents.lock().unwrap();
+        documents.insert((kind.to_string(), id), data.to_string());
+        Ok(())
+    }
+
+    fn delete_document(&self, kind: &str, id: Uuid) -> Result<()> {
+        let mut documents = self.documents.lock().unwrap();
+        if documents.remove(&(kind.to_string(), id)).is_none() {
+            return Err(StorageError::NotFound(id.to_string()));
+        }
+        Ok(())
+    }
 }
@@ -52,47 +52,172 @@ impl SqliteStore {
             [],
         )?;
         // Migration: add health column if it doesn't exist (for existing databases)
-        let _ = conn.execute("ALTER TABLE entities ADD COLUMN health INTEGER NOT NULL DEFAULT 100", []);
+        let _ = conn.execute(
+            "ALTER TABLE entities ADD COLUMN health INTEGER NOT NULL DEFAULT 100",
+            [],
+        );
+        let _ = conn.execute("ALTER TABLE entities ADD COLUMN dialogue_id TEXT", []);
+        let _ = conn.execute("ALTER TABLE entities ADD COLUMN weapon_id TEXT", []);
+        let _ = conn.execute("ALTER TABLE entities ADD COLUMN ai TEXT", []);
+        let _ = conn.execute("ALTER TABLE entities ADD COLUMN faction_id TEXT", []);
+        let _ = conn.execute("ALTER TABLE entities ADD COLUMN shop_id TEXT", []);
+        let _ = conn.execute("ALTER TABLE entities ADD COLUMN slug TEXT", []);
+        // Entities without a slug are NULL, which never collides
+        conn.execute(
+            "CREATE UNIQUE INDEX IF NOT EXISTS entities_slug ON entities (slug)",
+            [],
+        )?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS content (
+                kind TEXT NOT NULL,
+                id TEXT NOT NULL,
+                data TEXT NOT NULL,
+                PRIMARY KEY (kind, id)
+            )",
+            [],
+        )?;
         Ok(())
     }
 }
 
+fn parse_uuid(value: &str) -> Result<uuid::Uuid> {
+    value
+        .parse()
+        .map_err(|_| StorageError::InvalidUuid(value.to_string()))
+}
+
+/// Columns of an entity row, in the order [`read_entity_row`] reads them.
+const ENTITY_COLUMNS: &str =
+    "id, name, health, dialogue_id, weapon_id, ai, faction_id, shop_id, slug";
+
+/// An entity row as stored, before its IDs and AI profile are parsed.
+type EntityRow = (
+    String,
+    String,
+    i32,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+);
+
+fn read_entity_row(row: &rusqlite::Row) -> rusqlite::Result<EntityRow> {
+    Ok((
+        row.get(0)?,
+        row.get(1)?,
+        row.get(2)?,
+        row.get(3)?,
+        row.get(4)?,
+        // AI profiles are stored as JSON
+        row.get(5)?,
+        row.get(6)?,
+        row.get(7)?,
+        row.get(8)?,
+    ))
+}
+
+fn parse_entity(row: EntityRow) -> Result<EntityDef> {
+    let (id_str, name, health, dialogue_str, weapon_str, ai_json, faction_str, shop_str, slug) =
+        row;
+    Ok(EntityDef {
+        id: parse_uuid(&id_str)?,
+        slug,
+        name,
+        health,
+        dialogue: dialogue_str.as_deref().map(parse_uuid).transpose()?,
+        ai: ai_json.as_deref().map(serde_json::from_str).transpose()?,
+        weapon: weapon_str.as_deref().map(parse_uuid).transpose()?,
+        faction: faction_str.as_deref().map(parse_uuid).transpose()?,
+        shop: shop_str.as_deref().map(parse_uuid).transpose()?,
+    })
+}
+
 impl ContentStore for SqliteStore {
     fn load_entities(&self) -> Result<Vec<EntityDef>> {
         let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare("SELECT id, name, health FROM entities")?;
-
-        let mut entities = Vec::new();
-        let rows = stmt.query_map([], |row| {
-            let id: String = row.get(0)?;
-            let name: String = row.get(1)?;
-            let health: i32 = row.get(2)?;
-            Ok((id, name, health))
+        let mut stmt = conn.prepare(&format!("SELECT {ENTITY_COLUMNS} FROM entities"))?;
+        let rows = stmt.query_map([], read_entity_row)?;
+        rows.map(|row| parse_entity(row?)).collect()
+    }
+
+    fn load_entity(&self, id: uuid::Uuid) -> Result<Option<EntityDef>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {ENTITY_COLUMNS} FROM entities WHERE id = ?1"
+        ))?;
+        let mut rows = stmt.query_map([&id.to_string()], read_entity_row)?;
+        rows.next().map(|row| parse_entity(row?)).transpose()
+    }
+
+    fn save_entity(&self, entity: &EntityDef) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        // An upsert rather than INSERT OR REPLACE, which would delete the
+        // entity holding the slug instead of failing
+        conn.execute(
+            "INSERT INTO entities (id, name, health, dialogue_id, weapon_id, ai, faction_id, shop_id, slug) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+             ON CONFLICT (id) DO UPDATE SET name = excluded.name, health = excluded.health, dialogue_id = excluded.dialogue_id, weapon_id = excluded.weapon_id, ai = excluded.ai, faction_id = excluded.faction_id, shop_id = excluded.shop_id, slug = excluded.slug",
+            rusqlite::params![
+                &entity.id.to_string(),
+                &entity.name,
+                entity.health,
+                entity.dialogue.map(|id| id.to_string()),
+                entity.weapon.map(|id| id.to_string()),
+                entity.ai.as_ref().map(serde_json::to_string).transpose()?,
+                entity.faction.map(|id| id.to_string()),
+                entity.shop.map(|id| id.to_string()),
+                &entity.slug
+            ],
+        )
+        .map_err(|e| match (e.sqlite_error_code(), &entity.slug) {
+            (Some(rusqlite::ErrorCode::ConstraintViolation), Some(slug)) => {
+                StorageError::SlugTaken(slug.clone())
+            }
+            _ => e.into(),
         })?;
+        Ok(())
+    }
 
-        for row_result in rows {
-            let (id_str, name, health) = row_result?;
-            let id = id_str
-                .parse()
-                .map_err(|_| StorageError::InvalidUuid(id_str.clone()))?;
-            entities.push(EntityDef { id, name, health });
+    fn delete_entity(&self, id: uuid::Uuid) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let rows = conn.execute("DELETE FROM entities WHERE id = ?1", [&id.to_string()])?;
+        if rows == 0 {
+            return Err(StorageError::NotFound(id.to_string()));
         }
+        Ok(())
+    }
 
-        Ok(entities)
+    fn load_documents(&self, kind: &str) -> Result<Vec<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT data FROM content WHERE kind = ?1")?;
+        let rows = stmt.query_map([kind], |row| row.get(0))?;
+        Ok(rows.collect::<rusqlite::Result<Vec<String>>>()?)
     }
 
-    fn save_entity(&self, entity: &EntityDef) -> Result<()> {
+    fn load_document(&self, kind: &str, id: uuid::Uuid) -> Result<Option<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT data FROM content WHERE kind = ?1 AND id = ?2")?;
+        let mut rows =
+            stmt.query_map(rusqlite::params![kind, &id.to_string()], |row| row.get(0))?;
+        Ok(rows.next().transpose()?)
+    }
+
+    fn save_document(&self, kind: &str, id: uuid::Uuid, data: &str) -> Result<()> {
         let conn = self.conn.lock().unwrap();
         conn.execute(
-            "INSERT OR REPLACE INTO entities (id, name, health) VALUES (?1, ?2, ?3)",
-            rusqlite::params![&entity.id.to_string(), &entity.name, entity.health],
+            "INSERT OR REPLACE INTO content (kind, id, data) VALUES (?1, ?2, ?3)",
+            rusqlite::params![kind, &id.to_string(), data],
         )?;
         Ok(())
     }
 
-    fn delete_entity(&self, id: uuid::Uuid) -> Result<()> {
+    fn delete_document(&self, kind: &str, id: uuid::Uuid) -> Result<()> {
         let conn = self.conn.lock().unwrap();
-        let rows = conn.execute("DELETE FROM entities WHERE id = ?1", [&id.to_string()])?;
+        let rows = conn.execute(
+            "DELETE FROM content WHERE kind = ?1 AND id = ?2",
+            rusqlite::params![kind, &id.to_string()],
+        )?;
         if rows == 0 {
             return Err(StorageError::NotFound(id.to_string()));
         }
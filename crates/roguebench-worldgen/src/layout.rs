@@ -0,0 +1,175 @@
+//! Connectivity checks and exit/spawn placement on finished layouts.
+
+use std::collections::VecDeque;
+
+use rand::Rng;
+use roguebench_core::{ContentDef, Direction, RoomDef, RoomExit, SpawnPoint, TileKind, grid_index};
+
+use crate::RoomRequest;
+use crate::wfc::neighbour;
+
+/// Tag of the spawn point players enter the room at.
+pub const PLAYER_SPAWN_TAG: &str = "player";
+
+/// Tag of the spawn points enemies are placed at.
+pub const ENEMY_SPAWN_TAG: &str = "enemy";
+
+/// The exit cell in the middle of a room's side.
+pub(crate) fn exit_cell(width: u32, height: u32, direction: Direction) -> (u32, u32) {
+    match direction {
+        Direction::North => (width / 2, height - 1),
+        Direction::East => (width - 1, height / 2),
+        Direction::South => (width / 2, 0),
+        Direction::West => (0, height / 2),
+    }
+}
+
+/// The cell just inside an exit.
+pub(crate) fn entry_cell(width: u32, height: u32, direction: Direction) -> (u32, u32) {
+    let (x, y) = exit_cell(width, height, direction);
+    match direction {
+        Direction::North => (x, y - 1),
+        Direction::East => (x - 1, y),
+        Direction::South => (x, y + 1),
+        Direction::West => (x + 1, y),
+    }
+}
+
+/// Walking distance from the nearest source to every cell; `None` where unreachable.
+pub fn distances(room: &RoomDef, sources: &[(u32, u32)]) -> Vec<Option<u32>> {
+    let mut distances = vec![None; room.tiles.len()];
+    let mut queue = VecDeque::new();
+    for &(x, y) in sources {
+        if room.tile(x, y).is_some_and(TileKind::is_walkable) {
+            let index = grid_index(room.width, x, y);
+            distances[index] = Some(0);
+            queue.push_back(index);
+        }
+    }
+
+    while let Some(index) = queue.pop_front() {
+        let distance = distances[index].unwrap_or_default();
+        for direction in Direction::ALL {
+            let Some(next) = neighbour(room.width, room.height, index, direction) else {
+                continue;
+            };
+            if distances[next].is_none() && room.tiles[next].is_walkable() {
+                distances[next] = Some(distance + 1);
+                queue.push_back(next);
+            }
+        }
+    }
+    distances
+}
+
+/// Describe anything that makes a room unplayable.
+///
+/// A playable room is enclosed apart from its exits, has a player spawn, and
+/// every exit and spawn point can be walked to from the player spawn.
+pub fn playability_issues(room: &RoomDef) -> Vec<String> {
+    let mut issues = room.validate();
+    if !issues.is_empty() {
+        return issues;
+    }
+
+    let Some(start) = room
+        .spawn_points
+        .iter()
+        .find(|spawn| spawn.tag == PLAYER_SPAWN_TAG)
+    else {
+        issues.push("Room has no player spawn point".to_string());
+        return issues;
+    };
+    let reachable = distances(room, &[(start.x, start.y)]);
+    let is_reachable = |x: u32, y: u32| reachable[grid_index(room.width, x, y)].is_some();
+
+    if room.exits.is_empty() {
+        issues.push("Room has no exits".to_string());
+    }
+    for exit in &room.exits {
+        if !is_reachable(exit.x, exit.y) {
+            issues.push(format!("Exit ({}, {}) is unreachable", exit.x, exit.y));
+        }
+    }
+    for spawn in &room.spawn_points {
+        if !is_reachable(spawn.x, spawn.y) {
+            issues.push(format!(
+                "Spawn point ({}, {}) is unreachable",
+                spawn.x, spawn.y
+            ));
+        }
+    }
+
+    for y in 0..room.height {
+        for x in 0..room.width {
+            let on_border = x == 0 || y == 0 || x + 1 == room.width || y + 1 == room.height;
+            let is_exit = room.exits.iter().any(|exit| (exit.x, exit.y) == (x, y));
+            if on_border && !is_exit && room.tile(x, y).is_some_and(TileKind::is_walkable) {
+                issues.push(format!("Border tile ({x}, {y}) is open"));
+            }
+        }
+    }
+    issues
+}
+
+/// Add exits and spawn points to a generated layout.
+///
+/// Returns `false` if the layout can't satisfy the request's constraints.
+pub(crate) fn place_exits_and_spawns(
+    room: &mut RoomDef,
+    request: &RoomRequest,
+    rng: &mut impl Rng,
+) -> bool {
+    room.exits = request
+        .exits
+        .iter()
+        .map(|&direction| {
+            let (x, y) = exit_cell(room.width, room.height, direction);
+            RoomExit { x, y, direction }
+        })
+        .collect();
+
+    let Some(&entrance) = request.exits.first() else {
+        return false;
+    };
+    let (x, y) = entry_cell(room.width, room.height, entrance);
+    room.spawn_points = vec![SpawnPoint {
+        x,
+        y,
+        tag: PLAYER_SPAWN_TAG.to_string(),
+    }];
+
+    let reachable = distances(room, &[(x, y)]);
+    let exits: Vec<_> = room.exits.iter().map(|exit| (exit.x, exit.y)).collect();
+    if exits
+        .iter()
+        .any(|&(x, y)| reachable[grid_index(room.width, x, y)].is_none())
+    {
+        return false;
+    }
+
+    // Enemies spawn on reachable floor, away from every exit
+    let from_exits = distances(room, &exits);
+    let mut candidates: Vec<usize> = (0..room.tiles.len())
+        .filter(|&index| {
+            room.tiles[index] == TileKind::Floor
+                && reachable[index].is_some()
+                && from_exits[index].is_some_and(|d| d >= request.min_exit_distance)
+        })
+        .collect();
+    let count = request.enemy_spawns as usize;
+    if candidates.len() < count {
+        return false;
+    }
+    for i in 0..count {
+        let pick = rng.gen_range(i..candidates.len());
+        candidates.swap(i, pick);
+        let index = candidates[i] as u32;
+        room.spawn_points.push(SpawnPoint {
+            x: index % room.width,
+            y: index / room.width,
+            tag: ENEMY_SPAWN_TAG.to_string(),
+        });
+    }
+    true
+}
@@ -0,0 +1,315 @@
+//! Procedural room generation for roguebench.
+//!
+//! Builds [`RoomDef`]s from a [`TilesetDef`] using wave function collapse,
+//! then places exits and spawn points. Generation is deterministic per seed.
+
+mod layout;
+mod wfc;
+
+pub use layout::{ENEMY_SPAWN_TAG, PLAYER_SPAWN_TAG, distances, playability_issues};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use roguebench_core::{ContentDef, Direction, RoomDef, TileKind, TilesetDef, grid_index};
+use thiserror::Error;
+use uuid::Builder;
+
+/// Number of layouts tried before giving up on a request.
+pub const MAX_ATTEMPTS: u32 = 100;
+
+/// Errors that can occur while generating a room.
+#[derive(Debug, Error)]
+pub enum GenerationError {
+    #[error("Invalid room request: {0}")]
+    InvalidRequest(String),
+
+    #[error("Invalid tileset: {0}")]
+    InvalidTileset(String),
+
+    #[error("No playable layout found in {0} attempts")]
+    Exhausted(u32),
+}
+
+/// What kind of room to generate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoomRequest {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    /// Sides with an exit, in the middle of the side. The first is the
+    /// entrance, where the player spawn point is placed.
+    pub exits: Vec<Direction>,
+    /// Number of enemy spawn points to place.
+    pub enemy_spawns: u32,
+    /// Minimum walking distance between an enemy spawn point and any exit.
+    pub min_exit_distance: u32,
+}
+
+impl RoomRequest {
+    pub fn new(width: u32, height: u32, exits: Vec<Direction>) -> Self {
+        Self {
+            name: "Generated room".to_string(),
+            width,
+            height,
+            exits,
+            enemy_spawns: 3,
+            min_exit_distance: 3,
+        }
+    }
+
+    fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.width < 5 || self.height < 5 {
+            problems.push("Rooms must be at least 5x5".to_string());
+        }
+        if self.width > RoomDef::MAX_SIDE || self.height > RoomDef::MAX_SIDE {
+            problems.push(format!("Rooms can be at most {0}x{0}", RoomDef::MAX_SIDE));
+        }
+        if self.exits.is_empty() {
+            problems.push("Rooms need at least one exit".to_string());
+        }
+        for (index, exit) in self.exits.iter().enumerate() {
+            if self.exits[..index].contains(exit) {
+                problems.push(format!("Duplicate {exit:?} exit"));
+            }
+        }
+        if self.min_exit_distance < 2 {
+            problems.push("Enemy spawns must be at least 2 tiles from exits".to_string());
+        }
+        problems
+    }
+}
+
+/// Generate a playable room from a tileset.
+///
+/// The same tileset, request and seed always produce the same room.
+pub fn generate_room(
+    tileset: &TilesetDef,
+    request: &RoomRequest,
+    seed: u64,
+) -> Result<RoomDef, GenerationError> {
+    let problems = request.problems();
+    if !problems.is_empty() {
+        return Err(GenerationError::InvalidRequest(problems.join("; ")));
+    }
+    let problems = tileset.validate();
+    if !problems.is_empty() {
+        return Err(GenerationError::InvalidTileset(problems.join("; ")));
+    }
+
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let rules = wfc::Rules::new(tileset);
+    let initial = initial_cells(tileset, request);
+
+    for _ in 0..MAX_ATTEMPTS {
+        let Some(chosen) = wfc::collapse(
+            &rules,
+            request.width,
+            request.height,
+            initial.clone(),
+            &mut rng,
+        ) else {
+            continue;
+        };
+
+        let mut room = RoomDef::new(request.name.clone(), request.width, request.height);
+        room.id = Builder::from_random_bytes(rng.r#gen()).into_uuid();
+        room.tiles = chosen
+            .into_iter()
+            .map(|variant| tileset.variants[variant].kind)
+            .collect();
+
+        if layout::place_exits_and_spawns(&mut room, request, &mut rng)
+            && playability_issues(&room).is_empty()
+        {
+            return Ok(room);
+        }
+    }
+    Err(GenerationError::Exhausted(MAX_ATTEMPTS))
+}
+
+/// Options per cell before collapsing: walls around the border, floor at
+/// the exits and the cells just inside them, anything elsewhere.
+fn initial_cells(tileset: &TilesetDef, request: &RoomRequest) -> Vec<u64> {
+    let mask_of = |kind: TileKind| {
+        tileset
+            .variants
+            .iter()
+            .enumerate()
+            .filter(|(_, variant)| variant.kind == kind)
+            .fold(0u64, |mask, (index, _)| mask | 1 << index)
+    };
+    let any = mask_of(TileKind::Floor)
+        | mask_of(TileKind::Wall)
+        | mask_of(TileKind::Pit)
+        | mask_of(TileKind::Hazard);
+
+    let (width, height) = (request.width, request.height);
+    let mut cells = vec![any; width as usize * height as usize];
+    for y in 0..height {
+        for x in 0..width {
+            if x == 0 || y == 0 || x + 1 == width || y + 1 == height {
+                cells[grid_index(width, x, y)] = mask_of(TileKind::Wall);
+            }
+        }
+    }
+    for &direction in &request.exits {
+        for (x, y) in [
+            layout::exit_cell(width, height, direction),
+            layout::entry_cell(width, height, direction),
+        ] {
+            cells[grid_index(width, x, y)] = mask_of(TileKind::Floor);
+        }
+    }
+    cells
+}
+
+pub mod prelude {
+    pub use crate::{
+        ENEMY_SPAWN_TAG, GenerationError, PLAYER_SPAWN_TAG, RoomRequest, generate_room,
+        playability_issues,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use roguebench_core::{Sockets, TileVariant};
+
+    /// Pits and hazards only ever border floor (or themselves); walls and
+    /// floor mix freely.
+    fn dungeon_tileset() -> TilesetDef {
+        let mut floor = TileVariant::new("floor", TileKind::Floor, Sockets::uniform("open"));
+        floor.weight = 8.0;
+        let mut wall = TileVariant::new("wall", TileKind::Wall, Sockets::uniform("solid"));
+        wall.weight = 2.0;
+        let pit = TileVariant::new("pit", TileKind::Pit, Sockets::uniform("pit"));
+        let mut hazard = TileVariant::new("lava", TileKind::Hazard, Sockets::uniform("hot"));
+        hazard.weight = 0.5;
+
+        let mut tileset = TilesetDef::new("Dungeon", vec![floor, wall, pit, hazard]);
+        tileset.connections = vec![
+            ["open".to_string(), "solid".to_string()],
+            ["open".to_string(), "pit".to_string()],
+            ["open".to_string(), "hot".to_string()],
+        ];
+        tileset
+    }
+
+    fn requests() -> Vec<RoomRequest> {
+        vec![
+            RoomRequest::new(9, 9, vec![Direction::South, Direction::North]),
+            RoomRequest::new(
+                15,
+                11,
+                vec![Direction::West, Direction::East, Direction::North],
+            ),
+            RoomRequest::new(7, 12, vec![Direction::South]),
+        ]
+    }
+
+    #[test]
+    fn generated_rooms_are_connected_and_playable() {
+        let tileset = dungeon_tileset();
+        for request in requests() {
+            for seed in 0..40 {
+                let room = generate_room(&tileset, &request, seed).unwrap();
+                assert_eq!(playability_issues(&room), Vec::<String>::new());
+                assert_eq!(room.exits.len(), request.exits.len());
+
+                let enemies: Vec<_> = room
+                    .spawn_points
+                    .iter()
+                    .filter(|spawn| spawn.tag == ENEMY_SPAWN_TAG)
+                    .collect();
+                assert_eq!(enemies.len(), request.enemy_spawns as usize);
+
+                // Enemy spawns keep their distance from the exits
+                let exits: Vec<_> = room.exits.iter().map(|e| (e.x, e.y)).collect();
+                let from_exits = distances(&room, &exits);
+                for spawn in enemies {
+                    let distance = from_exits[grid_index(room.width, spawn.x, spawn.y)];
+                    assert!(distance.unwrap() >= request.min_exit_distance);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn generation_respects_adjacency_rules() {
+        let tileset = dungeon_tileset();
+        let request = RoomRequest::new(12, 12, vec![Direction::East]);
+        for seed in 0..40 {
+            let room = generate_room(&tileset, &request, seed).unwrap();
+            for y in 0..room.height {
+                for x in 0..room.width {
+                    let kind = room.tile(x, y).unwrap();
+                    if !matches!(kind, TileKind::Pit | TileKind::Hazard) {
+                        continue;
+                    }
+                    let neighbours = [
+                        (x + 1, y),
+                        (x.wrapping_sub(1), y),
+                        (x, y + 1),
+                        (x, y.wrapping_sub(1)),
+                    ];
+                    for (nx, ny) in neighbours {
+                        if let Some(next) = room.tile(nx, ny) {
+                            assert!(next == kind || next == TileKind::Floor);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn generation_is_deterministic_per_seed() {
+        let tileset = dungeon_tileset();
+        let request = &requests()[1];
+
+        let first = generate_room(&tileset, request, 99).unwrap();
+        assert_eq!(first, generate_room(&tileset, request, 99).unwrap());
+
+        let other = generate_room(&tileset, request, 100).unwrap();
+        assert_ne!(first.tiles, other.tiles);
+    }
+
+    #[test]
+    fn invalid_requests_are_rejected() {
+        let tileset = dungeon_tileset();
+        let request = RoomRequest::new(9, 9, Vec::new());
+        assert!(matches!(
+            generate_room(&tileset, &request, 0),
+            Err(GenerationError::InvalidRequest(_))
+        ));
+
+        let request = RoomRequest::new(65536, 65536, vec![Direction::North]);
+        assert!(matches!(
+            generate_room(&tileset, &request, 0),
+            Err(GenerationError::InvalidRequest(_))
+        ));
+
+        let mut heavy = tileset.clone();
+        heavy.variants[0].weight = f32::MAX;
+        let request = RoomRequest::new(9, 9, vec![Direction::North]);
+        assert!(matches!(
+            generate_room(&heavy, &request, 0),
+            Err(GenerationError::InvalidTileset(_))
+        ));
+
+        // Unvalidated weights are a contradiction, not a panic
+        heavy.variants[1].weight = f32::MAX;
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let cells = initial_cells(&heavy, &request);
+        assert!(wfc::collapse(&wfc::Rules::new(&heavy), 9, 9, cells, &mut rng).is_none());
+
+        let mut walls_only = tileset.clone();
+        walls_only.variants.retain(|v| v.kind == TileKind::Wall);
+        let request = RoomRequest::new(9, 9, vec![Direction::North]);
+        assert!(matches!(
+            generate_room(&walls_only, &request, 0),
+            Err(GenerationError::InvalidTileset(_))
+        ));
+    }
+}
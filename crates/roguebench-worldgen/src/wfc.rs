@@ -0,0 +1,158 @@
+//! Wave function collapse over a grid of tile variants.
+
+use rand::Rng;
+use roguebench_core::{Direction, TilesetDef, grid_index};
+
+/// Adjacency rules derived from a tileset's sockets.
+///
+/// Cell options are bitmasks over the tileset's variants, which is why a
+/// tileset is limited to [`MAX_TILE_VARIANTS`](roguebench_core::MAX_TILE_VARIANTS).
+pub(crate) struct Rules {
+    /// `allowed[direction][a]` is the mask of variants that may sit on that side of `a`.
+    allowed: [Vec<u64>; 4],
+    weights: Vec<f32>,
+}
+
+impl Rules {
+    pub(crate) fn new(tileset: &TilesetDef) -> Self {
+        let allowed = Direction::ALL.map(|direction| {
+            tileset
+                .variants
+                .iter()
+                .map(|a| {
+                    tileset
+                        .variants
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, b)| {
+                            tileset.connects(
+                                a.sockets.side(direction),
+                                b.sockets.side(direction.opposite()),
+                            )
+                        })
+                        .fold(0, |mask, (index, _)| mask | 1 << index)
+                })
+                .collect()
+        });
+        Self {
+            allowed,
+            weights: tileset.variants.iter().map(|v| v.weight).collect(),
+        }
+    }
+}
+
+/// The cell next to `index` in the given direction, if inside the grid.
+pub(crate) fn neighbour(
+    width: u32,
+    height: u32,
+    index: usize,
+    direction: Direction,
+) -> Option<usize> {
+    let (x, y) = ((index as u32) % width, (index as u32) / width);
+    let (x, y) = match direction {
+        Direction::North => (x, y.checked_add(1).filter(|y| *y < height)?),
+        Direction::East => (x.checked_add(1).filter(|x| *x < width)?, y),
+        Direction::South => (x, y.checked_sub(1)?),
+        Direction::West => (x.checked_sub(1)?, y),
+    };
+    Some(grid_index(width, x, y))
+}
+
+fn variants(mut mask: u64) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        (mask != 0).then(|| {
+            let index = mask.trailing_zeros() as usize;
+            mask &= mask - 1;
+            index
+        })
+    })
+}
+
+/// Remove options that no longer have a compatible neighbour, starting from `stack`.
+///
+/// Returns `false` if a cell runs out of options.
+fn propagate(
+    rules: &Rules,
+    width: u32,
+    height: u32,
+    cells: &mut [u64],
+    mut stack: Vec<usize>,
+) -> bool {
+    while let Some(index) = stack.pop() {
+        for direction in Direction::ALL {
+            let Some(next) = neighbour(width, height, index, direction) else {
+                continue;
+            };
+            let supported = variants(cells[index])
+                .fold(0, |mask, a| mask | rules.allowed[direction as usize][a]);
+            let narrowed = cells[next] & supported;
+            if narrowed != cells[next] {
+                if narrowed == 0 {
+                    return false;
+                }
+                cells[next] = narrowed;
+                stack.push(next);
+            }
+        }
+    }
+    true
+}
+
+/// Collapse every cell to a single variant, starting from the given options.
+///
+/// Returns the chosen variant per cell, or `None` on a contradiction.
+pub(crate) fn collapse(
+    rules: &Rules,
+    width: u32,
+    height: u32,
+    mut cells: Vec<u64>,
+    rng: &mut impl Rng,
+) -> Option<Vec<usize>> {
+    if cells.contains(&0) {
+        return None;
+    }
+    let everything = (0..cells.len()).collect();
+    if !propagate(rules, width, height, &mut cells, everything) {
+        return None;
+    }
+
+    // Observe one of the undecided cells with the fewest options left
+    while let Some(fewest) = cells
+        .iter()
+        .map(|cell| cell.count_ones())
+        .filter(|count| *count > 1)
+        .min()
+    {
+        let candidates: Vec<usize> = (0..cells.len())
+            .filter(|index| cells[*index].count_ones() == fewest)
+            .collect();
+        let index = candidates[rng.gen_range(0..candidates.len())];
+
+        let total: f32 = variants(cells[index]).map(|v| rules.weights[v]).sum();
+        // Nothing can be picked from a zero or unbounded total
+        if !total.is_finite() || total <= 0.0 {
+            return None;
+        }
+        let mut roll = rng.gen_range(0.0..total);
+        let mut choice = variants(cells[index]).last()?;
+        for variant in variants(cells[index]) {
+            if roll < rules.weights[variant] {
+                choice = variant;
+                break;
+            }
+            roll -= rules.weights[variant];
+        }
+
+        cells[index] = 1 << choice;
+        if !propagate(rules, width, height, &mut cells, vec![index]) {
+            return None;
+        }
+    }
+
+    Some(
+        cells
+            .iter()
+            .map(|cell| cell.trailing_zeros() as usize)
+            .collect(),
+    )
+}